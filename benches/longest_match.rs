@@ -0,0 +1,201 @@
+//! Longest-match and annotation throughput benchmarks.
+//!
+//! By default a synthetic dictionary and a ~3 MB synthetic text are generated.
+//! To benchmark real data, point the environment at a processed dictionary and
+//! a novel:
+//!
+//! ```bash
+//! ZHO_BENCH_DICT=processed_dictionary.json ZHO_BENCH_TEXT=novel.txt cargo bench
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::collections::HashMap;
+use zho_annotator::dictionary::{AnnotationData, Dictionary, ProcessedData, ProcessingStats};
+use zho_annotator::production_annotator::{AnnotationConfig, ProductionAnnotator};
+
+const CHAR_POOL: &str = "的一是不了人我在有他这中大来上个国到说们为子和你地出道也时年得就那要下以生会自着去之过家学对可她里后小么心多天而能好都然没日于起还发成事只作当想看文无开手十用主行方又如前所本见经头面公同三已老从动两长知民样现分将外但身些与高意进把法此实回二理美点月明其种声全工己话儿者向情部正名定女问力机给等几很业最间新什打便位因重被走电四第门相次东政海口使教西再平真听世气信北少关并内加化由却代军产入先山五太水万市眼体别处总才场师书比住员九笑性通目华报立马命张活难神数件安表原车白应路期叫死常提感金何更反合放做系计或司利受光王果亲界及今京务制解各任至清物台象记边共风战干接它许八特觉望直服毛林题建南度统色字请交爱让认算论百吃义科怎元社术结六功指思非流每青管夫连远资队跟带花快条院变联言权往展该领传近留红治决周保达办运武半候七必城父强步完革深区即求品士转量空甚众技轻程告江语英基派满式李息写呢识极令黄德收脸钱党倒未持取设始版双历越史商千片容研究生命起源";
+
+/// Deterministic pseudo-random generator so runs are comparable
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize
+    }
+}
+
+fn annotation(word: &str) -> AnnotationData {
+    AnnotationData {
+        pinyin: "pin yin".to_string(),
+        zhuyin: "ㄆㄧㄣ ㄧㄣ".to_string(),
+        traditional: word.to_string(),
+        simplified: word.to_string(),
+        ..Default::default()
+    }
+}
+
+fn synthetic_dictionary(pool: &[char]) -> (Dictionary, Vec<String>) {
+    let mut rng = Lcg(42);
+    let mut words: HashMap<String, Vec<AnnotationData>> = HashMap::new();
+
+    for ch in pool {
+        let word = ch.to_string();
+        words.insert(word.clone(), vec![annotation(&word)]);
+    }
+    for _ in 0..60_000 {
+        let len = 2 + rng.next() % 4;
+        let word: String = (0..len).map(|_| pool[rng.next() % pool.len()]).collect();
+        words.insert(word.clone(), vec![annotation(&word)]);
+    }
+
+    let vocabulary: Vec<String> = words.keys().cloned().collect();
+    let mut data = ProcessedData {
+        simplified_words: words.clone(),
+        traditional_words: words.clone(),
+        char_lookup: HashMap::new(),
+        simplified_index: Default::default(),
+        traditional_index: Default::default(),
+        stats: ProcessingStats {
+            total_entries: words.len(),
+            unique_simplified_chars: words.len(),
+            unique_traditional_chars: words.len(),
+            max_word_length: 5,
+            multi_char_entries: words.len() - pool.len(),
+            unihan_chars: 0,
+            total_frequency: 0,
+            jyutping_entries: 0,
+            cantonese_chars: 0,
+        },
+    };
+    data.build_indexes();
+
+    (Dictionary::from_data(data), vocabulary)
+}
+
+fn synthetic_text(vocabulary: &[String], target_bytes: usize) -> String {
+    let mut rng = Lcg(7);
+    let mut text = String::with_capacity(target_bytes + 64);
+    while text.len() < target_bytes {
+        text.push_str(&vocabulary[rng.next() % vocabulary.len()]);
+        match rng.next() % 12 {
+            0 => text.push('，'),
+            1 => text.push_str("。\n"),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn load_inputs() -> (Dictionary, String) {
+    let pool: Vec<char> = CHAR_POOL.chars().collect();
+
+    let (dictionary, vocabulary) = match std::env::var("ZHO_BENCH_DICT") {
+        Ok(path) => (
+            Dictionary::from_file(&path).expect("failed to load ZHO_BENCH_DICT"),
+            Vec::new(),
+        ),
+        Err(_) => synthetic_dictionary(&pool),
+    };
+
+    let text = match std::env::var("ZHO_BENCH_TEXT") {
+        Ok(path) => std::fs::read_to_string(&path).expect("failed to read ZHO_BENCH_TEXT"),
+        Err(_) if !vocabulary.is_empty() => synthetic_text(&vocabulary, 3 * 1024 * 1024),
+        Err(_) => {
+            let words: Vec<String> = pool.iter().map(|c| c.to_string()).collect();
+            synthetic_text(&words, 3 * 1024 * 1024)
+        }
+    };
+
+    (dictionary, text)
+}
+
+/// The pre-index algorithm: build a string for every candidate length up to
+/// the end of the input and probe the word table with each
+fn naive_longest_match(dictionary: &Dictionary, chars: &[char], start: usize) -> Option<usize> {
+    let mut longest = None;
+    for len in 1..=chars.len() - start {
+        let word: String = chars[start..start + len].iter().collect();
+        if dictionary.contains_word(&word, false) {
+            longest = Some(len);
+        }
+    }
+    longest
+}
+
+/// Greedy segmentation over the whole buffer, the access pattern of `annotate`
+fn segment_all<F>(chars: &[char], mut longest: F) -> usize
+where
+    F: FnMut(usize) -> Option<usize>,
+{
+    let mut i = 0;
+    let mut segments = 0;
+    while i < chars.len() {
+        i += longest(i).unwrap_or(1);
+        segments += 1;
+    }
+    segments
+}
+
+fn bench_longest_match(c: &mut Criterion) {
+    let (dictionary, text) = load_inputs();
+    let chars: Vec<char> = text.chars().collect();
+
+    let mut group = c.benchmark_group("longest_match");
+    group.sample_size(10);
+
+    // The naive scan builds a string for every length up to the end of the
+    // input, so compare on short prefixes of the text only
+    for size in [250usize, 500, 1_000, 2_000] {
+        let prefix = &chars[..size.min(chars.len())];
+        group.throughput(Throughput::Elements(prefix.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("naive_scan", size), prefix, |b, prefix| {
+            b.iter(|| segment_all(prefix, |i| naive_longest_match(&dictionary, prefix, i)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("prefix_index", size),
+            prefix,
+            |b, prefix| {
+                b.iter(|| {
+                    segment_all(prefix, |i| {
+                        dictionary
+                            .find_longest_match_in(prefix, i, false)
+                            .map(|(len, _)| len)
+                    })
+                })
+            },
+        );
+    }
+
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function(BenchmarkId::new("prefix_index", "full_text"), |b| {
+        b.iter(|| {
+            segment_all(&chars, |i| {
+                dictionary
+                    .find_longest_match_in(&chars, i, false)
+                    .map(|(len, _)| len)
+            })
+        })
+    });
+    group.finish();
+}
+
+fn bench_annotate(c: &mut Criterion) {
+    let (dictionary, text) = load_inputs();
+    let annotator = ProductionAnnotator::with_dictionary(dictionary, AnnotationConfig::default());
+
+    let mut group = c.benchmark_group("annotate");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("full_text", |b| {
+        b.iter(|| annotator.annotate(black_box(&text)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_longest_match, bench_annotate);
+criterion_main!(benches);
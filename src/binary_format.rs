@@ -0,0 +1,773 @@
+//! Compact binary dictionary format, opened with mmap.
+//!
+//! Every string is stored once in a shared pool, every distinct annotation is
+//! stored once as a fixed-size record of string ids, and each word table is a
+//! prefix index whose nodes point at lists of records. Opening a file only
+//! validates the header and section table; nothing is parsed up front, and a
+//! lookup touches only the pages it reads. Ids and offsets are bounds-checked
+//! as they are read, so a damaged file misses words instead of panicking;
+//! `MappedDictionary::verify` (`dict-processor --verify`) checks the checksum
+//! and every id and offset in one pass.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! 0   magic          8 bytes  "ZHODICT\0"
+//! 8   version        u32
+//! 12  checksum       u32      CRC-32 of every byte after the header (checked by `verify`)
+//! 16  body length    u64
+//! 24  section table  (offset u32, length u32) per section, in file order
+//! ..  sections       each starting on a 4-byte boundary
+//! ```
+
+use crate::dictionary::{
+    AnnotationData, PrefixIndex, ProcessedData, ProcessingStats, ReadingSource,
+};
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+pub const MAGIC: &[u8; 8] = b"ZHODICT\0";
+pub const FORMAT_VERSION: u32 = 5;
+
+const HEADER_LEN: usize = 24;
+const NO_VALUE: u32 = u32::MAX;
+
+/// Fields per record: pinyin, zhuyin, traditional and simplified string ids,
+/// the reading source, the id of the record's gloss list, the word frequency,
+/// then the Jyutping string id
+const RECORD_FIELDS: usize = 8;
+
+/// Statistics stored as u64 values, in `ProcessingStats` field order
+const STATS_FIELDS: usize = 9;
+
+const STATS: usize = 0;
+const STRING_OFFSETS: usize = 1;
+const STRING_BYTES: usize = 2;
+const RECORDS: usize = 3;
+const LIST_OFFSETS: usize = 4;
+const LIST_ITEMS: usize = 5;
+const GLOSS_OFFSETS: usize = 6;
+const GLOSS_ITEMS: usize = 7;
+const TABLES_START: usize = 8;
+
+/// Sections per word table: edge offsets, edge labels, edge targets, node values
+const SECTIONS_PER_TABLE: usize = 4;
+const EDGE_OFFSETS: usize = 0;
+const EDGE_LABELS: usize = 1;
+const EDGE_TARGETS: usize = 2;
+const NODE_VALUES: usize = 3;
+
+const TABLE_COUNT: usize = 3;
+const SECTION_COUNT: usize = TABLES_START + TABLE_COUNT * SECTIONS_PER_TABLE;
+
+/// Word tables stored in a binary dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+    Simplified = 0,
+    Traditional = 1,
+    Chars = 2,
+}
+
+impl Table {
+    fn section(self, part: usize) -> usize {
+        TABLES_START + self as usize * SECTIONS_PER_TABLE + part
+    }
+}
+
+/// Check whether a file starts with the binary dictionary magic
+pub fn is_binary_dictionary<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let mut file = File::open(path.as_ref()).with_context(|| {
+        format!(
+            "Failed to open dictionary file: {}",
+            path.as_ref().display()
+        )
+    })?;
+
+    let mut magic = [0u8; 8];
+    match std::io::Read::read_exact(&mut file, &mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Deduplicates strings, annotation records and annotation lists
+#[derive(Default)]
+struct Interner {
+    strings: HashMap<String, u32>,
+    string_offsets: Vec<u32>,
+    string_bytes: Vec<u8>,
+    records: HashMap<[u32; RECORD_FIELDS], u32>,
+    record_fields: Vec<u32>,
+    lists: HashMap<Vec<u32>, u32>,
+    list_offsets: Vec<u32>,
+    list_items: Vec<u32>,
+    glosses: HashMap<Vec<u32>, u32>,
+    gloss_offsets: Vec<u32>,
+    gloss_items: Vec<u32>,
+}
+
+impl Interner {
+    fn new() -> Self {
+        Interner {
+            string_offsets: vec![0],
+            list_offsets: vec![0],
+            gloss_offsets: vec![0],
+            ..Default::default()
+        }
+    }
+
+    fn string(&mut self, s: &str) -> u32 {
+        if let Some(&id) = self.strings.get(s) {
+            return id;
+        }
+        let id = self.strings.len() as u32;
+        self.string_bytes.extend_from_slice(s.as_bytes());
+        self.string_offsets.push(self.string_bytes.len() as u32);
+        self.strings.insert(s.to_string(), id);
+        id
+    }
+
+    fn record(&mut self, annotation: &AnnotationData) -> u32 {
+        let fields = [
+            self.string(&annotation.pinyin),
+            self.string(&annotation.zhuyin),
+            self.string(&annotation.traditional),
+            self.string(&annotation.simplified),
+            source_code(annotation.source),
+            self.glosses(&annotation.definitions),
+            annotation.frequency,
+            self.string(&annotation.jyutping),
+        ];
+        if let Some(&id) = self.records.get(&fields) {
+            return id;
+        }
+        let id = self.records.len() as u32;
+        self.record_fields.extend_from_slice(&fields);
+        self.records.insert(fields, id);
+        id
+    }
+
+    fn glosses(&mut self, definitions: &[String]) -> u32 {
+        let items: Vec<u32> = definitions.iter().map(|d| self.string(d)).collect();
+        if let Some(&id) = self.glosses.get(&items) {
+            return id;
+        }
+        let id = self.glosses.len() as u32;
+        self.gloss_items.extend_from_slice(&items);
+        self.gloss_offsets.push(self.gloss_items.len() as u32);
+        self.glosses.insert(items, id);
+        id
+    }
+
+    fn list(&mut self, annotations: &[AnnotationData]) -> u32 {
+        let items: Vec<u32> = annotations.iter().map(|a| self.record(a)).collect();
+        if let Some(&id) = self.lists.get(&items) {
+            return id;
+        }
+        let id = self.lists.len() as u32;
+        self.list_items.extend_from_slice(&items);
+        self.list_offsets.push(self.list_items.len() as u32);
+        self.lists.insert(items, id);
+        id
+    }
+}
+
+fn u32_section(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn table_sections(
+    words: &HashMap<String, Vec<AnnotationData>>,
+    interner: &mut Interner,
+) -> [Vec<u8>; SECTIONS_PER_TABLE] {
+    let mut keys: Vec<&str> = words.keys().map(String::as_str).collect();
+    // Sorted so the same input always produces the same file
+    keys.sort_unstable();
+
+    let index = PrefixIndex::build(keys.iter().copied());
+    let mut values = vec![NO_VALUE; index.node_count()];
+    for key in keys {
+        if let Some(node) = index.word_node(key) {
+            values[node] = interner.list(&words[key]);
+        }
+    }
+
+    let (offsets, labels, targets) = index.edges();
+    let labels: Vec<u32> = labels.iter().map(|&c| c as u32).collect();
+    [
+        u32_section(offsets),
+        u32_section(&labels),
+        u32_section(targets),
+        u32_section(&values),
+    ]
+}
+
+/// Serialize processed dictionary data in the binary format
+pub fn write<W: Write>(data: &ProcessedData, mut writer: W) -> Result<()> {
+    let mut interner = Interner::new();
+
+    let simplified = table_sections(&data.simplified_words, &mut interner);
+    let traditional = table_sections(&data.traditional_words, &mut interner);
+    let chars = table_sections(&data.char_lookup, &mut interner);
+
+    let stats = [
+        data.stats.total_entries,
+        data.stats.unique_simplified_chars,
+        data.stats.unique_traditional_chars,
+        data.stats.max_word_length,
+        data.stats.multi_char_entries,
+        data.stats.unihan_chars,
+    ];
+    let stats: Vec<u8> = stats
+        .iter()
+        .map(|&v| v as u64)
+        .chain([data.stats.total_frequency])
+        .chain([data.stats.jyutping_entries, data.stats.cantonese_chars].map(|v| v as u64))
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+
+    let mut sections: Vec<Vec<u8>> = vec![
+        stats,
+        u32_section(&interner.string_offsets),
+        interner.string_bytes,
+        u32_section(&interner.record_fields),
+        u32_section(&interner.list_offsets),
+        u32_section(&interner.list_items),
+        u32_section(&interner.gloss_offsets),
+        u32_section(&interner.gloss_items),
+    ];
+    sections.extend(simplified);
+    sections.extend(traditional);
+    sections.extend(chars);
+    debug_assert_eq!(sections.len(), SECTION_COUNT);
+
+    // Section table, then sections padded to 4-byte boundaries
+    let table_len = SECTION_COUNT * 8;
+    let mut body = Vec::new();
+    let mut offset = HEADER_LEN + table_len;
+    for section in &sections {
+        let len = u32::try_from(section.len()).context("Dictionary section exceeds 4 GiB")?;
+        let start = u32::try_from(offset).context("Dictionary file exceeds 4 GiB")?;
+        body.extend_from_slice(&start.to_le_bytes());
+        body.extend_from_slice(&len.to_le_bytes());
+        offset += section.len().next_multiple_of(4);
+    }
+    for section in &sections {
+        body.extend_from_slice(section);
+        body.resize(body.len().next_multiple_of(4), 0);
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    writer.write_all(&crc32fast::hash(&body).to_le_bytes())?;
+    writer.write_all(&(body.len() as u64).to_le_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// A binary dictionary mapped into memory
+pub struct MappedDictionary {
+    mmap: Mmap,
+    sections: [(usize, usize); SECTION_COUNT],
+    stats: ProcessingStats,
+}
+
+impl MappedDictionary {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref()).with_context(|| {
+            format!(
+                "Failed to open dictionary file: {}",
+                path.as_ref().display()
+            )
+        })?;
+
+        // Safety: the mapping is read-only; dictionary files are build
+        // artifacts that are replaced rather than modified in place
+        let mmap = unsafe { Mmap::map(&file) }.context("Failed to map dictionary file")?;
+        Self::from_mmap(mmap)
+            .with_context(|| format!("Invalid binary dictionary: {}", path.as_ref().display()))
+    }
+
+    fn from_mmap(mmap: Mmap) -> Result<Self> {
+        let bytes: &[u8] = &mmap;
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            bail!("missing dictionary header");
+        }
+
+        let version = read_u32(bytes, 8);
+        if version != FORMAT_VERSION {
+            bail!(
+                "unsupported format version {} (expected {}); rebuild it with dict-processor",
+                version,
+                FORMAT_VERSION
+            );
+        }
+
+        let body_len = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+        if bytes.len() - HEADER_LEN != body_len {
+            bail!("file is truncated");
+        }
+        if body_len < SECTION_COUNT * 8 {
+            bail!("section table is truncated");
+        }
+        let mut sections = [(0, 0); SECTION_COUNT];
+        for (i, section) in sections.iter_mut().enumerate() {
+            let start = read_u32(bytes, HEADER_LEN + i * 8) as usize;
+            let len = read_u32(bytes, HEADER_LEN + i * 8 + 4) as usize;
+            if !start.is_multiple_of(4) || start + len > bytes.len() {
+                bail!("section {} is out of bounds", i);
+            }
+            if i != STATS && i != STRING_BYTES && !len.is_multiple_of(4) {
+                bail!("section {} is malformed", i);
+            }
+            *section = (start, len);
+        }
+        if !sections[RECORDS].1.is_multiple_of(RECORD_FIELDS * 4) {
+            bail!("record section is malformed");
+        }
+
+        let (start, len) = sections[STATS];
+        if len != STATS_FIELDS * 8 {
+            bail!("statistics section is malformed");
+        }
+        let stat = |i: usize| {
+            u64::from_le_bytes(bytes[start + i * 8..start + i * 8 + 8].try_into().unwrap())
+        };
+        let stats = ProcessingStats {
+            total_entries: stat(0) as usize,
+            unique_simplified_chars: stat(1) as usize,
+            unique_traditional_chars: stat(2) as usize,
+            max_word_length: stat(3) as usize,
+            multi_char_entries: stat(4) as usize,
+            unihan_chars: stat(5) as usize,
+            total_frequency: stat(6),
+            jyutping_entries: stat(7) as usize,
+            cantonese_chars: stat(8) as usize,
+        };
+
+        Ok(MappedDictionary {
+            mmap,
+            sections,
+            stats,
+        })
+    }
+
+    pub fn stats(&self) -> &ProcessingStats {
+        &self.stats
+    }
+
+    fn section(&self, section: usize) -> &[u8] {
+        let (start, len) = self.sections[section];
+        &self.mmap[start..start + len]
+    }
+
+    /// Number of u32 values in a section
+    fn u32_count(&self, section: usize) -> usize {
+        self.sections[section].1 / 4
+    }
+
+    fn u32_at(&self, section: usize, index: usize) -> Option<u32> {
+        let bytes = self.section(section).get(index * 4..index * 4 + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// The `[start, end)` range of item `index` in an offsets section
+    fn range(&self, offsets: usize, index: usize) -> Option<std::ops::Range<usize>> {
+        let start = self.u32_at(offsets, index)? as usize;
+        let end = self.u32_at(offsets, index + 1)? as usize;
+        (start <= end).then_some(start..end)
+    }
+
+    fn string(&self, id: u32) -> Option<&str> {
+        let bytes = self
+            .section(STRING_BYTES)
+            .get(self.range(STRING_OFFSETS, id as usize)?)?;
+        std::str::from_utf8(bytes).ok()
+    }
+
+    fn annotations(&self, list: u32) -> Option<Vec<AnnotationData>> {
+        self.range(LIST_OFFSETS, list as usize)?
+            .map(|item| self.record(self.u32_at(LIST_ITEMS, item)?))
+            .collect()
+    }
+
+    fn record(&self, record: u32) -> Option<AnnotationData> {
+        let record = record as usize * RECORD_FIELDS;
+        let value = |i: usize| self.u32_at(RECORDS, record + i);
+        let field = |i: usize| Some(self.string(value(i)?)?.to_string());
+        Some(AnnotationData {
+            pinyin: field(0)?,
+            zhuyin: field(1)?,
+            traditional: field(2)?,
+            simplified: field(3)?,
+            source: source_from_code(value(4)?),
+            definitions: self.glosses(value(5)?)?,
+            frequency: value(6)?,
+            jyutping: field(7)?,
+        })
+    }
+
+    fn glosses(&self, gloss_list: u32) -> Option<Vec<String>> {
+        self.range(GLOSS_OFFSETS, gloss_list as usize)?
+            .map(|item| Some(self.string(self.u32_at(GLOSS_ITEMS, item)?)?.to_string()))
+            .collect()
+    }
+
+    fn child(&self, table: Table, node: usize, ch: char) -> Option<usize> {
+        let labels = table.section(EDGE_LABELS);
+
+        let edges = self.range(table.section(EDGE_OFFSETS), node)?;
+        let (mut low, mut high) = (edges.start, edges.end);
+        let target = ch as u32;
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.u32_at(labels, mid)?.cmp(&target) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => {
+                    return Some(self.u32_at(table.section(EDGE_TARGETS), mid)? as usize)
+                }
+            }
+        }
+        None
+    }
+
+    fn node_value(&self, table: Table, node: usize) -> Option<u32> {
+        let value = self.u32_at(table.section(NODE_VALUES), node)?;
+        (value != NO_VALUE).then_some(value)
+    }
+
+    fn word_value(&self, table: Table, word: &str) -> Option<u32> {
+        let mut node = 0;
+        for ch in word.chars() {
+            node = self.child(table, node, ch)?;
+        }
+        self.node_value(table, node)
+    }
+
+    /// Node and length of the longest word in `table` that is a prefix of
+    /// `chars`, looking at no more than `max_len` characters
+    fn longest_node(&self, table: Table, chars: &[char], max_len: usize) -> Option<(usize, u32)> {
+        let mut node = 0;
+        let mut longest = None;
+        for (i, &ch) in chars.iter().take(max_len).enumerate() {
+            match self.child(table, node, ch) {
+                Some(next) => node = next,
+                None => break,
+            }
+            if let Some(value) = self.node_value(table, node) {
+                longest = Some((i + 1, value));
+            }
+        }
+        longest
+    }
+
+    /// Longest word in `table` that is a prefix of `chars`, with its readings
+    pub fn longest_match(
+        &self,
+        table: Table,
+        chars: &[char],
+        max_len: usize,
+    ) -> Option<(usize, Vec<AnnotationData>)> {
+        let (len, list) = self.longest_node(table, chars, max_len)?;
+        Some((len, self.annotations(list)?))
+    }
+
+    /// Length of the longest word in `table` that is a prefix of `chars`
+    pub fn longest_prefix(&self, table: Table, chars: &[char], max_len: usize) -> Option<usize> {
+        self.longest_node(table, chars, max_len).map(|(len, _)| len)
+    }
+
+    /// Every word in `table` that is a prefix of `chars`, as `(length,
+    /// frequency)` pairs, without materializing any annotations
+    pub fn prefix_matches(
+        &self,
+        table: Table,
+        chars: &[char],
+        max_len: usize,
+    ) -> Vec<(usize, u32)> {
+        let mut matches = Vec::new();
+        let mut node = 0;
+        for (i, &ch) in chars.iter().take(max_len).enumerate() {
+            match self.child(table, node, ch) {
+                Some(next) => node = next,
+                None => break,
+            }
+            if let Some(list) = self.node_value(table, node) {
+                matches.push((i + 1, self.list_frequency(list)));
+            }
+        }
+        matches
+    }
+
+    /// Highest frequency among the records of an annotation list
+    fn list_frequency(&self, list: u32) -> u32 {
+        self.range(LIST_OFFSETS, list as usize)
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let record = self.u32_at(LIST_ITEMS, item)? as usize * RECORD_FIELDS;
+                self.u32_at(RECORDS, record + 6)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn lookup(&self, table: Table, word: &str) -> Option<Vec<AnnotationData>> {
+        self.annotations(self.word_value(table, word)?)
+    }
+
+    pub fn contains(&self, table: Table, word: &str) -> bool {
+        self.word_value(table, word).is_some()
+    }
+
+    /// Check the checksum and that every offset and id in the file is in
+    /// bounds. This reads the whole file, so `open` leaves it to callers
+    /// that want it, such as `dict-processor --verify`.
+    pub fn verify(&self) -> Result<()> {
+        let bytes: &[u8] = &self.mmap;
+        if crc32fast::hash(&bytes[HEADER_LEN..]) != read_u32(bytes, 12) {
+            bail!("checksum mismatch");
+        }
+
+        let strings =
+            self.check_offsets(STRING_OFFSETS, self.sections[STRING_BYTES].1, "string")?;
+        let gloss_lists =
+            self.check_offsets(GLOSS_OFFSETS, self.u32_count(GLOSS_ITEMS), "gloss list")?;
+        self.check_ids(GLOSS_ITEMS, |_| strings, "gloss")?;
+
+        let records = self.u32_count(RECORDS) / RECORD_FIELDS;
+        self.check_ids(
+            RECORDS,
+            |i| match i % RECORD_FIELDS {
+                5 => gloss_lists,
+                4 | 6 => usize::MAX,
+                _ => strings,
+            },
+            "record field",
+        )?;
+        let lists = self.check_offsets(LIST_OFFSETS, self.u32_count(LIST_ITEMS), "list")?;
+        self.check_ids(LIST_ITEMS, |_| records, "list item")?;
+
+        for table in [Table::Simplified, Table::Traditional, Table::Chars] {
+            let edges = self.u32_count(table.section(EDGE_LABELS));
+            let nodes = self.check_offsets(table.section(EDGE_OFFSETS), edges, "edge")?;
+            if self.u32_count(table.section(EDGE_TARGETS)) != edges
+                || self.u32_count(table.section(NODE_VALUES)) != nodes
+            {
+                bail!("{:?} table sizes do not match", table);
+            }
+            self.check_ids(table.section(EDGE_TARGETS), |_| nodes, "edge target")?;
+            for node in 0..nodes {
+                match self.node_value(table, node) {
+                    Some(list) if list as usize >= lists => {
+                        bail!("{:?} table node {} points past the lists", table, node)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check an offsets section: it starts at 0, never decreases and ends
+    /// within `items`. Returns the number of ranges it holds.
+    fn check_offsets(&self, section: usize, items: usize, what: &str) -> Result<usize> {
+        let count = self.u32_count(section);
+        if count == 0 || self.u32_at(section, 0) != Some(0) {
+            bail!("{} offsets are malformed", what);
+        }
+        for i in 0..count - 1 {
+            if self.range(section, i).is_none_or(|range| range.end > items) {
+                bail!("{} {} is out of bounds", what, i);
+            }
+        }
+        Ok(count - 1)
+    }
+
+    /// Check that every id in a section is below the limit for its position
+    fn check_ids(&self, section: usize, limit: impl Fn(usize) -> usize, what: &str) -> Result<()> {
+        for i in 0..self.u32_count(section) {
+            let id = self.u32_at(section, i).unwrap_or(u32::MAX) as usize;
+            if id >= limit(i) {
+                bail!("{} {} is out of bounds", what, i);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn source_code(source: ReadingSource) -> u32 {
+    match source {
+        ReadingSource::Dictionary => 0,
+        ReadingSource::UnihanMandarin => 1,
+        ReadingSource::UnihanXhc1983 => 2,
+        ReadingSource::UnihanHanyuPinyin => 3,
+    }
+}
+
+fn source_from_code(code: u32) -> ReadingSource {
+    match code {
+        1 => ReadingSource::UnihanMandarin,
+        2 => ReadingSource::UnihanXhc1983,
+        3 => ReadingSource::UnihanHanyuPinyin,
+        _ => ReadingSource::Dictionary,
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(simplified: &str, traditional: &str, pinyin: &str) -> AnnotationData {
+        AnnotationData {
+            pinyin: pinyin.to_string(),
+            zhuyin: String::new(),
+            traditional: traditional.to_string(),
+            simplified: simplified.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn sample() -> ProcessedData {
+        let mut zhongguo = annotation("中国", "中國", "zhōng guó");
+        zhongguo.definitions = vec!["China".to_string()];
+        zhongguo.frequency = 120;
+        zhongguo.jyutping = "zung1 gwok3".to_string();
+        let zhong = annotation("中", "中", "zhōng");
+        let mut zhong_unihan = annotation("中", "中", "zhòng");
+        zhong_unihan.source = ReadingSource::UnihanHanyuPinyin;
+
+        let simplified_words = HashMap::from([
+            ("中国".to_string(), vec![zhongguo.clone()]),
+            ("中".to_string(), vec![zhong.clone()]),
+        ]);
+        let traditional_words = HashMap::from([
+            ("中國".to_string(), vec![zhongguo]),
+            ("中".to_string(), vec![zhong.clone()]),
+        ]);
+        let char_lookup = HashMap::from([("中".to_string(), vec![zhong, zhong_unihan])]);
+
+        ProcessedData {
+            simplified_words,
+            traditional_words,
+            char_lookup,
+            simplified_index: PrefixIndex::default(),
+            traditional_index: PrefixIndex::default(),
+            stats: ProcessingStats {
+                total_entries: 2,
+                unique_simplified_chars: 2,
+                unique_traditional_chars: 2,
+                max_word_length: 2,
+                multi_char_entries: 1,
+                unihan_chars: 1,
+                total_frequency: 1000,
+                jyutping_entries: 1,
+                cantonese_chars: 0,
+            },
+        }
+    }
+
+    /// Write `data` to a file of its own and open it
+    fn write_and_open(name: &str, data: &ProcessedData) -> (std::path::PathBuf, MappedDictionary) {
+        let path =
+            std::env::temp_dir().join(format!("zho-annotator-{}-{}.bin", std::process::id(), name));
+        let mut bytes = Vec::new();
+        write(data, &mut bytes).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        let dictionary = MappedDictionary::open(&path).unwrap();
+        (path, dictionary)
+    }
+
+    fn readings(annotations: &[AnnotationData]) -> Vec<(&str, &str, &str)> {
+        annotations
+            .iter()
+            .map(|a| {
+                (
+                    a.simplified.as_str(),
+                    a.traditional.as_str(),
+                    a.pinyin.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_keeps_words_readings_and_stats() {
+        let (path, dictionary) = write_and_open("round-trip", &sample());
+
+        let found = dictionary.lookup(Table::Simplified, "中国").unwrap();
+        assert_eq!(readings(&found), [("中国", "中國", "zhōng guó")]);
+        assert_eq!(found[0].definitions, ["China"]);
+        assert_eq!(found[0].frequency, 120);
+        assert_eq!(found[0].jyutping, "zung1 gwok3");
+        assert!(dictionary.contains(Table::Traditional, "中國"));
+        assert!(!dictionary.contains(Table::Simplified, "国"));
+
+        let chars = dictionary.lookup(Table::Chars, "中").unwrap();
+        assert_eq!(chars[1].pinyin, "zhòng");
+        assert_eq!(chars[1].source, ReadingSource::UnihanHanyuPinyin);
+
+        let text: Vec<char> = "中国人".chars().collect();
+        let (len, found) = dictionary
+            .longest_match(Table::Simplified, &text, 2)
+            .unwrap();
+        assert_eq!(len, 2);
+        assert_eq!(found[0].pinyin, "zhōng guó");
+        assert_eq!(
+            dictionary.longest_prefix(Table::Simplified, &text, 1),
+            Some(1)
+        );
+        assert_eq!(
+            dictionary.prefix_matches(Table::Simplified, &text, 3),
+            [(1, 0), (2, 120)]
+        );
+
+        assert_eq!(dictionary.stats().max_word_length, 2);
+        assert_eq!(dictionary.stats().total_frequency, 1000);
+        assert!(dictionary.verify().is_ok());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn verify_rejects_a_damaged_body() {
+        let (path, dictionary) = write_and_open("damaged", &sample());
+        drop(dictionary);
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        let dictionary = MappedDictionary::open(&path).unwrap();
+        assert!(dictionary.verify().is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn open_rejects_other_files() {
+        let path = std::env::temp_dir().join(format!(
+            "zho-annotator-{}-not-a-dictionary.bin",
+            std::process::id()
+        ));
+        std::fs::write(&path, b"{\"simplified_words\": {}}").unwrap();
+        assert!(MappedDictionary::open(&path).is_err());
+        assert!(!is_binary_dictionary(&path).unwrap());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,72 +1,282 @@
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
+use zho_annotator::binary_format;
+use zho_annotator::dictionary::{AnnotationData, PrefixIndex, ProcessedData, ProcessingStats};
+use zho_annotator::import::{cedict, frequency, unihan, DictionaryEntry};
 
-#[derive(Debug, Deserialize)]
-struct DictionaryEntry {
-    sm: String,     // Simplified
-    tr: String,     // Traditional
-    pinyin: String, // Pinyin
-    zhuyin: String, // Zhuyin/Bopomofo
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputKind {
+    Json,
+    Binary,
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct AnnotationData {
-    pinyin: String,
-    zhuyin: String,
-    traditional: String,
-    simplified: String,
+struct Options {
+    input: Option<String>,
+    cedict: Option<String>,
+    cc_canto: Vec<String>,
+    unihan: Option<String>,
+    frequency: Option<String>,
+    output: String,
+    output_kind: OutputKind,
+    dry_run: bool,
+    stats_json: bool,
 }
 
-#[derive(Debug, Serialize)]
-struct ProcessedData {
-    // Flat word-to-annotations mapping for both scripts
-    simplified_words: HashMap<String, Vec<AnnotationData>>,
-    traditional_words: HashMap<String, Vec<AnnotationData>>,
+fn main() -> Result<()> {
+    let matches = cli().get_matches();
+
+    if let Some(path) = matches.get_one::<String>("verify") {
+        binary_format::MappedDictionary::open(path)?
+            .verify()
+            .with_context(|| format!("Invalid binary dictionary: {}", path))?;
+        println!("{} is a valid binary dictionary", path);
+        return Ok(());
+    }
+
+    let options = parse_options(&matches);
+
+    if !options.stats_json {
+        println!("Dictionary Processor - Creating optimized mapping files");
+    }
+
+    let mut entries = Vec::new();
+
+    if let Some(input) = &options.input {
+        if !options.stats_json {
+            println!("Loading {}...", input);
+        }
+        let loaded = load_entries(input)?;
+        if !options.stats_json {
+            println!("Loaded {} entries", loaded.len());
+        }
+        entries.extend(loaded);
+    }
+
+    if let Some(path) = &options.cedict {
+        if !options.stats_json {
+            println!("Importing CC-CEDICT from {}...", path);
+        }
+        let imported = cedict::load(path)?;
+        if !options.stats_json {
+            println!("Imported {} CC-CEDICT entries", imported.len());
+        }
+        entries.extend(imported);
+    }
+
+    for path in &options.cc_canto {
+        if !options.stats_json {
+            println!("Importing Cantonese readings from {}...", path);
+        }
+        let imported = cedict::load(path)?;
+        if !options.stats_json {
+            println!("Imported {} CC-Canto entries", imported.len());
+        }
+        entries.extend(imported);
+    }
+
+    if !options.stats_json {
+        println!("Processing entries and building tries...");
+    }
+
+    let mut processed = process_dictionary(entries)?;
+
+    if let Some(path) = &options.unihan {
+        if !options.stats_json {
+            println!("Merging Unihan readings from {}...", path);
+        }
+        let readings = unihan::load(path)?;
+        processed.stats.unihan_chars =
+            unihan::merge_into(&mut processed.char_lookup, &readings.mandarin);
+        processed.stats.cantonese_chars =
+            unihan::merge_cantonese_into(&mut processed.char_lookup, &readings.cantonese);
+    }
+
+    if let Some(path) = &options.frequency {
+        if !options.stats_json {
+            println!("Applying word frequencies from {}...", path);
+        }
+        let frequencies = frequency::load(path)?;
+        let matched = frequency::apply(&mut processed, &frequencies);
+        if !options.stats_json {
+            println!(
+                "Matched {} entries against {} listed words",
+                matched,
+                frequencies.len()
+            );
+        }
+    }
 
-    // Quick character-to-annotation lookup
-    char_lookup: HashMap<String, Vec<AnnotationData>>,
+    if !options.dry_run {
+        if !options.stats_json {
+            println!("Writing {}...", options.output);
+        }
+        write_output(&processed, &options)?;
+    }
 
-    // Statistics
-    stats: ProcessingStats,
+    if options.stats_json {
+        println!("{}", stats_json(&processed.stats)?);
+    } else {
+        print_report(&processed, &options);
+    }
+
+    Ok(())
 }
 
-#[derive(Debug, Serialize)]
-struct ProcessingStats {
-    total_entries: usize,
-    unique_simplified_chars: usize,
-    unique_traditional_chars: usize,
-    max_word_length: usize,
-    multi_char_entries: usize,
+fn cli() -> Command {
+    Command::new("dict-processor")
+        .version("1.0.0")
+        .about("Build processed dictionary files for zho-annotator")
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .value_name("PATH")
+                .help(
+                    "Source dictionary (JSON array of sm/tr/pinyin/zhuyin entries) \
+                     [default: enhanced_dictionary.json when no other source is given]",
+                ),
+        )
+        .arg(
+            Arg::new("cedict")
+                .long("cedict")
+                .value_name("PATH")
+                .help("CC-CEDICT source file (cedict_ts.u8)"),
+        )
+        .arg(
+            Arg::new("cc-canto")
+                .long("cc-canto")
+                .value_name("PATH")
+                .action(ArgAction::Append)
+                .help(
+                    "CC-Canto dictionary or CC-CEDICT Cantonese readings file, adding Jyutping \
+                     to matching entries (may be given more than once)",
+                ),
+        )
+        .arg(Arg::new("unihan").long("unihan").value_name("PATH").help(
+            "Unihan_Readings.txt, merged into the character table as fallback readings \
+                     (Mandarin, and Jyutping from kCantonese)",
+        ))
+        .arg(
+            Arg::new("frequency")
+                .long("frequency")
+                .value_name("PATH")
+                .help(
+                    "Word-frequency list (`word count` per line) for max-probability segmentation",
+                ),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("PATH")
+                .help(
+                    "Output path [default: processed_dictionary.json, or \
+                     processed_dictionary.bin with --format binary]",
+                ),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value("json")
+                .value_parser(["json", "binary"])
+                .help("Output format: json, binary"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Process the input and print statistics without writing output")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .value_name("PATH")
+                .help("Check a binary dictionary's checksum and offsets, then exit"),
+        )
+        .arg(
+            Arg::new("stats-json")
+                .long("stats-json")
+                .help("Print processing statistics as JSON on stdout instead of the report")
+                .action(ArgAction::SetTrue),
+        )
 }
 
-fn main() -> Result<()> {
-    println!("Dictionary Processor - Creating optimized mapping files");
-    println!("Loading enhanced_dictionary.json...");
+fn parse_options(matches: &ArgMatches) -> Options {
+    let output_kind = match matches.get_one::<String>("format").unwrap().as_str() {
+        "binary" => OutputKind::Binary,
+        _ => OutputKind::Json,
+    };
 
-    let dict_file = File::open("enhanced_dictionary.json")
-        .context("Failed to open enhanced_dictionary.json")?;
-    let reader = BufReader::new(dict_file);
+    let output =
+        matches
+            .get_one::<String>("output")
+            .cloned()
+            .unwrap_or_else(|| match output_kind {
+                OutputKind::Json => "processed_dictionary.json".to_string(),
+                OutputKind::Binary => "processed_dictionary.bin".to_string(),
+            });
 
-    let entries: Vec<DictionaryEntry> =
-        serde_json::from_reader(reader).context("Failed to parse JSON")?;
+    let cedict = matches.get_one::<String>("cedict").cloned();
+    let cc_canto: Vec<String> = matches
+        .get_many::<String>("cc-canto")
+        .map(|paths| paths.cloned().collect())
+        .unwrap_or_default();
+    let unihan = matches.get_one::<String>("unihan").cloned();
+    let input = match matches.get_one::<String>("input") {
+        Some(input) => Some(input.clone()),
+        None if cedict.is_none() && cc_canto.is_empty() && unihan.is_none() => {
+            Some("enhanced_dictionary.json".to_string())
+        }
+        None => None,
+    };
 
-    println!("Loaded {} entries", entries.len());
-    println!("Processing entries and building tries...");
+    Options {
+        input,
+        cedict,
+        cc_canto,
+        unihan,
+        frequency: matches.get_one::<String>("frequency").cloned(),
+        output,
+        output_kind,
+        dry_run: matches.get_flag("dry-run"),
+        stats_json: matches.get_flag("stats-json"),
+    }
+}
 
-    let processed = process_dictionary(entries)?;
+fn load_entries(path: &str) -> Result<Vec<DictionaryEntry>> {
+    let dict_file = File::open(path).with_context(|| format!("Failed to open {}", path))?;
+    let reader = BufReader::new(dict_file);
 
-    println!("Writing optimized mapping files...");
+    serde_json::from_reader(reader).with_context(|| format!("Failed to parse JSON in {}", path))
+}
 
-    // Write the main processed data
-    let output_file = File::create("processed_dictionary.json")
-        .context("Failed to create processed_dictionary.json")?;
+fn write_output(processed: &ProcessedData, options: &Options) -> Result<()> {
+    let output_file = File::create(&options.output)
+        .with_context(|| format!("Failed to create {}", options.output))?;
     let writer = BufWriter::new(output_file);
-    serde_json::to_writer(writer, &processed).context("Failed to write processed dictionary")?;
 
-    // Print statistics
+    match options.output_kind {
+        OutputKind::Json => serde_json::to_writer(writer, processed)
+            .context("Failed to write processed dictionary")?,
+        // Compact binary form, memory-mapped by the annotator
+        OutputKind::Binary => {
+            binary_format::write(processed, writer).context("Failed to write binary dictionary")?
+        }
+    }
+
+    Ok(())
+}
+
+/// Processing statistics for `--stats-json`
+fn stats_json(stats: &ProcessingStats) -> Result<String> {
+    serde_json::to_string_pretty(stats).context("Failed to serialize statistics")
+}
+
+fn print_report(processed: &ProcessedData, options: &Options) {
     println!("\nProcessing Complete!");
     println!("Statistics:");
     println!("  Total entries: {}", processed.stats.total_entries);
@@ -79,14 +289,49 @@ fn main() -> Result<()> {
         processed.stats.unique_traditional_chars
     );
     println!("  Maximum word length: {}", processed.stats.max_word_length);
+    println!(
+        "  Prefix index nodes: {} simplified, {} traditional",
+        processed.simplified_index.node_count(),
+        processed.traditional_index.node_count()
+    );
     println!(
         "  Multi-character entries: {}",
         processed.stats.multi_char_entries
     );
-    println!("\nGenerated files:");
-    println!("  - processed_dictionary.json (main lookup data)");
+    if options.frequency.is_some() {
+        println!("  Total frequency: {}", processed.stats.total_frequency);
+    }
+    if !options.cc_canto.is_empty() {
+        println!(
+            "  Entries with Jyutping: {}",
+            processed.stats.jyutping_entries
+        );
+    }
+    if options.unihan.is_some() {
+        println!(
+            "  Characters with Unihan readings: {}",
+            processed.stats.unihan_chars
+        );
+        println!(
+            "  Characters with Unihan Jyutping: {}",
+            processed.stats.cantonese_chars
+        );
+    }
 
-    Ok(())
+    if options.dry_run {
+        println!("\nDry run: no files written");
+        return;
+    }
+
+    println!("\nGenerated files:");
+    match options.output_kind {
+        OutputKind::Json => println!("  - {} (main lookup data)", options.output),
+        OutputKind::Binary => println!(
+            "  - {} (binary format v{}, memory-mapped)",
+            options.output,
+            binary_format::FORMAT_VERSION
+        ),
+    }
 }
 
 fn process_dictionary(entries: Vec<DictionaryEntry>) -> Result<ProcessedData> {
@@ -98,13 +343,23 @@ fn process_dictionary(entries: Vec<DictionaryEntry>) -> Result<ProcessedData> {
     let mut unique_traditional = std::collections::HashSet::new();
     let mut max_word_length = 0;
     let mut multi_char_count = 0;
+    let mut total_entries = 0;
+    let mut jyutping_entries = 0;
+
+    for entry in &merge_entries(entries) {
+        total_entries += 1;
+        if !entry.jyutping.is_empty() {
+            jyutping_entries += 1;
+        }
 
-    for entry in &entries {
         let annotation = AnnotationData {
             pinyin: entry.pinyin.clone(),
             zhuyin: entry.zhuyin.clone(),
             traditional: entry.tr.clone(),
             simplified: entry.sm.clone(),
+            definitions: entry.definitions.clone(),
+            jyutping: entry.jyutping.clone(),
+            ..Default::default()
         };
 
         // Track statistics
@@ -118,43 +373,274 @@ fn process_dictionary(entries: Vec<DictionaryEntry>) -> Result<ProcessedData> {
         // Add to simplified words
         simplified_words
             .entry(entry.sm.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(annotation.clone());
         unique_simplified.insert(entry.sm.clone());
 
         // Add to traditional words (always, even if same as simplified)
         traditional_words
             .entry(entry.tr.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(annotation.clone());
         unique_traditional.insert(entry.tr.clone());
 
         // Add to character lookup for both simplified and traditional
         char_lookup
             .entry(entry.sm.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(annotation.clone());
 
         if entry.sm != entry.tr {
             char_lookup
                 .entry(entry.tr.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(annotation);
         }
     }
 
     let stats = ProcessingStats {
-        total_entries: entries.len(),
+        total_entries,
         unique_simplified_chars: unique_simplified.len(),
         unique_traditional_chars: unique_traditional.len(),
         max_word_length,
         multi_char_entries: multi_char_count,
+        unihan_chars: 0,
+        total_frequency: 0,
+        jyutping_entries,
+        cantonese_chars: 0,
     };
 
+    // Prefix indexes give linear-time longest-match lookup at annotation time
+    let simplified_index = PrefixIndex::build(simplified_words.keys().map(String::as_str));
+    let traditional_index = PrefixIndex::build(traditional_words.keys().map(String::as_str));
+
     Ok(ProcessedData {
         simplified_words,
         traditional_words,
         char_lookup,
+        simplified_index,
+        traditional_index,
         stats,
     })
 }
+
+/// Collapse entries for the same word and reading: the same reading can come
+/// from more than one source, and CC-CEDICT gives some senses of a word on
+/// lines of their own. The glosses of all of them are kept. A later entry
+/// fills in the Jyutping an earlier one lacks; one with a different Jyutping
+/// is kept as a separate reading. Readings match regardless of case, so a
+/// surname line (`何 何 [He2] /surname He/`) joins the common word `hé`,
+/// whose lowercase reading and glosses come first.
+fn merge_entries(entries: Vec<DictionaryEntry>) -> Vec<DictionaryEntry> {
+    let mut merged: Vec<DictionaryEntry> = Vec::with_capacity(entries.len());
+    let mut by_reading: HashMap<(String, String, String), Vec<usize>> = HashMap::new();
+
+    for mut entry in entries {
+        let key = (
+            entry.sm.clone(),
+            entry.tr.clone(),
+            entry.pinyin.to_lowercase(),
+        );
+        let indexes = by_reading.entry(key).or_default();
+
+        let existing = indexes.iter().copied().find(|&i| {
+            merged[i].jyutping.is_empty()
+                || entry.jyutping.is_empty()
+                || merged[i].jyutping == entry.jyutping
+        });
+
+        match existing {
+            Some(i) => {
+                let target = &mut merged[i];
+                if target.jyutping.is_empty() {
+                    target.jyutping = std::mem::take(&mut entry.jyutping);
+                }
+                if is_capitalized(&target.pinyin) && !is_capitalized(&entry.pinyin) {
+                    std::mem::swap(&mut target.pinyin, &mut entry.pinyin);
+                    std::mem::swap(&mut target.zhuyin, &mut entry.zhuyin);
+                    std::mem::swap(&mut target.definitions, &mut entry.definitions);
+                }
+                append_definitions(&mut target.definitions, entry.definitions);
+            }
+            None => {
+                indexes.push(merged.len());
+                merged.push(entry);
+            }
+        }
+    }
+
+    merged
+}
+
+/// Whether a reading is written as a proper noun (`Hé`)
+fn is_capitalized(pinyin: &str) -> bool {
+    pinyin.chars().next().is_some_and(char::is_uppercase)
+}
+
+/// Add the glosses `target` does not have yet
+fn append_definitions(target: &mut Vec<String>, definitions: Vec<String>) {
+    for definition in definitions {
+        if !target.contains(&definition) {
+            target.push(definition);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zho_annotator::dictionary::Dictionary;
+
+    fn options(args: &[&str]) -> Options {
+        let args = std::iter::once("dict-processor").chain(args.iter().copied());
+        parse_options(&cli().try_get_matches_from(args).unwrap())
+    }
+
+    fn entry(sm: &str, tr: &str, pinyin: &str, definitions: &[&str]) -> DictionaryEntry {
+        DictionaryEntry {
+            sm: sm.to_string(),
+            tr: tr.to_string(),
+            pinyin: pinyin.to_string(),
+            zhuyin: String::new(),
+            definitions: definitions.iter().map(|d| d.to_string()).collect(),
+            jyutping: String::new(),
+        }
+    }
+
+    fn processed() -> ProcessedData {
+        process_dictionary(vec![
+            entry("中国", "中國", "zhōng guó", &["China"]),
+            entry("中", "中", "zhōng", &["middle"]),
+            entry("何", "何", "Hé", &["surname He"]),
+            entry("何", "何", "hé", &["what"]),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn defaults_to_the_enhanced_dictionary_and_json() {
+        let options = options(&[]);
+        assert_eq!(options.input.as_deref(), Some("enhanced_dictionary.json"));
+        assert_eq!(options.output, "processed_dictionary.json");
+        assert_eq!(options.output_kind, OutputKind::Json);
+        assert!(!options.dry_run && !options.stats_json);
+    }
+
+    #[test]
+    fn takes_paths_format_and_flags() {
+        let binary = options(&["--format", "binary"]);
+        assert_eq!(binary.output_kind, OutputKind::Binary);
+        assert_eq!(binary.output, "processed_dictionary.bin");
+
+        let options = options(&[
+            "--cedict",
+            "cedict_ts.u8",
+            "--cc-canto",
+            "cccanto.u8",
+            "--cc-canto",
+            "readings.u8",
+            "-o",
+            "out/dict.bin",
+            "--format",
+            "binary",
+            "--dry-run",
+            "--stats-json",
+        ]);
+        // Another source replaces the default input
+        assert_eq!(options.input, None);
+        assert_eq!(options.cedict.as_deref(), Some("cedict_ts.u8"));
+        assert_eq!(options.cc_canto, ["cccanto.u8", "readings.u8"]);
+        assert_eq!(options.output, "out/dict.bin");
+        assert!(options.dry_run && options.stats_json);
+
+        assert!(cli()
+            .try_get_matches_from(["dict-processor", "--format", "xml"])
+            .is_err());
+    }
+
+    #[test]
+    fn stats_json_has_every_statistic() {
+        let stats: serde_json::Value =
+            serde_json::from_str(&stats_json(&processed().stats).unwrap()).unwrap();
+
+        // The two 何 lines are one reading
+        assert_eq!(stats["total_entries"], 3);
+        assert_eq!(stats["unique_simplified_chars"], 3);
+        assert_eq!(stats["unique_traditional_chars"], 3);
+        assert_eq!(stats["max_word_length"], 2);
+        assert_eq!(stats["multi_char_entries"], 1);
+        for key in [
+            "unihan_chars",
+            "total_frequency",
+            "jyutping_entries",
+            "cantonese_chars",
+        ] {
+            assert_eq!(stats[key], 0, "{key}");
+        }
+    }
+
+    #[test]
+    fn writes_json_and_binary_dictionaries() {
+        let processed = processed();
+        let dir = std::env::temp_dir();
+        let prefix = format!("dict-processor-{}", std::process::id());
+
+        for (output_kind, extension) in [(OutputKind::Json, "json"), (OutputKind::Binary, "bin")] {
+            let path = dir.join(format!("{prefix}.{extension}"));
+            let options = Options {
+                output: path.to_string_lossy().into_owned(),
+                output_kind,
+                ..options(&[])
+            };
+            write_output(&processed, &options).unwrap();
+
+            let dictionary = Dictionary::from_file(&path).unwrap();
+            let he = dictionary.lookup_char("何").unwrap();
+            assert_eq!(he.len(), 1);
+            assert_eq!(he[0].pinyin, "hé");
+            assert_eq!(he[0].definitions, ["what", "surname He"]);
+            if output_kind == OutputKind::Binary {
+                binary_format::MappedDictionary::open(&path)
+                    .unwrap()
+                    .verify()
+                    .unwrap();
+            }
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn imported_definitions_are_merged_without_duplicates() {
+        let lines = [
+            "中國 中国 [Zhong1 guo2] /China/",
+            "傳統 传统 [chuan2 tong3] /tradition/traditional/",
+            "傳統 传统 [chuan2 tong3] /convention/tradition/",
+        ];
+        let entries = lines
+            .iter()
+            .filter_map(|line| cedict::parse_line(line))
+            .collect();
+        let path = std::env::temp_dir().join(format!(
+            "dict-processor-{}-definitions.json",
+            std::process::id()
+        ));
+        let options = Options {
+            output: path.to_string_lossy().into_owned(),
+            ..options(&[])
+        };
+        write_output(&process_dictionary(entries).unwrap(), &options).unwrap();
+        let dictionary = Dictionary::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Glosses of repeated lines are merged without duplicates
+        assert_eq!(
+            dictionary.lookup_char("中国").unwrap()[0].definitions,
+            ["China"]
+        );
+        let chuantong = dictionary.lookup_char("传统").unwrap();
+        assert_eq!(
+            chuantong[0].definitions,
+            ["tradition", "traditional", "convention"]
+        );
+    }
+}
@@ -1,22 +1,199 @@
+use crate::binary_format::{self, MappedDictionary, Table};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AnnotationData {
     pub pinyin: String,
     pub zhuyin: String,
     pub traditional: String,
     pub simplified: String,
+    #[serde(default, skip_serializing_if = "ReadingSource::is_dictionary")]
+    pub source: ReadingSource,
+    /// English glosses, when the source dictionary has them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub definitions: Vec<String>,
+    /// Corpus frequency of the word, 0 when unknown
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub frequency: u32,
+    /// Cantonese reading in Jyutping (`nei5 hou2`), empty when unknown
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub jyutping: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TrieNode {
-    pub annotations: Vec<AnnotationData>,
-    pub children: BTreeMap<char, TrieNode>,
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+/// Where a reading came from, in order of precedence: word-level dictionary
+/// data first, then the Unihan fields from most to least customary
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingSource {
+    #[default]
+    Dictionary,
+    UnihanMandarin,
+    UnihanXhc1983,
+    UnihanHanyuPinyin,
+}
+
+impl ReadingSource {
+    pub fn is_dictionary(&self) -> bool {
+        matches!(self, ReadingSource::Dictionary)
+    }
+
+    pub fn is_unihan(&self) -> bool {
+        !self.is_dictionary()
+    }
+}
+
+/// Prefix index (trie) over dictionary words, flattened into arrays so it can be
+/// serialized with the word tables and walked without allocating.
+///
+/// Node `n` owns the edges `edge_offsets[n]..edge_offsets[n + 1]`; edge labels
+/// are sorted within each node so children are found by binary search.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PrefixIndex {
+    edge_offsets: Vec<u32>,
+    edge_labels: Vec<char>,
+    edge_targets: Vec<u32>,
+    terminal: Vec<bool>,
+}
+
+impl PrefixIndex {
+    /// Build an index over the given words
+    pub fn build<'a, I>(words: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut sorted: Vec<&str> = words.into_iter().filter(|w| !w.is_empty()).collect();
+        // Byte order of UTF-8 matches code point order, so children are
+        // appended to each node already sorted
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let mut children: Vec<Vec<(char, u32)>> = vec![Vec::new()];
+        let mut terminal = vec![false];
+
+        for word in sorted {
+            let mut node = 0;
+            for ch in word.chars() {
+                node = match children[node].last() {
+                    Some(&(label, target)) if label == ch => target as usize,
+                    _ => {
+                        let id = children.len();
+                        children.push(Vec::new());
+                        terminal.push(false);
+                        children[node].push((ch, id as u32));
+                        id
+                    }
+                };
+            }
+            terminal[node] = true;
+        }
+
+        let mut edge_offsets = Vec::with_capacity(children.len() + 1);
+        let mut edge_labels = Vec::new();
+        let mut edge_targets = Vec::new();
+        edge_offsets.push(0);
+        for edges in children {
+            for (label, target) in edges {
+                edge_labels.push(label);
+                edge_targets.push(target);
+            }
+            edge_offsets.push(edge_labels.len() as u32);
+        }
+
+        PrefixIndex {
+            edge_offsets,
+            edge_labels,
+            edge_targets,
+            terminal,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terminal.len() <= 1
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.terminal.len()
+    }
+
+    fn child(&self, node: usize, ch: char) -> Option<usize> {
+        let start = self.edge_offsets[node] as usize;
+        let end = self.edge_offsets[node + 1] as usize;
+        let labels = &self.edge_labels[start..end];
+        labels
+            .binary_search(&ch)
+            .ok()
+            .map(|i| self.edge_targets[start + i] as usize)
+    }
+
+    /// Node reached by walking the whole of `word`, if `word` is indexed
+    pub(crate) fn word_node(&self, word: &str) -> Option<usize> {
+        if self.terminal.is_empty() {
+            return None;
+        }
+
+        let mut node = 0;
+        for ch in word.chars() {
+            node = self.child(node, ch)?;
+        }
+        self.terminal[node].then_some(node)
+    }
+
+    /// Raw edge arrays: per-node edge offsets, edge labels and edge targets
+    pub(crate) fn edges(&self) -> (&[u32], &[char], &[u32]) {
+        (&self.edge_offsets, &self.edge_labels, &self.edge_targets)
+    }
+
+    /// Length of the longest word that is a prefix of `chars`, looking at no
+    /// more than `max_len` characters
+    pub fn longest_prefix(&self, chars: &[char], max_len: usize) -> Option<usize> {
+        if self.terminal.is_empty() {
+            return None;
+        }
+
+        let mut node = 0;
+        let mut longest = None;
+        for (i, &ch) in chars.iter().take(max_len).enumerate() {
+            match self.child(node, ch) {
+                Some(next) => node = next,
+                None => break,
+            }
+            if self.terminal[node] {
+                longest = Some(i + 1);
+            }
+        }
+        longest
+    }
+
+    /// Lengths of every word that is a prefix of `chars`, shortest first,
+    /// looking at no more than `max_len` characters
+    pub fn prefix_lengths(&self, chars: &[char], max_len: usize) -> Vec<usize> {
+        let mut lengths = Vec::new();
+        if self.terminal.is_empty() {
+            return lengths;
+        }
+
+        let mut node = 0;
+        for (i, &ch) in chars.iter().take(max_len).enumerate() {
+            match self.child(node, ch) {
+                Some(next) => node = next,
+                None => break,
+            }
+            if self.terminal[node] {
+                lengths.push(i + 1);
+            }
+        }
+        lengths
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,9 +201,24 @@ pub struct ProcessedData {
     pub simplified_words: std::collections::HashMap<String, Vec<AnnotationData>>,
     pub traditional_words: std::collections::HashMap<String, Vec<AnnotationData>>,
     pub char_lookup: std::collections::HashMap<String, Vec<AnnotationData>>,
+    /// Prefix indexes over the word tables; rebuilt on load if missing
+    #[serde(default)]
+    pub simplified_index: PrefixIndex,
+    #[serde(default)]
+    pub traditional_index: PrefixIndex,
     pub stats: ProcessingStats,
 }
 
+impl ProcessedData {
+    /// Build the prefix indexes from the word tables
+    pub fn build_indexes(&mut self) {
+        self.simplified_index =
+            PrefixIndex::build(self.simplified_words.keys().map(String::as_str));
+        self.traditional_index =
+            PrefixIndex::build(self.traditional_words.keys().map(String::as_str));
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProcessingStats {
     pub total_entries: usize,
@@ -34,14 +226,41 @@ pub struct ProcessingStats {
     pub unique_traditional_chars: usize,
     pub max_word_length: usize,
     pub multi_char_entries: usize,
+    /// Characters that received fallback readings from Unihan
+    #[serde(default)]
+    pub unihan_chars: usize,
+    /// Sum of all counts in the word-frequency list, 0 without frequency data
+    #[serde(default)]
+    pub total_frequency: u64,
+    /// Dictionary entries with a Cantonese reading
+    #[serde(default)]
+    pub jyutping_entries: usize,
+    /// Characters that received a Cantonese reading from Unihan
+    #[serde(default)]
+    pub cantonese_chars: usize,
 }
 
 pub struct Dictionary {
-    data: ProcessedData,
+    storage: Storage,
+}
+
+/// Where dictionary data lives: parsed JSON in memory, or a mapped binary file
+enum Storage {
+    Json(ProcessedData),
+    Binary(MappedDictionary),
 }
 
 impl Dictionary {
+    /// Load a dictionary, memory-mapping binary files and parsing anything
+    /// else as JSON
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        if binary_format::is_binary_dictionary(path.as_ref())? {
+            let mapped = MappedDictionary::open(path)?;
+            return Ok(Dictionary {
+                storage: Storage::Binary(mapped),
+            });
+        }
+
         let file = File::open(path.as_ref()).with_context(|| {
             format!(
                 "Failed to open dictionary file: {}",
@@ -53,15 +272,38 @@ impl Dictionary {
         let data: ProcessedData =
             serde_json::from_reader(reader).context("Failed to parse processed dictionary JSON")?;
 
-        Ok(Dictionary { data })
+        Ok(Self::from_data(data))
+    }
+
+    /// Wrap already-loaded dictionary data, building the prefix indexes if the
+    /// data was produced without them
+    pub fn from_data(mut data: ProcessedData) -> Self {
+        if data.simplified_index.is_empty() || data.traditional_index.is_empty() {
+            data.build_indexes();
+        }
+        Dictionary {
+            storage: Storage::Json(data),
+        }
+    }
+
+    /// Whether the dictionary is backed by a memory-mapped binary file
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.storage, Storage::Binary(_))
+    }
+
+    fn stats(&self) -> &ProcessingStats {
+        match &self.storage {
+            Storage::Json(data) => &data.stats,
+            Storage::Binary(mapped) => mapped.stats(),
+        }
     }
 
     pub fn entry_count(&self) -> usize {
-        self.data.stats.total_entries
+        self.stats().total_entries
     }
 
     pub fn max_word_length(&self) -> usize {
-        self.data.stats.max_word_length
+        self.stats().max_word_length
     }
 
     /// Find the longest match starting from the given position in text
@@ -72,51 +314,173 @@ impl Dictionary {
         use_traditional: bool,
     ) -> Option<(usize, Vec<AnnotationData>)> {
         let chars: Vec<char> = text.chars().collect();
+        self.find_longest_match_in(&chars, start_pos, use_traditional)
+    }
+
+    /// Find the longest match starting at `start_pos` in an already split
+    /// character buffer. Runs in O(max_word_length) per call.
+    pub fn find_longest_match_in(
+        &self,
+        chars: &[char],
+        start_pos: usize,
+        use_traditional: bool,
+    ) -> Option<(usize, Vec<AnnotationData>)> {
         if start_pos >= chars.len() {
             return None;
         }
 
-        let words = if use_traditional {
-            &self.data.traditional_words
-        } else {
-            &self.data.simplified_words
+        let remaining = &chars[start_pos..];
+        let max_len = match self.max_word_length() {
+            0 => remaining.len(),
+            max => max.min(remaining.len()),
         };
 
-        let mut longest_match: Option<(usize, Vec<AnnotationData>)> = None;
+        match &self.storage {
+            Storage::Json(data) => {
+                let (words, index) = if use_traditional {
+                    (&data.traditional_words, &data.traditional_index)
+                } else {
+                    (&data.simplified_words, &data.simplified_index)
+                };
 
-        // Try all possible substring lengths starting from start_pos
-        for len in 1..=chars.len() - start_pos {
-            let word: String = chars[start_pos..start_pos + len].iter().collect();
+                let len = index.longest_prefix(remaining, max_len)?;
+                let word: String = remaining[..len].iter().collect();
+                words
+                    .get(&word)
+                    .map(|annotations| (len, annotations.clone()))
+            }
+            Storage::Binary(mapped) => {
+                mapped.longest_match(word_table(use_traditional), remaining, max_len)
+            }
+        }
+    }
+
+    /// Length of the longest dictionary word starting at `start_pos`, without
+    /// looking up its readings
+    pub fn longest_match_len(
+        &self,
+        chars: &[char],
+        start_pos: usize,
+        use_traditional: bool,
+    ) -> Option<usize> {
+        if start_pos >= chars.len() {
+            return None;
+        }
+
+        let remaining = &chars[start_pos..];
+        let max_len = match self.max_word_length() {
+            0 => remaining.len(),
+            max => max.min(remaining.len()),
+        };
+
+        match &self.storage {
+            Storage::Json(data) if use_traditional => {
+                data.traditional_index.longest_prefix(remaining, max_len)
+            }
+            Storage::Json(data) => data.simplified_index.longest_prefix(remaining, max_len),
+            Storage::Binary(mapped) => {
+                mapped.longest_prefix(word_table(use_traditional), remaining, max_len)
+            }
+        }
+    }
+
+    /// Whether the dictionary was built with any Cantonese readings
+    pub fn has_jyutping(&self) -> bool {
+        self.stats().jyutping_entries > 0 || self.stats().cantonese_chars > 0
+    }
+
+    /// Sum of the word-frequency counts the dictionary was built with
+    pub fn total_frequency(&self) -> u64 {
+        self.stats().total_frequency
+    }
 
-            if let Some(annotations) = words.get(&word) {
-                // Found a match, update longest_match
-                longest_match = Some((len, annotations.clone()));
+    /// Every dictionary word starting at `start_pos`, as `(length, frequency)`
+    /// pairs, shortest first. The frequency is the highest of the word's entries.
+    pub fn prefix_matches(
+        &self,
+        chars: &[char],
+        start_pos: usize,
+        use_traditional: bool,
+    ) -> Vec<(usize, u32)> {
+        if start_pos >= chars.len() {
+            return Vec::new();
+        }
+
+        let remaining = &chars[start_pos..];
+        let max_len = match self.max_word_length() {
+            0 => remaining.len(),
+            max => max.min(remaining.len()),
+        };
+
+        match &self.storage {
+            Storage::Json(data) => {
+                let (words, index) = if use_traditional {
+                    (&data.traditional_words, &data.traditional_index)
+                } else {
+                    (&data.simplified_words, &data.simplified_index)
+                };
+
+                index
+                    .prefix_lengths(remaining, max_len)
+                    .into_iter()
+                    .map(|len| {
+                        let word: String = remaining[..len].iter().collect();
+                        let frequency = words
+                            .get(&word)
+                            .and_then(|annotations| annotations.iter().map(|a| a.frequency).max())
+                            .unwrap_or(0);
+                        (len, frequency)
+                    })
+                    .collect()
+            }
+            Storage::Binary(mapped) => {
+                mapped.prefix_matches(word_table(use_traditional), remaining, max_len)
             }
         }
+    }
 
-        longest_match
+    /// Look up the readings of a whole word
+    pub fn lookup_word(&self, word: &str, use_traditional: bool) -> Option<Vec<AnnotationData>> {
+        match &self.storage {
+            Storage::Json(data) if use_traditional => data.traditional_words.get(word).cloned(),
+            Storage::Json(data) => data.simplified_words.get(word).cloned(),
+            Storage::Binary(mapped) => mapped.lookup(word_table(use_traditional), word),
+        }
+    }
+
+    /// Check whether a whole word is in the dictionary
+    pub fn contains_word(&self, word: &str, use_traditional: bool) -> bool {
+        match &self.storage {
+            Storage::Json(data) if use_traditional => data.traditional_words.contains_key(word),
+            Storage::Json(data) => data.simplified_words.contains_key(word),
+            Storage::Binary(mapped) => mapped.contains(word_table(use_traditional), word),
+        }
     }
 
     /// Quick character lookup for single characters
-    pub fn lookup_char(&self, ch: &str) -> Option<&Vec<AnnotationData>> {
-        self.data.char_lookup.get(ch)
+    pub fn lookup_char(&self, ch: &str) -> Option<Vec<AnnotationData>> {
+        match &self.storage {
+            Storage::Json(data) => data.char_lookup.get(ch).cloned(),
+            Storage::Binary(mapped) => mapped.lookup(Table::Chars, ch),
+        }
     }
 
     /// Detect if text is primarily traditional Chinese
     pub fn detect_traditional(&self, text: &str) -> bool {
         let mut traditional_count = 0;
         let mut simplified_count = 0;
+        let mut buf = [0u8; 4];
 
         for ch in text.chars() {
-            let ch_str = ch.to_string();
+            let ch_str = ch.encode_utf8(&mut buf);
 
             // Check if character exists in traditional words
-            if self.data.traditional_words.contains_key(&ch_str) {
+            if self.contains_word(ch_str, true) {
                 traditional_count += 1;
             }
 
             // Check if character exists in simplified words
-            if self.data.simplified_words.contains_key(&ch_str) {
+            if self.contains_word(ch_str, false) {
                 simplified_count += 1;
             }
         }
@@ -126,3 +490,164 @@ impl Dictionary {
         traditional_count > simplified_count
     }
 }
+
+fn word_table(use_traditional: bool) -> Table {
+    if use_traditional {
+        Table::Traditional
+    } else {
+        Table::Simplified
+    }
+}
+
+/// Small in-memory dictionaries for unit tests
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::*;
+    use crate::pinyin::to_zhuyin;
+
+    /// Entry for a word written `simplified` and `traditional`, read `pinyin`.
+    /// Latin letters, which CC-CEDICT writes in capitals (`kǎ lā O K`), stay
+    /// as written in the zhuyin.
+    pub fn entry(simplified: &str, traditional: &str, pinyin: &str) -> AnnotationData {
+        let zhuyin: Vec<String> = pinyin
+            .split_whitespace()
+            .map(|token| {
+                if token
+                    .bytes()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+                {
+                    token.to_string()
+                } else {
+                    to_zhuyin(token)
+                }
+            })
+            .collect();
+        AnnotationData {
+            pinyin: pinyin.to_string(),
+            zhuyin: zhuyin.join(" "),
+            traditional: traditional.to_string(),
+            simplified: simplified.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Word tables, character table and statistics over `entries`, laid out
+    /// the way dict-processor lays them out
+    pub fn data(entries: &[AnnotationData]) -> ProcessedData {
+        let mut data = ProcessedData {
+            simplified_words: Default::default(),
+            traditional_words: Default::default(),
+            char_lookup: Default::default(),
+            simplified_index: PrefixIndex::default(),
+            traditional_index: PrefixIndex::default(),
+            stats: ProcessingStats {
+                total_entries: entries.len(),
+                unique_simplified_chars: 0,
+                unique_traditional_chars: 0,
+                max_word_length: 0,
+                multi_char_entries: 0,
+                unihan_chars: 0,
+                total_frequency: entries.iter().map(|e| e.frequency as u64).sum(),
+                jyutping_entries: entries.iter().filter(|e| !e.jyutping.is_empty()).count(),
+                cantonese_chars: 0,
+            },
+        };
+
+        for entry in entries {
+            let len = entry.simplified.chars().count();
+            data.stats.max_word_length = data.stats.max_word_length.max(len);
+            if len > 1 {
+                data.stats.multi_char_entries += 1;
+            }
+
+            for (words, word) in [
+                (&mut data.simplified_words, &entry.simplified),
+                (&mut data.traditional_words, &entry.traditional),
+                (&mut data.char_lookup, &entry.simplified),
+            ] {
+                words.entry(word.clone()).or_default().push(entry.clone());
+            }
+            if entry.traditional != entry.simplified {
+                data.char_lookup
+                    .entry(entry.traditional.clone())
+                    .or_default()
+                    .push(entry.clone());
+            }
+        }
+        data.stats.unique_simplified_chars = data.simplified_words.len();
+        data.stats.unique_traditional_chars = data.traditional_words.len();
+        data.build_indexes();
+        data
+    }
+
+    /// Dictionary over `entries`
+    pub fn dictionary(entries: &[AnnotationData]) -> Dictionary {
+        Dictionary::from_data(data(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn index() -> PrefixIndex {
+        PrefixIndex::build(["中", "中国", "中国人", "中华", "国", "人民", ""])
+    }
+
+    #[test]
+    fn finds_the_longest_indexed_prefix() {
+        let index = index();
+
+        assert_eq!(index.longest_prefix(&chars("中国人民"), 8), Some(3));
+        assert_eq!(index.longest_prefix(&chars("中国队"), 8), Some(2));
+        assert_eq!(index.longest_prefix(&chars("中文"), 8), Some(1));
+        // 人 alone is only the start of 人民
+        assert_eq!(index.longest_prefix(&chars("人们"), 8), None);
+        assert_eq!(index.longest_prefix(&[], 8), None);
+    }
+
+    #[test]
+    fn lists_every_prefix_shortest_first() {
+        let index = index();
+
+        assert_eq!(index.prefix_lengths(&chars("中国人民"), 8), [1, 2, 3]);
+        assert_eq!(index.prefix_lengths(&chars("中华人民"), 8), [1, 2]);
+        assert!(index.prefix_lengths(&chars("民"), 8).is_empty());
+    }
+
+    #[test]
+    fn looks_no_further_than_max_len() {
+        let index = index();
+
+        assert_eq!(index.longest_prefix(&chars("中国人"), 2), Some(2));
+        assert_eq!(index.longest_prefix(&chars("中国人"), 0), None);
+        assert_eq!(index.prefix_lengths(&chars("中国人"), 2), [1, 2]);
+        assert!(index.prefix_lengths(&chars("人民"), 1).is_empty());
+    }
+
+    #[test]
+    fn indexes_whole_words_only() {
+        let index = index();
+
+        assert!(index.word_node("中国人").is_some());
+        assert!(index.word_node("人").is_none());
+        assert!(index.word_node("中国人民").is_none());
+        // Root, 中, 中国, 中国人, 中华, 国, 人, 人民
+        assert_eq!(index.node_count(), 8);
+        assert!(!index.is_empty());
+    }
+
+    #[test]
+    fn empty_indexes_match_nothing() {
+        for index in [PrefixIndex::default(), PrefixIndex::build([""])] {
+            assert!(index.is_empty());
+            assert_eq!(index.longest_prefix(&chars("中国"), 8), None);
+            assert!(index.prefix_lengths(&chars("中国"), 8).is_empty());
+            assert!(index.word_node("中国").is_none());
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! EPUB 3 books for the `epub` output format.
+//!
+//! The annotator hands over the input as lines of ruby markup; this module
+//! splits them into chapters, writes an XHTML file per chapter plus the nav
+//! document, stylesheet and package metadata, and stores everything in a
+//! zip container written here (uncompressed entries, `mimetype` first), so
+//! no external tools are needed.
+//!
+//! A chapter starts at a heading line (`# 标题`, or `第一章` and the like) or
+//! after two or more blank lines; a single blank line separates paragraphs.
+
+use crate::html::Line;
+use anyhow::{bail, Result};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A chapter of the book: its title and its body as XHTML
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub title: String,
+    pub body: String,
+}
+
+/// Everything that goes into the package
+#[derive(Debug, Clone)]
+pub struct Book {
+    pub title: String,
+    /// BCP 47 language tag, e.g. `zh-Hans`
+    pub language: String,
+    /// Stylesheet shared by every chapter
+    pub stylesheet: String,
+    pub chapters: Vec<Chapter>,
+}
+
+/// Numerals that can follow 第 in a chapter heading
+const HEADING_NUMERALS: &str = "〇零一二三四五六七八九十百千两兩0123456789０１２３４５６７８９";
+
+/// Split annotated lines into chapters. Text before the first heading
+/// becomes a chapter titled `untitled`.
+pub fn split_chapters(lines: &[Line], untitled: &str) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    let mut title: Option<String> = None;
+    let mut body = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut blank_lines = 0;
+
+    for line in lines {
+        let text = line.text.trim();
+        if text.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+
+        let heading = heading_level(text);
+        if heading.is_some() || blank_lines >= 2 {
+            close_paragraph(&mut body, &mut paragraph);
+            if !body.is_empty() {
+                let title = title.take().unwrap_or_else(|| untitled.to_string());
+                chapters.push(Chapter {
+                    title,
+                    body: std::mem::take(&mut body),
+                });
+            }
+        } else if blank_lines == 1 {
+            close_paragraph(&mut body, &mut paragraph);
+        }
+        blank_lines = 0;
+
+        match heading {
+            Some(level) => {
+                let tag = if level == 1 { "h1" } else { "h2" };
+                title = Some(strip_heading_marks(text).to_string());
+                body.push_str(&format!(
+                    "<{tag}>{}</{tag}>\n",
+                    strip_heading_marks(&line.markup)
+                ));
+            }
+            None => paragraph.push(&line.markup),
+        }
+    }
+    close_paragraph(&mut body, &mut paragraph);
+    if !body.is_empty() || chapters.is_empty() {
+        chapters.push(Chapter {
+            title: title.unwrap_or_else(|| untitled.to_string()),
+            body,
+        });
+    }
+
+    // Number untitled chapters so the table of contents tells them apart
+    if chapters.len() > 1 {
+        for (i, chapter) in chapters.iter_mut().enumerate() {
+            if chapter.title == untitled {
+                chapter.title = format!("{} {}", untitled, i + 1);
+            }
+        }
+    }
+
+    chapters
+}
+
+/// Heading level of a line: the number of `#` marks of a Markdown heading,
+/// or 1 for a Chinese chapter heading such as `第一章` or `第12回`
+fn heading_level(text: &str) -> Option<usize> {
+    let hashes = text.chars().take_while(|&c| c == '#').count();
+    if hashes > 0 {
+        let rest = &text[hashes..];
+        return (rest.starts_with(' ') && !rest.trim().is_empty()).then_some(hashes);
+    }
+
+    let rest = text.strip_prefix('第')?;
+    let numerals = rest
+        .chars()
+        .take_while(|&c| HEADING_NUMERALS.contains(c))
+        .count();
+    let after = rest.chars().nth(numerals)?;
+    (numerals > 0 && "章回节節卷部篇".contains(after)).then_some(1)
+}
+
+/// A heading without its Markdown `#` marks
+fn strip_heading_marks(text: &str) -> &str {
+    text.trim_start_matches('#').trim()
+}
+
+/// Move the collected lines into the body as one paragraph
+fn close_paragraph(body: &mut String, paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    body.push_str("<p>");
+    body.push_str(&paragraph.join("<br/>\n"));
+    body.push_str("</p>\n");
+    paragraph.clear();
+}
+
+/// Package a book as an EPUB 3 file
+pub fn package(book: &Book) -> Result<Vec<u8>> {
+    if book.chapters.is_empty() {
+        bail!("An EPUB needs at least one chapter");
+    }
+
+    let modified = utc_timestamp(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    );
+    let identifier = format!(
+        "urn:zho-annotator:{:08x}",
+        crc32fast::hash(
+            book.chapters
+                .iter()
+                .map(|c| c.body.as_str())
+                .collect::<String>()
+                .as_bytes()
+        )
+    );
+
+    let mut zip = ZipWriter::default();
+    zip.add("mimetype", b"application/epub+zip");
+    zip.add("META-INF/container.xml", CONTAINER_XML.as_bytes());
+    zip.add(
+        "OEBPS/content.opf",
+        package_document(book, &identifier, &modified).as_bytes(),
+    );
+    zip.add("OEBPS/nav.xhtml", nav_document(book).as_bytes());
+    zip.add("OEBPS/style.css", book.stylesheet.as_bytes());
+    for (i, chapter) in book.chapters.iter().enumerate() {
+        zip.add(
+            &chapter_file(i),
+            xhtml_page(&book.language, &chapter.title, &chapter.body).as_bytes(),
+        );
+    }
+    Ok(zip.finish())
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn chapter_file(index: usize) -> String {
+    format!("OEBPS/chapter-{}.xhtml", index + 1)
+}
+
+/// `content.opf`: metadata, manifest and reading order
+fn package_document(book: &Book, identifier: &str, modified: &str) -> String {
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    for i in 0..book.chapters.len() {
+        manifest.push_str(&format!(
+            "    <item id=\"chapter-{n}\" href=\"chapter-{n}.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+            n = i + 1
+        ));
+        spine.push_str(&format!("    <itemref idref=\"chapter-{}\"/>\n", i + 1));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{language}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>{language}</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+        language = book.language,
+        title = escape_xml(&book.title),
+    )
+}
+
+/// `nav.xhtml`: the table of contents
+fn nav_document(book: &Book) -> String {
+    let mut items = String::new();
+    for (i, chapter) in book.chapters.iter().enumerate() {
+        items.push_str(&format!(
+            "      <li><a href=\"chapter-{}.xhtml\">{}</a></li>\n",
+            i + 1,
+            escape_xml(&chapter.title)
+        ));
+    }
+    let body = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n  <h1>{}</h1>\n  <ol>\n{}  </ol>\n</nav>\n",
+        escape_xml(&book.title),
+        items
+    );
+    xhtml_page(&book.language, &book.title, &body)
+}
+
+fn xhtml_page(language: &str, title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+<meta charset="utf-8"/>
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        title = escape_xml(title),
+    )
+}
+
+/// Escape text for XML content and attribute values
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `CCYY-MM-DDThh:mm:ssZ` for seconds since the Unix epoch
+fn utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // Civil date from a day count (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// A minimal zip writer: every entry is stored uncompressed, which EPUB
+/// requires for `mimetype` and readers accept for the rest
+#[derive(Default)]
+struct ZipWriter {
+    bytes: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+/// DOS date for 1980-01-01, the earliest a zip entry can carry
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+impl ZipWriter {
+    fn add(&mut self, name: &str, data: &[u8]) {
+        let offset = self.bytes.len() as u32;
+        let crc = crc32fast::hash(data);
+        let size = data.len() as u32;
+
+        // Local file header
+        push_u32(&mut self.bytes, 0x0403_4b50);
+        push_u16(&mut self.bytes, 10); // version needed: stored
+        push_u16(&mut self.bytes, 0); // flags
+        push_u16(&mut self.bytes, 0); // method: stored
+        push_u16(&mut self.bytes, 0); // time
+        push_u16(&mut self.bytes, DOS_DATE);
+        push_u32(&mut self.bytes, crc);
+        push_u32(&mut self.bytes, size);
+        push_u32(&mut self.bytes, size);
+        push_u16(&mut self.bytes, name.len() as u16);
+        push_u16(&mut self.bytes, 0); // extra field length
+        self.bytes.extend_from_slice(name.as_bytes());
+        self.bytes.extend_from_slice(data);
+
+        // Central directory record
+        let cd = &mut self.central_directory;
+        push_u32(cd, 0x0201_4b50);
+        push_u16(cd, 20); // version made by
+        push_u16(cd, 10); // version needed
+        push_u16(cd, 0); // flags
+        push_u16(cd, 0); // method
+        push_u16(cd, 0); // time
+        push_u16(cd, DOS_DATE);
+        push_u32(cd, crc);
+        push_u32(cd, size);
+        push_u32(cd, size);
+        push_u16(cd, name.len() as u16);
+        push_u16(cd, 0); // extra field length
+        push_u16(cd, 0); // comment length
+        push_u16(cd, 0); // disk number
+        push_u16(cd, 0); // internal attributes
+        push_u32(cd, 0); // external attributes
+        push_u32(cd, offset);
+        cd.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let offset = self.bytes.len() as u32;
+        let size = self.central_directory.len() as u32;
+        self.bytes.append(&mut self.central_directory);
+
+        // End of central directory record
+        push_u32(&mut self.bytes, 0x0605_4b50);
+        push_u16(&mut self.bytes, 0); // this disk
+        push_u16(&mut self.bytes, 0); // disk with the central directory
+        push_u16(&mut self.bytes, self.entries);
+        push_u16(&mut self.bytes, self.entries);
+        push_u32(&mut self.bytes, size);
+        push_u32(&mut self.bytes, offset);
+        push_u16(&mut self.bytes, 0); // comment length
+        self.bytes
+    }
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u16(bytes: &[u8], offset: usize) -> usize {
+        u16::from_le_bytes([bytes[offset], bytes[offset + 1]]) as usize
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    /// Walk a zip through its central directory, checking that every record
+    /// points at a matching local header, and return the entries
+    fn unzip(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = zip.len() - 22;
+        assert_eq!(read_u32(zip, end), 0x0605_4b50);
+        let count = read_u16(zip, end + 10);
+        let size = read_u32(zip, end + 12);
+        let mut record = read_u32(zip, end + 16);
+        assert_eq!(
+            record + size,
+            end,
+            "central directory ends at the end record"
+        );
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(read_u32(zip, record), 0x0201_4b50);
+            let crc = read_u32(zip, record + 16);
+            let compressed = read_u32(zip, record + 20);
+            let name_len = read_u16(zip, record + 28);
+            let local = read_u32(zip, record + 42);
+            let name = &zip[record + 46..record + 46 + name_len];
+
+            assert_eq!(read_u32(zip, local), 0x0403_4b50);
+            assert_eq!(read_u16(zip, local + 8), 0, "stored");
+            assert_eq!(read_u32(zip, local + 14), crc);
+            assert_eq!(read_u32(zip, local + 18), compressed);
+            assert_eq!(read_u16(zip, local + 26), name_len);
+            assert_eq!(&zip[local + 30..local + 30 + name_len], name);
+            let start = local + 30 + name_len + read_u16(zip, local + 28);
+            let data = &zip[start..start + compressed];
+            assert_eq!(crc32fast::hash(data) as usize, crc);
+
+            entries.push((String::from_utf8(name.to_vec()).unwrap(), data.to_vec()));
+            record += 46 + name_len;
+        }
+        assert_eq!(record, end);
+        entries
+    }
+
+    fn lines(text: &str) -> Vec<Line> {
+        text.lines()
+            .map(|line| Line {
+                text: line.to_string(),
+                markup: line.to_string(),
+            })
+            .collect()
+    }
+
+    fn book() -> Book {
+        Book {
+            title: "故事 & 诗".to_string(),
+            language: "zh-Hans".to_string(),
+            stylesheet: "ruby { ruby-position: over; }".to_string(),
+            chapters: split_chapters(&lines("# 第一章\n你好\n\n# 第二章\n世界"), "正文"),
+        }
+    }
+
+    #[test]
+    fn zip_central_directory_matches_local_headers() {
+        let entries = unzip(&package(&book()).unwrap());
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "mimetype",
+                "META-INF/container.xml",
+                "OEBPS/content.opf",
+                "OEBPS/nav.xhtml",
+                "OEBPS/style.css",
+                "OEBPS/chapter-1.xhtml",
+                "OEBPS/chapter-2.xhtml",
+            ]
+        );
+        assert_eq!(entries[0].1, b"application/epub+zip");
+        assert_eq!(entries[4].1, b"ruby { ruby-position: over; }");
+    }
+
+    #[test]
+    fn mimetype_comes_first_at_a_fixed_offset() {
+        let zip = package(&book()).unwrap();
+        assert_eq!(&zip[30..38], b"mimetype");
+        assert_eq!(&zip[38..58], b"application/epub+zip");
+    }
+
+    #[test]
+    fn package_lists_chapters_in_manifest_spine_and_nav() {
+        let entries = unzip(&package(&book()).unwrap());
+        let text = |name: &str| {
+            let (_, data) = entries.iter().find(|(n, _)| n == name).unwrap();
+            String::from_utf8(data.clone()).unwrap()
+        };
+
+        let opf = text("OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>故事 &amp; 诗</dc:title>"));
+        assert!(opf.contains("<itemref idref=\"chapter-2\"/>"));
+        let nav = text("OEBPS/nav.xhtml");
+        assert!(nav.contains("第一章") && nav.contains("第二章"));
+        let chapter = text("OEBPS/chapter-1.xhtml");
+        assert!(chapter.contains("<h1>第一章</h1>\n<p>你好</p>"));
+        assert!(chapter.contains("xml:lang=\"zh-Hans\""));
+    }
+
+    #[test]
+    fn splits_chapters_at_headings_and_double_blank_lines() {
+        let chapters = split_chapters(&lines("序\n\n\n第十二回 下山\n一\n二\n\n三"), "正文");
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "正文 1");
+        assert_eq!(chapters[0].body, "<p>序</p>\n");
+        assert_eq!(chapters[1].title, "第十二回 下山");
+        assert_eq!(
+            chapters[1].body,
+            "<h1>第十二回 下山</h1>\n<p>一<br/>\n二</p>\n<p>三</p>\n"
+        );
+
+        assert_eq!(heading_level("## 小节"), Some(2));
+        assert_eq!(heading_level("#标签"), None);
+        assert_eq!(heading_level("第三者"), None);
+        assert!(package(&Book {
+            chapters: Vec::new(),
+            ..book()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(951_827_696), "2000-02-29T12:34:56Z");
+    }
+}
@@ -0,0 +1,315 @@
+//! Vocabulary flashcards from annotated text.
+//!
+//! Every dictionary-matched word becomes a card with its pinyin, zhuyin and
+//! definitions, plus the sentence it first appears in, with the word as an
+//! Anki cloze deletion (`我爱{{c1::中国}}。`). Cards are written as Anki
+//! import TSV or as a Pleco flashcard text file.
+
+use crate::pinyin;
+use crate::production_annotator::{escape_html, AnnotatedSegment};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Flashcard file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashcardFormat {
+    /// Tab-separated fields with Anki's `#separator`/`#columns` headers
+    Anki,
+    /// Pleco's `word<TAB>pinyin<TAB>definition` import format
+    Pleco,
+}
+
+/// Which occurrences count as the same card
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dedupe {
+    /// One card per word, with the reading of its first occurrence
+    #[default]
+    Word,
+    /// One card per word and reading, so 还 huán and 还 hái are two cards
+    Reading,
+    /// One card per occurrence
+    None,
+}
+
+/// Order of the cards in the file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CardOrder {
+    /// As the words first appear in the text
+    #[default]
+    Appearance,
+    /// Most frequent in the text first
+    Frequency,
+    /// Alphabetically by pinyin, tones in order
+    Pinyin,
+}
+
+/// A vocabulary card
+#[derive(Debug, Clone)]
+pub struct Card {
+    pub word: String,
+    pub pinyin: String,
+    pub zhuyin: String,
+    pub definitions: Vec<String>,
+    /// The sentence the word first appears in
+    pub sentence: String,
+    /// The sentence with the word as a cloze deletion
+    pub cloze: String,
+    /// How often the card's word (and reading) occurs in the text
+    pub count: usize,
+}
+
+/// Characters that end a sentence
+const SENTENCE_ENDS: [char; 9] = ['。', '！', '？', '!', '?', '；', ';', '…', '\n'];
+
+/// Collect a card for each dictionary-matched word of the segments
+pub fn collect(segments: &[AnnotatedSegment], dedupe: Dedupe) -> Vec<Card> {
+    let mut cards: Vec<Card> = Vec::new();
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+
+    for (start, end) in sentences(segments) {
+        let sentence = &segments[start..end];
+        for (i, segment) in sentence.iter().enumerate() {
+            let Some(pinyin) = segment.pinyin.as_ref().filter(|_| segment.is_chinese) else {
+                continue;
+            };
+
+            let key = match dedupe {
+                Dedupe::Word => Some((segment.text.clone(), String::new())),
+                Dedupe::Reading => Some((segment.text.clone(), pinyin.clone())),
+                Dedupe::None => None,
+            };
+            if let Some(&card) = key.as_ref().and_then(|key| index.get(key)) {
+                cards[card].count += 1;
+                continue;
+            }
+            if let Some(key) = key {
+                index.insert(key, cards.len());
+            }
+
+            cards.push(Card {
+                word: segment.text.clone(),
+                pinyin: pinyin.clone(),
+                zhuyin: segment.zhuyin.clone().unwrap_or_default(),
+                definitions: segment.definitions.clone(),
+                sentence: sentence_text(sentence, None),
+                cloze: sentence_text(sentence, Some(i)),
+                count: 1,
+            });
+        }
+    }
+
+    cards
+}
+
+/// Sort cards; ties keep the order of appearance
+pub fn sort(cards: &mut [Card], order: CardOrder) {
+    match order {
+        CardOrder::Appearance => {}
+        CardOrder::Frequency => cards.sort_by_key(|card| Reverse(card.count)),
+        CardOrder::Pinyin => cards.sort_by_cached_key(|card| {
+            (
+                pinyin::to_toneless(&card.pinyin).to_lowercase(),
+                pinyin::to_numbered(&card.pinyin).to_lowercase(),
+            )
+        }),
+    }
+}
+
+/// Write cards in the given format
+pub fn render(cards: &[Card], format: FlashcardFormat) -> String {
+    match format {
+        FlashcardFormat::Anki => to_anki(cards),
+        FlashcardFormat::Pleco => to_pleco(cards),
+    }
+}
+
+/// Anki TSV: import into a note type with Word, Pinyin, Zhuyin,
+/// Definitions, Sentence and Cloze fields (the Cloze field works with
+/// Anki's cloze note type)
+pub fn to_anki(cards: &[Card]) -> String {
+    let mut result = String::new();
+    result.push_str("#separator:tab\n");
+    result.push_str("#html:true\n");
+    result.push_str("#columns:Word\tPinyin\tZhuyin\tDefinitions\tSentence\tCloze\n");
+
+    for card in cards {
+        let fields = [
+            escape_html(&card.word),
+            escape_html(&card.pinyin),
+            escape_html(&card.zhuyin),
+            escape_html(&card.definitions.join("; ")),
+            escape_html(&card.sentence),
+            escape_html(&card.cloze),
+        ];
+        result.push_str(&fields.map(|field| tsv_field(&field)).join("\t"));
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Pleco flashcards: headword, numbered pinyin and a definition that ends
+/// with the example sentence
+pub fn to_pleco(cards: &[Card]) -> String {
+    let mut result = String::new();
+
+    for card in cards {
+        let mut definition = card.definitions.join("; ");
+        if !card.sentence.is_empty() {
+            if !definition.is_empty() {
+                definition.push(' ');
+            }
+            definition.push_str(&format!("例: {}", card.sentence));
+        }
+        let fields = [
+            card.word.clone(),
+            pinyin::to_numbered(&card.pinyin).replace(' ', ""),
+            definition,
+        ];
+        result.push_str(&fields.map(|field| tsv_field(&field)).join("\t"));
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Segment ranges of the sentences of a text
+pub(crate) fn sentences(segments: &[AnnotatedSegment]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for (i, segment) in segments.iter().enumerate() {
+        if segment.text.contains(SENTENCE_ENDS) {
+            ranges.push((start, i + 1));
+            start = i + 1;
+        }
+    }
+    if start < segments.len() {
+        ranges.push((start, segments.len()));
+    }
+    ranges
+}
+
+/// The text of a sentence, with the segment at `cloze` as `{{c1::…}}`
+fn sentence_text(sentence: &[AnnotatedSegment], cloze: Option<usize>) -> String {
+    let mut text = String::new();
+    for (i, segment) in sentence.iter().enumerate() {
+        if Some(i) == cloze {
+            text.push_str(&format!("{{{{c1::{}}}}}", segment.text));
+        } else {
+            text.push_str(&segment.text);
+        }
+    }
+    text.trim().to_string()
+}
+
+/// A field without the tabs and line breaks that would split it
+fn tsv_field(text: &str) -> String {
+    text.replace(['\t', '\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::fixture::{self, entry};
+    use crate::production_annotator::{AnnotationConfig, ProductionAnnotator};
+
+    fn segments(text: &str) -> Vec<AnnotatedSegment> {
+        let mut zhongguo = entry("中国", "中國", "zhōng guó");
+        zhongguo.definitions = vec!["China".to_string(), "<b>PRC</b> & co".to_string()];
+        let entries = [
+            entry("我", "我", "wǒ"),
+            entry("他", "他", "tā"),
+            entry("还", "還", "hái"),
+            entry("还", "還", "huán"),
+            entry("钱", "錢", "qián"),
+            entry("有", "有", "yǒu"),
+            zhongguo,
+        ];
+        let annotator = ProductionAnnotator::with_dictionary(
+            fixture::dictionary(&entries),
+            AnnotationConfig::default(),
+        );
+        annotator.annotate(text).unwrap()
+    }
+
+    fn words(cards: &[Card]) -> Vec<(&str, &str, usize)> {
+        cards
+            .iter()
+            .map(|card| (card.word.as_str(), card.pinyin.as_str(), card.count))
+            .collect()
+    }
+
+    #[test]
+    fn dedupes_by_word_reading_or_not_at_all() {
+        let segments = segments("我还钱。他还有钱！");
+
+        assert_eq!(
+            words(&collect(&segments, Dedupe::Word)),
+            [
+                ("我", "wǒ", 1),
+                ("还", "huán", 2),
+                ("钱", "qián", 2),
+                ("他", "tā", 1),
+                ("有", "yǒu", 1)
+            ]
+        );
+        assert_eq!(
+            words(&collect(&segments, Dedupe::Reading)),
+            [
+                ("我", "wǒ", 1),
+                ("还", "huán", 1),
+                ("钱", "qián", 2),
+                ("他", "tā", 1),
+                ("还", "hái", 1),
+                ("有", "yǒu", 1)
+            ]
+        );
+        assert_eq!(collect(&segments, Dedupe::None).len(), 7);
+    }
+
+    #[test]
+    fn sorts_by_frequency_or_pinyin() {
+        let mut cards = collect(&segments("我还钱。他还有钱！"), Dedupe::Reading);
+
+        sort(&mut cards, CardOrder::Frequency);
+        assert_eq!(
+            words(&cards).iter().map(|w| w.0).collect::<String>(),
+            "钱我还他还有"
+        );
+
+        sort(&mut cards, CardOrder::Pinyin);
+        assert_eq!(
+            words(&cards).iter().map(|w| w.1).collect::<Vec<_>>(),
+            ["hái", "huán", "qián", "tā", "wǒ", "yǒu"]
+        );
+    }
+
+    #[test]
+    fn cards_keep_their_first_sentence_as_a_cloze() {
+        let cards = collect(&segments("我还钱。\n他还有钱！"), Dedupe::Word);
+        let qian = cards.iter().find(|card| card.word == "钱").unwrap();
+
+        assert_eq!(qian.sentence, "我还钱。");
+        assert_eq!(qian.cloze, "我还{{c1::钱}}。");
+        assert_eq!(qian.zhuyin, "ㄑㄧㄢˊ");
+    }
+
+    #[test]
+    fn writes_anki_and_pleco_files() {
+        let cards = collect(&segments("我爱中国。"), Dedupe::Word);
+
+        assert_eq!(
+            render(&cards, FlashcardFormat::Anki),
+            "#separator:tab\n#html:true\n\
+             #columns:Word\tPinyin\tZhuyin\tDefinitions\tSentence\tCloze\n\
+             我\twǒ\tㄨㄛˇ\t\t我爱中国。\t{{c1::我}}爱中国。\n\
+             中国\tzhōng guó\tㄓㄨㄥ ㄍㄨㄛˊ\tChina; &lt;b&gt;PRC&lt;/b&gt; &amp; co\t\
+             我爱中国。\t我爱{{c1::中国}}。\n"
+        );
+        assert_eq!(
+            render(&cards, FlashcardFormat::Pleco),
+            "我\two3\t例: 我爱中国。\n\
+             中国\tzhong1guo2\tChina; <b>PRC</b> & co 例: 我爱中国。\n"
+        );
+    }
+}
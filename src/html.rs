@@ -0,0 +1,260 @@
+//! Standalone HTML documents for the `html` output format.
+//!
+//! The annotator turns each line of the input into ruby markup; this module
+//! groups the lines into paragraphs (a blank line starts a new `<p>`, other
+//! line breaks become `<br>`) and wraps them in a complete document with a charset,
+//! a language tag, a stylesheet for the chosen theme and, optionally, the
+//! Pleco tone colors and a script that shows and hides readings on click.
+
+use serde::{Deserialize, Serialize};
+
+/// One line of annotated input
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// The line as written
+    pub text: String,
+    /// Ruby markup for the line, also valid XHTML
+    pub markup: String,
+}
+
+/// Page colors of the embedded stylesheet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HtmlTheme {
+    #[default]
+    Light,
+    Dark,
+    Sepia,
+}
+
+impl HtmlTheme {
+    /// Background, text and reading colors
+    fn colors(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            HtmlTheme::Light => ("#ffffff", "#1a1a1a", "#555555"),
+            HtmlTheme::Dark => ("#1e1e1e", "#e6e6e6", "#a8a8a8"),
+            HtmlTheme::Sepia => ("#f4ecd8", "#3b2f20", "#6b5a43"),
+        }
+    }
+
+    /// Tone colors 1-6 (Pleco's defaults for the Mandarin tones 1-4 and the
+    /// neutral tone; Cantonese uses all six), lightened on dark backgrounds
+    fn tone_colors(self) -> [&'static str; 6] {
+        match self {
+            HtmlTheme::Dark => [
+                "#ff5c5c", "#4cd964", "#6b8cff", "#c86bff", "#9a9a9a", "#e0a030",
+            ],
+            HtmlTheme::Light | HtmlTheme::Sepia => [
+                "#e30000", "#02b31c", "#1510f0", "#8900bf", "#777777", "#b36b00",
+            ],
+        }
+    }
+}
+
+/// What goes around the annotated body
+#[derive(Debug, Clone)]
+pub struct DocumentOptions<'a> {
+    /// BCP 47 language tag for the `lang` attribute, e.g. `zh-Hans`
+    pub lang: &'a str,
+    pub title: &'a str,
+    pub theme: HtmlTheme,
+    /// Include the `.tone1`-`.tone6` color classes
+    pub tone_colors: bool,
+    /// Hide readings until their word is clicked
+    pub toggle_readings: bool,
+}
+
+/// Shows or hides the readings of the clicked word
+const TOGGLE_SCRIPT: &str = "document.addEventListener('click', function (event) {
+  var ruby = event.target.closest('ruby');
+  if (ruby) {
+    ruby.classList.toggle('shown');
+  }
+});";
+
+/// CSS class for a tone number, `None` for syllables without a known tone
+pub fn tone_class(tone: u8) -> Option<&'static str> {
+    match tone {
+        1 => Some("tone1"),
+        2 => Some("tone2"),
+        3 => Some("tone3"),
+        4 => Some("tone4"),
+        5 => Some("tone5"),
+        6 => Some("tone6"),
+        _ => None,
+    }
+}
+
+/// Group lines into paragraphs: blank lines end a paragraph, and the lines
+/// of a paragraph are separated by `<br>`
+pub fn paragraphs(lines: &[Line]) -> String {
+    let mut body = String::new();
+    for paragraph in lines.split(|line| line.text.trim().is_empty()) {
+        if paragraph.is_empty() {
+            continue;
+        }
+        let markup: Vec<&str> = paragraph.iter().map(|line| line.markup.as_str()).collect();
+        body.push_str("<p>");
+        body.push_str(&markup.join("<br>\n"));
+        body.push_str("</p>\n");
+    }
+    body
+}
+
+/// Wrap annotated body markup in a complete HTML document
+pub fn document(body: &str, options: &DocumentOptions) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n");
+    html.push_str(&format!("<html lang=\"{}\">\n", options.lang));
+    html.push_str("<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>{}</title>\n", options.title));
+    html.push_str("<style>\n");
+    html.push_str(&stylesheet(options));
+    html.push_str("</style>\n</head>\n");
+
+    if options.toggle_readings {
+        html.push_str("<body class=\"toggle-readings\">\n");
+    } else {
+        html.push_str("<body>\n");
+    }
+    html.push_str(body);
+    if options.toggle_readings {
+        html.push_str("<script>\n");
+        html.push_str(TOGGLE_SCRIPT);
+        html.push_str("\n</script>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// The embedded stylesheet: page colors, ruby layout and the optional tone
+/// colors and click-to-show rules
+pub fn stylesheet(options: &DocumentOptions) -> String {
+    let (background, text, reading) = options.theme.colors();
+    let mut css = format!(
+        "body {{
+  background: {background};
+  color: {text};
+  font-family: \"Noto Serif CJK SC\", \"Noto Serif CJK TC\", \"Songti SC\", serif;
+  font-size: 1.5em;
+  line-height: 2.6;
+  max-width: 36em;
+  margin: 2em auto;
+  padding: 0 1em;
+}}
+p {{
+  margin: 0 0 1em;
+}}
+ruby {{
+  ruby-position: over;
+  ruby-align: center;
+}}
+rt {{
+  color: {reading};
+  font-size: 0.5em;
+  font-family: sans-serif;
+}}
+rtc + rtc {{
+  ruby-position: under;
+}}
+"
+    );
+
+    if options.tone_colors {
+        for (i, color) in options.theme.tone_colors().iter().enumerate() {
+            css.push_str(&format!(".tone{} {{\n  color: {};\n}}\n", i + 1, color));
+        }
+    }
+    if options.toggle_readings {
+        css.push_str(
+            ".toggle-readings ruby {
+  cursor: pointer;
+}
+.toggle-readings rt {
+  visibility: hidden;
+}
+.toggle-readings ruby.shown rt {
+  visibility: visible;
+}
+",
+        );
+    }
+
+    css
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> Line {
+        Line {
+            text: text.to_string(),
+            markup: format!("<b>{text}</b>"),
+        }
+    }
+
+    fn options(tone_colors: bool, toggle_readings: bool) -> DocumentOptions<'static> {
+        DocumentOptions {
+            lang: "zh-Hant",
+            title: "標題",
+            theme: HtmlTheme::Light,
+            tone_colors,
+            toggle_readings,
+        }
+    }
+
+    #[test]
+    fn tone_classes_cover_six_tones() {
+        assert_eq!(tone_class(1), Some("tone1"));
+        assert_eq!(tone_class(5), Some("tone5"));
+        assert_eq!(tone_class(6), Some("tone6"));
+        assert_eq!(tone_class(0), None);
+        assert_eq!(tone_class(7), None);
+    }
+
+    #[test]
+    fn blank_lines_separate_paragraphs() {
+        let lines = [line("一"), line("二"), line(""), line("  "), line("三")];
+        assert_eq!(
+            paragraphs(&lines),
+            "<p><b>一</b><br>\n<b>二</b></p>\n<p><b>三</b></p>\n"
+        );
+        assert_eq!(paragraphs(&[line("")]), "");
+    }
+
+    #[test]
+    fn documents_declare_charset_language_and_title() {
+        let html = document("<p>中</p>\n", &options(false, false));
+
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"zh-Hant\">\n"));
+        assert!(html.contains("<meta charset=\"utf-8\">"));
+        assert!(html.contains("<title>標題</title>"));
+        assert!(html.contains("<body>\n<p>中</p>\n</body>\n</html>\n"));
+        assert!(!html.contains(".tone1"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn tone_colors_follow_the_theme() {
+        let light = stylesheet(&options(true, false));
+        assert!(light.contains(".tone1 {\n  color: #e30000;\n}"));
+        assert!(light.contains(".tone6 {\n  color: #b36b00;\n}"));
+
+        let dark = stylesheet(&DocumentOptions {
+            theme: HtmlTheme::Dark,
+            ..options(true, false)
+        });
+        assert!(dark.contains("background: #1e1e1e;"));
+        assert!(dark.contains(".tone1 {\n  color: #ff5c5c;\n}"));
+    }
+
+    #[test]
+    fn toggled_readings_add_a_class_rules_and_script() {
+        let html = document("", &options(false, true));
+
+        assert!(html.contains("<body class=\"toggle-readings\">"));
+        assert!(html.contains(".toggle-readings rt {\n  visibility: hidden;\n}"));
+        assert!(html.contains(&format!("<script>\n{TOGGLE_SCRIPT}\n</script>\n</body>")));
+    }
+}
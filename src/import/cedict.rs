@@ -0,0 +1,157 @@
+//! CC-CEDICT importer.
+//!
+//! Entries look like `傳統 传统 [chuan2 tong3] /tradition/traditional/convention/`.
+//! Lines starting with `#` are comments. Numbered pinyin is converted to tone
+//! marks and zhuyin is derived from it.
+//!
+//! CC-Canto and its CC-CEDICT readings file use the same layout with a
+//! Jyutping reading after the pinyin, `傳統 传统 [chuan2 tong3] {cyun4 tung2}`,
+//! and are read by the same parser.
+
+use super::DictionaryEntry;
+use crate::pinyin::{convert_syllables, Syllable};
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Read every entry from a CC-CEDICT file
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<DictionaryEntry>> {
+    let file = File::open(path.as_ref())
+        .with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+    read_entries(BufReader::new(file))
+        .with_context(|| format!("Failed to read CC-CEDICT file {}", path.as_ref().display()))
+}
+
+/// Read every entry from CC-CEDICT formatted text
+pub fn read_entries<R: BufRead>(reader: R) -> Result<Vec<DictionaryEntry>> {
+    let mut entries = Vec::new();
+
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match parse_line(line) {
+            Some(entry) => entries.push(entry),
+            None => bail!(
+                "Malformed CC-CEDICT entry on line {}: {}",
+                line_number + 1,
+                line
+            ),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parse a single `trad simp [pin1 yin1] {jyut6 ping3} /def/` line; the
+/// Jyutping and the definitions are optional
+pub fn parse_line(line: &str) -> Option<DictionaryEntry> {
+    let (traditional, rest) = line.split_once(' ')?;
+    let (simplified, rest) = rest.split_once(' ')?;
+    let rest = rest.trim_start().strip_prefix('[')?;
+    let (numbered, rest) = rest.split_once(']')?;
+
+    let (jyutping, rest) = match rest.trim_start().strip_prefix('{') {
+        Some(rest) => {
+            let (jyutping, rest) = rest.split_once('}')?;
+            let jyutping: Vec<&str> = jyutping.split_whitespace().collect();
+            (jyutping.join(" "), rest)
+        }
+        None => (String::new(), rest),
+    };
+
+    let definitions = rest
+        .trim()
+        .trim_matches('/')
+        .split('/')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    // Tokens for Latin letters and digits in the headword (`A4纸 [A4 zhi3]`,
+    // `卡拉OK [ka3 la1 O K]`) stay as written in both readings
+    let pinyin = convert_syllables(simplified, numbered, Syllable::marked).join(" ");
+    let zhuyin = convert_syllables(simplified, numbered, Syllable::zhuyin).join(" ");
+
+    Some(DictionaryEntry {
+        sm: simplified.to_string(),
+        tr: traditional.to_string(),
+        pinyin,
+        zhuyin,
+        definitions,
+        jyutping,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_an_entry() {
+        let entry =
+            parse_line("傳統 传统 [chuan2 tong3] /tradition/traditional/convention/").unwrap();
+        assert_eq!(entry.tr, "傳統");
+        assert_eq!(entry.sm, "传统");
+        assert_eq!(entry.pinyin, "chuán tǒng");
+        assert_eq!(entry.zhuyin, "ㄔㄨㄢˊ ㄊㄨㄥˇ");
+        assert_eq!(
+            entry.definitions,
+            ["tradition", "traditional", "convention"]
+        );
+        assert!(entry.jyutping.is_empty());
+    }
+
+    #[test]
+    fn parses_cc_canto_jyutping() {
+        let entry = parse_line("傳統 传统 [chuan2 tong3] {cyun4  tung2} /tradition/").unwrap();
+        assert_eq!(entry.jyutping, "cyun4 tung2");
+        assert_eq!(entry.definitions, ["tradition"]);
+
+        let entry = parse_line("嘅 嘅 [ge4] {ge3}").unwrap();
+        assert_eq!(entry.jyutping, "ge3");
+        assert!(entry.definitions.is_empty());
+    }
+
+    #[test]
+    fn converts_u_colon_neutral_tone_and_surnames() {
+        let entry = parse_line("女兒 女儿 [nu:3 er5] /daughter/").unwrap();
+        assert_eq!(entry.pinyin, "nǚ er");
+        assert_eq!(entry.zhuyin, "ㄋㄩˇ ˙ㄦ");
+
+        let entry = parse_line("王 王 [Wang2] /surname Wang/").unwrap();
+        assert_eq!(entry.pinyin, "Wáng");
+    }
+
+    #[test]
+    fn keeps_latin_tokens_as_written() {
+        let entry = parse_line("A4紙 A4纸 [A4 zhi3] /A4 paper/").unwrap();
+        assert_eq!(entry.pinyin, "A4 zhǐ");
+        assert_eq!(entry.zhuyin, "A4 ㄓˇ");
+
+        let entry = parse_line("卡拉OK 卡拉OK [ka3 la1 O K] /karaoke/").unwrap();
+        assert_eq!(entry.sm, "卡拉OK");
+        assert_eq!(entry.pinyin, "kǎ lā O K");
+        assert_eq!(entry.zhuyin, "ㄎㄚˇ ㄌㄚ O K");
+
+        let entry = parse_line("AA制 AA制 [A A zhi4] /to split the bill/").unwrap();
+        assert_eq!(entry.pinyin, "A A zhì");
+        assert_eq!(entry.zhuyin, "A A ㄓˋ");
+    }
+
+    #[test]
+    fn reads_a_file_and_reports_malformed_lines() {
+        let text = "# CC-CEDICT\n\n中國 中国 [Zhong1 guo2] /China/\n你好 你好 [ni3 hao3] /hello/\n";
+        let entries = read_entries(text.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].pinyin, "Zhōng guó");
+
+        let error = read_entries("你好 你好 [ni3 hao3] /hello/\n壞\n".as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+}
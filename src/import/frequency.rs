@@ -0,0 +1,123 @@
+//! Word-frequency list importer.
+//!
+//! Accepts whitespace-separated `word count` lines, optionally followed by
+//! more columns (jieba's `dict.txt` has a part-of-speech tag). Lines starting
+//! with `#` and lines without a numeric count are skipped.
+
+use crate::dictionary::ProcessedData;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Read a word-frequency list
+pub fn load<P: AsRef<Path>>(path: P) -> Result<HashMap<String, u64>> {
+    let file = File::open(path.as_ref())
+        .with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+    read_frequencies(BufReader::new(file))
+        .with_context(|| format!("Failed to read frequency list {}", path.as_ref().display()))
+}
+
+/// Read word-frequency lines, summing counts for repeated words
+pub fn read_frequencies<R: BufRead>(reader: R) -> Result<HashMap<String, u64>> {
+    let mut frequencies = HashMap::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let mut columns = line.split_whitespace();
+        let (Some(word), Some(count)) = (columns.next(), columns.next()) else {
+            continue;
+        };
+        let Ok(count) = count.parse::<u64>() else {
+            continue;
+        };
+
+        *frequencies.entry(word.to_string()).or_insert(0) += count;
+    }
+
+    Ok(frequencies)
+}
+
+/// Store frequencies on every dictionary entry, looked up by the entry's
+/// simplified form and then its traditional form. Sets the total frequency
+/// in the statistics and returns the number of entries that got a frequency.
+pub fn apply(processed: &mut ProcessedData, frequencies: &HashMap<String, u64>) -> usize {
+    let lookup = |simplified: &str, traditional: &str| {
+        frequencies
+            .get(simplified)
+            .or_else(|| frequencies.get(traditional))
+            // Counts above u32::MAX are clamped; only relative size matters
+            .map(|&count| count.min(u32::MAX as u64) as u32)
+    };
+
+    let mut matched = 0;
+    for annotations in processed.simplified_words.values_mut() {
+        for annotation in annotations {
+            if let Some(frequency) = lookup(&annotation.simplified, &annotation.traditional) {
+                annotation.frequency = frequency;
+                matched += 1;
+            }
+        }
+    }
+
+    for annotations in processed
+        .traditional_words
+        .values_mut()
+        .chain(processed.char_lookup.values_mut())
+    {
+        for annotation in annotations {
+            if let Some(frequency) = lookup(&annotation.simplified, &annotation.traditional) {
+                annotation.frequency = frequency;
+            }
+        }
+    }
+
+    processed.stats.total_frequency = frequencies.values().sum();
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::fixture::{self, entry};
+
+    #[test]
+    fn reads_counts_and_skips_other_lines() {
+        let text =
+            "# word count tag\n的 318825 uj\n研究 5000\n\n研究 20 n\n生命 abc\n单\n起源\t800\n";
+        let frequencies = read_frequencies(text.as_bytes()).unwrap();
+
+        assert_eq!(frequencies.len(), 3);
+        assert_eq!(frequencies["的"], 318_825);
+        assert_eq!(frequencies["研究"], 5020);
+        assert_eq!(frequencies["起源"], 800);
+    }
+
+    #[test]
+    fn applies_counts_by_simplified_then_traditional_form() {
+        let mut data = fixture::data(&[
+            entry("研究", "研究", "yán jiū"),
+            entry("发现", "發現", "fā xiàn"),
+            entry("起源", "起源", "qǐ yuán"),
+        ]);
+        let frequencies = HashMap::from([
+            ("研究".to_string(), 5000),
+            ("發現".to_string(), 700),
+            ("其他".to_string(), 300),
+            ("巨大".to_string(), u32::MAX as u64 + 1),
+        ]);
+
+        assert_eq!(apply(&mut data, &frequencies), 2);
+        assert_eq!(data.simplified_words["研究"][0].frequency, 5000);
+        assert_eq!(data.simplified_words["发现"][0].frequency, 700);
+        assert_eq!(data.traditional_words["發現"][0].frequency, 700);
+        assert_eq!(data.char_lookup["发现"][0].frequency, 700);
+        assert_eq!(data.simplified_words["起源"][0].frequency, 0);
+        assert_eq!(data.stats.total_frequency, 6000 + u32::MAX as u64 + 1);
+    }
+}
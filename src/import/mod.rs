@@ -0,0 +1,23 @@
+//! Source dictionary importers used by `dict-processor`.
+
+pub mod cedict;
+pub mod frequency;
+pub mod unihan;
+
+use serde::Deserialize;
+
+/// A source dictionary entry, as read from `enhanced_dictionary.json` or
+/// produced by one of the importers
+#[derive(Debug, Clone, Deserialize)]
+pub struct DictionaryEntry {
+    pub sm: String,     // Simplified
+    pub tr: String,     // Traditional
+    pub pinyin: String, // Pinyin
+    pub zhuyin: String, // Zhuyin/Bopomofo
+    /// English glosses, when the source provides them
+    #[serde(default)]
+    pub definitions: Vec<String>,
+    /// Cantonese reading in Jyutping, when the source provides one
+    #[serde(default)]
+    pub jyutping: String,
+}
@@ -0,0 +1,306 @@
+//! Unihan readings importer.
+//!
+//! Reads tab-separated `Unihan_Readings.txt` lines such as
+//!
+//! ```text
+//! U+4E2D  kMandarin     zhōng
+//! U+4E2D  kHanyuPinyin  10012.010:zhōng,zhòng
+//! U+4E2D  kXHC1983      1511.010,1511.030:zhōng 1511.050:zhòng
+//! ```
+//!
+//! and merges the readings into the character table as fallbacks. Word-level
+//! dictionary readings always come first; Unihan readings are ordered by
+//! [`ReadingSource`] precedence and only added when not already present.
+//!
+//! The `kCantonese` field (`U+4E2D  kCantonese  zung1`) supplies Jyutping for
+//! characters that no Cantonese source covered.
+
+use crate::dictionary::{AnnotationData, ReadingSource};
+use crate::pinyin::Syllable;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Readings per character, each tagged with the field it came from
+pub type UnihanReadings = BTreeMap<char, Vec<(ReadingSource, String)>>;
+
+/// Jyutping readings per character, most common first
+pub type CantoneseReadings = BTreeMap<char, Vec<String>>;
+
+/// Readings collected from one Unihan file
+#[derive(Debug, Default)]
+pub struct Unihan {
+    pub mandarin: UnihanReadings,
+    pub cantonese: CantoneseReadings,
+}
+
+/// Read the Mandarin reading fields and `kCantonese` from a Unihan readings file
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Unihan> {
+    let file = File::open(path.as_ref())
+        .with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+    read(BufReader::new(file))
+        .with_context(|| format!("Failed to read Unihan file {}", path.as_ref().display()))
+}
+
+/// Read the Mandarin reading fields and `kCantonese` from Unihan formatted
+/// text in a single pass
+pub fn read<R: BufRead>(reader: R) -> Result<Unihan> {
+    let mut unihan = Unihan::default();
+
+    for_each_field(reader, |ch, field, value| {
+        let (source, values) = match field {
+            "kMandarin" => (ReadingSource::UnihanMandarin, mandarin_values(value)),
+            "kXHC1983" => (ReadingSource::UnihanXhc1983, located_values(value)),
+            "kHanyuPinyin" => (ReadingSource::UnihanHanyuPinyin, located_values(value)),
+            "kCantonese" => {
+                unihan
+                    .cantonese
+                    .entry(ch)
+                    .or_default()
+                    .extend(value.split_whitespace().map(str::to_lowercase));
+                return;
+            }
+            _ => return,
+        };
+
+        let entry = unihan.mandarin.entry(ch).or_default();
+        for value in values {
+            if let Some(syllable) = Syllable::parse(value) {
+                entry.push((source, syllable.marked()));
+            }
+        }
+    })?;
+
+    // Most customary readings first; the sort is stable, so each field keeps
+    // its own ordering
+    for entry in unihan.mandarin.values_mut() {
+        entry.sort_by_key(|(source, _)| *source);
+    }
+
+    Ok(unihan)
+}
+
+/// Call `f` with the character, field name and value of every data line
+fn for_each_field<R, F>(reader: R, mut f: F) -> Result<()>
+where
+    R: BufRead,
+    F: FnMut(char, &str, &str),
+{
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let mut fields = line.splitn(3, '\t');
+        let (Some(code_point), Some(field), Some(value)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+
+        if let Some(ch) = parse_code_point(code_point) {
+            f(ch, field, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse `U+4E2D` into a character
+fn parse_code_point(code_point: &str) -> Option<char> {
+    let hex = code_point.strip_prefix("U+")?;
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+/// `kMandarin` holds space-separated readings
+fn mandarin_values(value: &str) -> Vec<&str> {
+    value.split_whitespace().collect()
+}
+
+/// `kHanyuPinyin` and `kXHC1983` hold `location,location:reading,reading`
+/// groups separated by spaces
+fn located_values(value: &str) -> Vec<&str> {
+    value
+        .split_whitespace()
+        .filter_map(|group| group.split_once(':'))
+        .flat_map(|(_, readings)| readings.split(','))
+        .collect()
+}
+
+/// Append Unihan readings to the character table, keeping existing readings
+/// first and skipping readings that are already present. Returns the number
+/// of characters that gained at least one reading.
+pub fn merge_into(
+    char_lookup: &mut HashMap<String, Vec<AnnotationData>>,
+    readings: &UnihanReadings,
+) -> usize {
+    let mut merged_chars = 0;
+
+    for (ch, char_readings) in readings {
+        if char_readings.is_empty() {
+            continue;
+        }
+
+        let key = ch.to_string();
+        let annotations = char_lookup.entry(key.clone()).or_default();
+        let before = annotations.len();
+
+        for (source, pinyin) in char_readings {
+            if annotations
+                .iter()
+                .any(|a| a.pinyin.eq_ignore_ascii_case(pinyin))
+            {
+                continue;
+            }
+
+            let zhuyin = Syllable::parse(pinyin)
+                .map(|s| s.zhuyin())
+                .unwrap_or_default();
+            annotations.push(AnnotationData {
+                pinyin: pinyin.clone(),
+                zhuyin,
+                traditional: key.clone(),
+                simplified: key.clone(),
+                source: *source,
+                ..Default::default()
+            });
+        }
+
+        if annotations.len() > before {
+            merged_chars += 1;
+        }
+    }
+
+    merged_chars
+}
+
+/// Give the first `kCantonese` reading to characters none of whose readings
+/// has Jyutping yet. Characters that already have some Cantonese data are
+/// left alone, since Unihan does not say which Mandarin reading each
+/// Cantonese reading belongs to. Returns the number of characters filled in.
+pub fn merge_cantonese_into(
+    char_lookup: &mut HashMap<String, Vec<AnnotationData>>,
+    readings: &CantoneseReadings,
+) -> usize {
+    let mut merged_chars = 0;
+
+    for (ch, char_readings) in readings {
+        let Some(jyutping) = char_readings.first() else {
+            continue;
+        };
+        let Some(annotations) = char_lookup.get_mut(ch.to_string().as_str()) else {
+            continue;
+        };
+        if annotations.is_empty() || annotations.iter().any(|a| !a.jyutping.is_empty()) {
+            continue;
+        }
+
+        for annotation in annotations.iter_mut() {
+            annotation.jyutping = jyutping.clone();
+        }
+        merged_chars += 1;
+    }
+
+    merged_chars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const READINGS: &str = "\
+# Unihan_Readings.txt
+U+4E2D\tkCantonese\tzung1 zung3
+U+4E2D\tkHanyuPinyin\t10012.010:zhōng,zhòng
+U+4E2D\tkMandarin\tzhōng
+U+4E2D\tkXHC1983\t1511.010,1511.030:zhōng 1511.050:zhòng
+U+4E2D\tkDefinition\tcentral; center, middle
+U+3400\tkMandarin\tqiū
+U+3400\tkCantonese\tjau1
+malformed line
+U+ZZZZ\tkMandarin\tbad
+";
+
+    #[test]
+    fn reads_mandarin_fields_in_order_of_precedence() {
+        let readings = read(READINGS.as_bytes()).unwrap().mandarin;
+
+        assert_eq!(
+            readings[&'中'],
+            [
+                (ReadingSource::UnihanMandarin, "zhōng".to_string()),
+                (ReadingSource::UnihanXhc1983, "zhōng".to_string()),
+                (ReadingSource::UnihanXhc1983, "zhòng".to_string()),
+                (ReadingSource::UnihanHanyuPinyin, "zhōng".to_string()),
+                (ReadingSource::UnihanHanyuPinyin, "zhòng".to_string()),
+            ]
+        );
+        assert_eq!(
+            readings[&'㐀'],
+            [(ReadingSource::UnihanMandarin, "qiū".to_string())]
+        );
+        assert_eq!(readings.len(), 2);
+    }
+
+    #[test]
+    fn splits_located_values() {
+        assert_eq!(
+            located_values("1511.010,1511.030:zhōng 1511.050:zhòng"),
+            ["zhōng", "zhòng"]
+        );
+        assert_eq!(located_values("10012.010:zhōng,zhòng"), ["zhōng", "zhòng"]);
+        assert_eq!(parse_code_point("U+4E2D"), Some('中'));
+        assert_eq!(parse_code_point("4E2D"), None);
+    }
+
+    #[test]
+    fn merges_new_readings_after_dictionary_ones() {
+        let readings = read(READINGS.as_bytes()).unwrap().mandarin;
+        let mut char_lookup = HashMap::from([(
+            "中".to_string(),
+            vec![AnnotationData {
+                pinyin: "zhōng".to_string(),
+                zhuyin: "ㄓㄨㄥ".to_string(),
+                traditional: "中".to_string(),
+                simplified: "中".to_string(),
+                ..Default::default()
+            }],
+        )]);
+
+        assert_eq!(merge_into(&mut char_lookup, &readings), 2);
+
+        let zhong = &char_lookup["中"];
+        assert_eq!(zhong.len(), 2);
+        assert_eq!(zhong[0].source, ReadingSource::Dictionary);
+        assert_eq!(zhong[1].pinyin, "zhòng");
+        assert_eq!(zhong[1].zhuyin, "ㄓㄨㄥˋ");
+        assert_eq!(zhong[1].source, ReadingSource::UnihanXhc1983);
+        assert_eq!(char_lookup["㐀"][0].pinyin, "qiū");
+    }
+
+    #[test]
+    fn fills_in_cantonese_only_where_missing() {
+        let cantonese = read(READINGS.as_bytes()).unwrap().cantonese;
+        assert_eq!(cantonese[&'中'], ["zung1", "zung3"]);
+
+        let reading = |pinyin: &str, jyutping: &str| AnnotationData {
+            pinyin: pinyin.to_string(),
+            jyutping: jyutping.to_string(),
+            ..Default::default()
+        };
+        let mut char_lookup = HashMap::from([
+            (
+                "中".to_string(),
+                vec![reading("zhōng", ""), reading("zhòng", "")],
+            ),
+            ("㐀".to_string(), vec![reading("qiū", "jau1")]),
+        ]);
+
+        assert_eq!(merge_cantonese_into(&mut char_lookup, &cantonese), 1);
+        assert!(char_lookup["中"].iter().all(|a| a.jyutping == "zung1"));
+        assert_eq!(char_lookup["㐀"][0].jyutping, "jau1");
+    }
+}
@@ -0,0 +1,180 @@
+//! Broad IPA transcription of Mandarin.
+//!
+//! Each pinyin syllable maps to its initial and final in broad IPA plus the
+//! tone, written as Chao tone letters (`ni˨˩˦`) or superscript numbers
+//! (`ni²¹⁴`). The neutral tone is left unmarked.
+
+use crate::pinyin::{map_syllables, Syllable};
+use serde::{Deserialize, Serialize};
+
+/// How tones are written in IPA output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToneNotation {
+    /// Chao tone letters: ˥˥ ˧˥ ˨˩˦ ˥˩
+    #[default]
+    Letters,
+    /// Superscript tone numbers: ⁵⁵ ³⁵ ²¹⁴ ⁵¹
+    Superscript,
+}
+
+const TONE_LETTERS: [&str; 4] = ["˥˥", "˧˥", "˨˩˦", "˥˩"];
+const TONE_SUPERSCRIPTS: [&str; 4] = ["⁵⁵", "³⁵", "²¹⁴", "⁵¹"];
+
+/// Transcribe space-separated pinyin in any spelling, passing non-pinyin
+/// tokens through unchanged (`nǐ hǎo` → `ni˨˩˦ xɑʊ˨˩˦`)
+pub fn convert(pinyin: &str, notation: ToneNotation) -> String {
+    map_syllables(pinyin, |syllable| transcribe(syllable, notation))
+}
+
+/// Transcribe a single syllable
+pub fn transcribe(syllable: &Syllable, notation: ToneNotation) -> String {
+    let mut result = String::from(initial(syllable.initial));
+
+    if syllable.is_apical() {
+        result.push_str(match syllable.initial {
+            "z" | "c" | "s" => "z̩",
+            _ => "ʐ̩",
+        });
+    } else if matches!(syllable.initial, "b" | "p" | "m" | "f") && syllable.final_ == "o" {
+        result.push_str("wo");
+    } else {
+        result.push_str(final_(syllable.final_));
+    }
+
+    if syllable.erhua {
+        result.push('ɻ');
+    }
+
+    if (1..=4).contains(&syllable.tone) {
+        let tones = match notation {
+            ToneNotation::Letters => &TONE_LETTERS,
+            ToneNotation::Superscript => &TONE_SUPERSCRIPTS,
+        };
+        result.push_str(tones[syllable.tone as usize - 1]);
+    }
+    result
+}
+
+fn initial(initial: &str) -> &'static str {
+    match initial {
+        "b" => "p",
+        "p" => "pʰ",
+        "m" => "m",
+        "f" => "f",
+        "d" => "t",
+        "t" => "tʰ",
+        "n" => "n",
+        "l" => "l",
+        "g" => "k",
+        "k" => "kʰ",
+        "h" => "x",
+        "j" => "tɕ",
+        "q" => "tɕʰ",
+        "x" => "ɕ",
+        "zh" => "ʈʂ",
+        "ch" => "ʈʂʰ",
+        "sh" => "ʂ",
+        "r" => "ʐ",
+        "z" => "ts",
+        "c" => "tsʰ",
+        "s" => "s",
+        _ => "",
+    }
+}
+
+fn final_(final_: &str) -> &'static str {
+    match final_ {
+        "a" => "a",
+        "o" => "o",
+        "e" => "ɤ",
+        "ê" => "ɛ",
+        "ai" => "aɪ",
+        "ei" => "eɪ",
+        "ao" => "ɑʊ",
+        "ou" => "oʊ",
+        "an" => "an",
+        "en" => "ən",
+        "ang" => "ɑŋ",
+        "eng" => "ɤŋ",
+        "ong" => "ʊŋ",
+        "er" => "ɑɻ",
+        "i" => "i",
+        "ia" => "ja",
+        "io" => "jo",
+        "ie" => "jɛ",
+        "iao" => "jɑʊ",
+        "iou" => "joʊ",
+        "ian" => "jɛn",
+        "in" => "in",
+        "iang" => "jɑŋ",
+        "ing" => "iŋ",
+        "iong" => "jʊŋ",
+        "u" => "u",
+        "ua" => "wa",
+        "uo" => "wo",
+        "uai" => "waɪ",
+        "uei" => "weɪ",
+        "uan" => "wan",
+        "uen" => "wən",
+        "uang" => "wɑŋ",
+        "ueng" => "wɤŋ",
+        "ü" => "y",
+        "üe" => "ɥɛ",
+        "üan" => "ɥɛn",
+        "ün" => "yn",
+        "m" => "m̩",
+        "n" => "n̩",
+        "ng" => "ŋ̍",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_tones_as_letters_or_superscripts() {
+        assert_eq!(convert("nǐ hǎo", ToneNotation::Letters), "ni˨˩˦ xɑʊ˨˩˦");
+        assert_eq!(convert("nǐ hǎo", ToneNotation::Superscript), "ni²¹⁴ xɑʊ²¹⁴");
+        assert_eq!(
+            convert("mā má mǎ mà ma", ToneNotation::Letters),
+            "ma˥˥ ma˧˥ ma˨˩˦ ma˥˩ ma"
+        );
+        assert_eq!(
+            convert("mā má mǎ mà ma", ToneNotation::Superscript),
+            "ma⁵⁵ ma³⁵ ma²¹⁴ ma⁵¹ ma"
+        );
+    }
+
+    #[test]
+    fn reads_any_pinyin_spelling() {
+        for pinyin in ["zhōng guó", "zhong1 guo2", "ZHONG1 GUO2"] {
+            assert_eq!(
+                convert(pinyin, ToneNotation::Letters),
+                "ʈʂʊŋ˥˥ kwo˧˥",
+                "{pinyin}"
+            );
+        }
+    }
+
+    #[test]
+    fn transcribes_spelling_variants_of_finals() {
+        let ipa = |pinyin| convert(pinyin, ToneNotation::Superscript);
+
+        assert_eq!(ipa("shì sì rì"), "ʂʐ̩⁵¹ sz̩⁵¹ ʐʐ̩⁵¹");
+        assert_eq!(ipa("bō mō"), "pwo⁵⁵ mwo⁵⁵");
+        assert_eq!(ipa("yī wǒ yuè"), "i⁵⁵ wo²¹⁴ ɥɛ⁵¹");
+        assert_eq!(ipa("lǜ xué jiǔ duì"), "ly⁵¹ ɕɥɛ³⁵ tɕjoʊ²¹⁴ tweɪ⁵¹");
+        assert_eq!(ipa("huār"), "xwaɻ⁵⁵");
+    }
+
+    #[test]
+    fn passes_other_tokens_through() {
+        assert_eq!(convert("kǎ lā OK", ToneNotation::Letters), "kʰa˨˩˦ la˥˥ OK");
+        assert_eq!(
+            convert("nǐ ， hǎo", ToneNotation::Letters),
+            "ni˨˩˦ ， xɑʊ˨˩˦"
+        );
+    }
+}
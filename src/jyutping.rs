@@ -0,0 +1,200 @@
+//! Cantonese Jyutping syllables and their Yale spelling.
+//!
+//! A [`Jyutping`] syllable is an initial, a final and a tone number 1-6
+//! (`gwong2`, `hoeng1`, `m4`). Cantonese Yale spells a few initials and
+//! finals differently and writes the tone with a diacritic on the first
+//! vowel, adding an `h` after the vowels for the low tones 4-6:
+//!
+//! | Jyutping | Yale |
+//! |----------|------|
+//! | hoeng1 gong2 | hēung góng |
+//! | jan4 | yàhn |
+//! | ngo5 | ngóh |
+//! | hai6 | haih |
+//! | m4 | m̀h |
+
+use crate::pinyin::{COMBINING_TONES, TONE_MARKS};
+
+/// Initials, two-letter ones first so `gw` wins over `g`
+const INITIALS: [&str; 19] = [
+    "gw", "kw", "ng", "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "h", "w", "z", "c", "s",
+    "j",
+];
+
+/// Every final Jyutping spells, including the syllabic nasals
+const FINALS: [&str; 59] = [
+    "aa", "aai", "aau", "aam", "aan", "aang", "aap", "aat", "aak", "a", "ai", "au", "am", "an",
+    "ang", "ap", "at", "ak", "e", "ei", "eu", "em", "en", "eng", "ep", "et", "ek", "i", "iu", "im",
+    "in", "ing", "ip", "it", "ik", "o", "oi", "ou", "on", "ong", "ot", "ok", "oe", "oeng", "oek",
+    "eoi", "eon", "eot", "u", "ui", "un", "ung", "ut", "uk", "yu", "yun", "yut", "m", "ng",
+];
+
+/// A parsed Jyutping syllable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jyutping {
+    pub initial: &'static str,
+    pub final_: &'static str,
+    /// Tone number, 1-6
+    pub tone: u8,
+}
+
+impl Jyutping {
+    /// Parse a syllable such as `gwong2`. Returns `None` for anything that is
+    /// not a lowercase toned Jyutping syllable, so Latin letters in headwords
+    /// (`A4紙 {A4 zi2}`) pass through.
+    pub fn parse(token: &str) -> Option<Jyutping> {
+        if !token
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        {
+            return None;
+        }
+        let (letters, tone) = token.split_at(token.len().checked_sub(1)?);
+        let tone = tone.parse::<u8>().ok().filter(|t| (1..=6).contains(t))?;
+
+        // Syllabic nasals have no initial, though `m` and `ng` are initials
+        // too, so take the first split that leaves a known final
+        let (initial, final_) = INITIALS
+            .iter()
+            .copied()
+            .chain([""])
+            .filter_map(|initial| Some((initial, letters.strip_prefix(initial)?)))
+            .find_map(|(initial, rest)| {
+                FINALS
+                    .iter()
+                    .find(|&&f| f == rest)
+                    .map(|&final_| (initial, final_))
+            })?;
+
+        Some(Jyutping {
+            initial,
+            final_,
+            tone,
+        })
+    }
+
+    /// Cantonese Yale spelling with tone diacritics (`yàhn`)
+    pub fn yale(&self) -> String {
+        let initial = match (self.initial, self.final_) {
+            // jyu → yu, not yyu
+            ("j", f) if f.starts_with("yu") => "",
+            ("z", _) => "j",
+            ("c", _) => "ch",
+            ("j", _) => "y",
+            (initial, _) => initial,
+        };
+        let final_ = match self.final_ {
+            "aa" => "a".to_string(),
+            f if f.starts_with("oe") || f.starts_with("eo") => format!("eu{}", &f[2..]),
+            f => f.to_string(),
+        };
+
+        let letters: Vec<char> = final_.chars().collect();
+        let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u');
+
+        // The tone mark goes on the first vowel (or the nasal of `m`, `ng`),
+        // and the low-tone `h` after the last vowel
+        let first_vowel = letters.iter().position(|&c| is_vowel(c));
+        let mark_at = first_vowel.unwrap_or(0);
+        let h_at = match first_vowel {
+            Some(start) => letters[start..]
+                .iter()
+                .position(|&c| !is_vowel(c))
+                .map_or(letters.len(), |len| start + len),
+            None => letters.len(),
+        };
+
+        let mut result = String::from(initial);
+        for (i, &c) in letters.iter().enumerate() {
+            if i == h_at && self.tone >= 4 {
+                result.push('h');
+            }
+            if i == mark_at {
+                push_marked(&mut result, c, self.tone);
+            } else {
+                result.push(c);
+            }
+        }
+        if h_at == letters.len() && self.tone >= 4 {
+            result.push('h');
+        }
+        result
+    }
+}
+
+/// Push `c` with the Yale diacritic for `tone`: macron for tone 1, acute for
+/// the rising tones 2 and 5, grave for tone 4 and none for the level tones 3
+/// and 6
+fn push_marked(result: &mut String, c: char, tone: u8) {
+    // Index into the pinyin mark tables, which hold tones 1-4
+    let mark = match tone {
+        1 => 0,
+        2 | 5 => 1,
+        4 => 3,
+        _ => {
+            result.push(c);
+            return;
+        }
+    };
+
+    match TONE_MARKS.iter().find(|(plain, _)| *plain == c) {
+        Some((_, marked)) => result.push(marked[mark]),
+        None => {
+            result.push(c);
+            result.push(COMBINING_TONES[mark]);
+        }
+    }
+}
+
+/// Convert space-separated Jyutping to Cantonese Yale, passing other tokens
+/// through unchanged (`nei5 hou2` → `néih hóu`)
+pub fn to_yale(jyutping: &str) -> String {
+    jyutping
+        .split_whitespace()
+        .map(|token| match Jyutping::parse(token) {
+            Some(syllable) => syllable.yale(),
+            None => token.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_initials_finals_and_syllabic_nasals() {
+        let parse = |token| Jyutping::parse(token).map(|j| (j.initial, j.final_, j.tone));
+
+        assert_eq!(parse("gwong2"), Some(("gw", "ong", 2)));
+        assert_eq!(parse("hoeng1"), Some(("h", "oeng", 1)));
+        assert_eq!(parse("ngo5"), Some(("ng", "o", 5)));
+        assert_eq!(parse("m4"), Some(("", "m", 4)));
+        assert_eq!(parse("ng5"), Some(("", "ng", 5)));
+
+        assert_eq!(parse("A4"), None);
+        assert_eq!(parse("hou7"), None);
+        assert_eq!(parse("hou"), None);
+        assert_eq!(parse("xyz1"), None);
+    }
+
+    #[test]
+    fn converts_to_yale() {
+        assert_eq!(to_yale("hoeng1 gong2"), "hēung góng");
+        assert_eq!(to_yale("jan4"), "yàhn");
+        assert_eq!(to_yale("ngo5"), "ngóh");
+        assert_eq!(to_yale("hai6"), "haih");
+        assert_eq!(to_yale("m4"), "m̀h");
+        assert_eq!(to_yale("jyu4"), "yùh");
+        assert_eq!(to_yale("zi2 coeng4 seoi2"), "jí chèuhng séui");
+        assert_eq!(to_yale("gwong2 dung1 waa2"), "gwóng dūng wá");
+    }
+
+    #[test]
+    fn passes_other_tokens_through() {
+        assert_eq!(to_yale("nei5 hou2"), "néih hóu");
+        assert_eq!(to_yale("A4 zi2"), "A4 jí");
+        assert_eq!(to_yale("kaa1 laai1 O K"), "kā lāai O K");
+    }
+}
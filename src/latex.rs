@@ -0,0 +1,93 @@
+//! LaTeX markup for the `latex` output format.
+//!
+//! Words are annotated with the `xpinyin` package (`\xpinyin{中国}{zhong1
+//! guo2}`, which typesets the tone marks itself) or with `\ruby{中}{zhōng}`
+//! per character for any annotation style. Text outside annotated words has
+//! the LaTeX special characters escaped. A full document adds a XeLaTeX
+//! preamble with xeCJK, so the output compiles as is.
+
+use serde::{Deserialize, Serialize};
+
+/// How annotated words are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LatexMarkup {
+    /// `\xpinyin{中国}{zhong1 guo2}`, for the pinyin styles; other styles
+    /// fall back to `\ruby`
+    #[default]
+    Xpinyin,
+    /// `\ruby{中}{zhōng}\ruby{国}{guó}`, compatible with the `ruby` package
+    Ruby,
+}
+
+/// `\ruby{base}{reading}` and `\doubleruby{base}{above}{below}` (for the
+/// `both` style), defined only where no package provides them
+const RUBY_COMMANDS: &str = r"\providecommand{\ruby}[2]{\leavevmode\begin{tabular}[b]{@{}c@{}}{\scriptsize #2}\\[-0.6ex]#1\end{tabular}}
+\providecommand{\doubleruby}[3]{\leavevmode\begin{tabular}[c]{@{}c@{}}{\scriptsize #2}\\[-0.6ex]#1\\[-0.6ex]{\scriptsize #3}\end{tabular}}
+";
+
+/// Escape LaTeX special characters in running text
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '^' => escaped.push_str(r"\textasciicircum{}"),
+            '~' => escaped.push_str(r"\textasciitilde{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Wrap annotated body markup in a XeLaTeX document using xeCJK, with the
+/// CJK font for the given script
+pub fn document(body: &str, traditional: bool) -> String {
+    let font = if traditional {
+        "Noto Serif CJK TC"
+    } else {
+        "Noto Serif CJK SC"
+    };
+
+    let mut tex = String::new();
+    tex.push_str("\\documentclass{article}\n");
+    tex.push_str("\\usepackage{xeCJK}\n");
+    tex.push_str("\\usepackage{xpinyin}\n");
+    tex.push_str(&format!("\\setCJKmainfont{{{}}}\n", font));
+    tex.push_str("\\linespread{1.8}\n");
+    tex.push_str(RUBY_COMMANDS);
+    tex.push_str("\n\\begin{document}\n\n");
+    tex.push_str(body.trim_end());
+    tex.push_str("\n\n\\end{document}\n");
+    tex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(
+            escape(r"\ { } $ & # _ % ^ ~"),
+            r"\textbackslash{} \{ \} \$ \& \# \_ \% \textasciicircum{} \textasciitilde{}"
+        );
+        assert_eq!(escape("中国 100% OK"), r"中国 100\% OK");
+    }
+
+    #[test]
+    fn documents_pick_the_font_for_the_script() {
+        let tex = document("\\xpinyin{中国}{zhong1 guo2}\n\n", false);
+
+        assert!(tex.starts_with("\\documentclass{article}\n\\usepackage{xeCJK}\n"));
+        assert!(tex.contains("\\setCJKmainfont{Noto Serif CJK SC}\n"));
+        assert!(tex.contains(RUBY_COMMANDS));
+        assert!(
+            tex.ends_with("\\begin{document}\n\n\\xpinyin{中国}{zhong1 guo2}\n\n\\end{document}\n")
+        );
+        assert!(document("", true).contains("\\setCJKmainfont{Noto Serif CJK TC}\n"));
+    }
+}
@@ -0,0 +1,366 @@
+//! Proficiency levels (HSK 2.0, HSK 3.0, TOCFL) and known-word lists.
+//!
+//! Level lists are plain text in the shapes they are usually published in:
+//! one word per line with the level in the file name (`HSK4.txt`), or a
+//! word and a level column (`爱<TAB>1`, `爱,HSK1`, `愛,A1`, `爱 7-9`). Other
+//! columns such as pinyin or glosses are ignored; when several columns read
+//! as a level, the last one counts. A word listed at several levels keeps
+//! the lowest.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// The proficiency scale a level list belongs to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LevelSystem {
+    /// HSK 2.0, levels 1-6
+    Hsk2,
+    /// HSK 3.0, levels 1-6 and the advanced band as 7 (levels 7-9)
+    #[default]
+    Hsk3,
+    /// TOCFL, 0 for the novice band and levels 1-6 (A1-C2)
+    Tocfl,
+}
+
+impl LevelSystem {
+    pub fn name(self) -> &'static str {
+        match self {
+            LevelSystem::Hsk2 => "HSK 2.0",
+            LevelSystem::Hsk3 => "HSK 3.0",
+            LevelSystem::Tocfl => "TOCFL",
+        }
+    }
+
+    /// Display label of a level, e.g. `HSK 3`, `HSK 7-9`, `TOCFL B1`
+    pub fn label(self, level: u8) -> String {
+        match (self, level) {
+            (LevelSystem::Hsk3, 7..) => "HSK 7-9".to_string(),
+            (LevelSystem::Hsk2 | LevelSystem::Hsk3, _) => format!("HSK {}", level),
+            (LevelSystem::Tocfl, 0) => "TOCFL Novice".to_string(),
+            (LevelSystem::Tocfl, _) => {
+                let band = ["A1", "A2", "B1", "B2", "C1", "C2"];
+                match band.get(usize::from(level) - 1) {
+                    Some(band) => format!("TOCFL {}", band),
+                    None => format!("TOCFL {}", level),
+                }
+            }
+        }
+    }
+
+    /// The levels of the scale, lowest first
+    pub fn levels(self) -> std::ops::RangeInclusive<u8> {
+        match self {
+            LevelSystem::Hsk2 => 1..=6,
+            LevelSystem::Hsk3 => 1..=7,
+            LevelSystem::Tocfl => 0..=6,
+        }
+    }
+}
+
+/// Words and their level on one proficiency scale
+#[derive(Debug, Clone, Default)]
+pub struct LevelList {
+    system: LevelSystem,
+    words: HashMap<String, u8>,
+}
+
+impl LevelList {
+    pub fn new(system: LevelSystem) -> Self {
+        Self {
+            system,
+            words: HashMap::new(),
+        }
+    }
+
+    pub fn system(&self) -> LevelSystem {
+        self.system
+    }
+
+    /// Add the words of a level list file; lines without a level column
+    /// take the last number in the file name. Returns the number of words
+    /// read.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read level list {}", path.display()))?;
+        let file_level = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(level_from_file_name);
+        Ok(self.parse(&text, file_level))
+    }
+
+    /// Add the words of a level list; `default_level` is used for lines
+    /// without a level column. Returns the number of words read.
+    pub fn parse(&mut self, text: &str, default_level: Option<u8>) -> usize {
+        let mut count = 0;
+        for line in text.lines() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = split_fields(line);
+            let Some(word) = fields.next() else {
+                continue;
+            };
+            // Level columns come after pinyin, which can look like one (`a1`)
+            let level = fields.filter_map(|field| parse_level(field, self.system));
+            let Some(level) = level.last().or(default_level) else {
+                continue;
+            };
+            // Levels past the scale join its top band (HSK 8 and 9 are 7-9)
+            let range = self.system.levels();
+            let level = level.clamp(*range.start(), *range.end());
+
+            for word in headwords(word) {
+                let entry = self.words.entry(word).or_insert(level);
+                *entry = (*entry).min(level);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// The level of a word
+    pub fn level(&self, word: &str) -> Option<u8> {
+        self.words.get(word).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+/// Words the learner already knows
+#[derive(Debug, Clone, Default)]
+pub struct KnownWords {
+    words: HashSet<String>,
+}
+
+impl KnownWords {
+    /// Read a word list: the first column of each line, so flashcard
+    /// exports and Pleco or Anki vocabulary lists can be used as they are
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read known words {}", path.display()))?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let words = text
+            .lines()
+            .map(|line| line.trim_start_matches('\u{feff}').trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| split_fields(line).next())
+            .flat_map(headwords)
+            .collect();
+        Self { words }
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+/// Columns of a list line: tab-separated, comma-separated or, failing
+/// both, whitespace-separated
+fn split_fields(line: &str) -> Box<dyn Iterator<Item = &str> + '_> {
+    let fields: Box<dyn Iterator<Item = &str>> = if line.contains('\t') {
+        Box::new(line.split('\t'))
+    } else if line.contains([',', '，']) {
+        Box::new(line.split([',', '，']))
+    } else {
+        Box::new(line.split_whitespace())
+    };
+    Box::new(fields.map(str::trim).filter(|field| !field.is_empty()))
+}
+
+/// The written forms in a headword column: variants separated by `/` or
+/// `|` (`爱/愛`), without part-of-speech notes in parentheses or the sense
+/// numbers some lists append (`打1`)
+fn headwords(field: &str) -> Vec<String> {
+    field
+        .split(['/', '|', '／', '｜'])
+        .map(|word| {
+            let word = word.split(['(', '（']).next().unwrap_or_default();
+            word.trim()
+                .trim_end_matches(|c: char| c.is_ascii_digit())
+                .trim()
+        })
+        .filter(|word| !word.is_empty() && !word.is_ascii())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read a level column: `3`, `HSK3`, `7-9`, `Level 2`, `三级`, `高等`, the
+/// TOCFL band names and `Novice`, and on TOCFL lists the bands `A1`-`C2`
+/// (elsewhere those are pinyin)
+fn parse_level(field: &str, system: LevelSystem) -> Option<u8> {
+    let lower = field.to_lowercase();
+    let mut level = lower.as_str();
+    for prefix in ["hsk", "tocfl", "level", "band"] {
+        level = level.strip_prefix(prefix).unwrap_or(level).trim_start();
+    }
+
+    if let [band @ b'a'..=b'c', digit @ b'1'..=b'2'] = level.as_bytes() {
+        return (system == LevelSystem::Tocfl).then(|| (band - b'a') * 2 + (digit - b'0'));
+    }
+
+    let level = level.trim_end_matches(['级', '級']);
+    let digits = level.split('-').next().unwrap_or_default();
+    if !digits.is_empty() && level.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return digits.parse().ok().filter(|&level| level <= 9);
+    }
+
+    const NUMERALS: [char; 9] = ['一', '二', '三', '四', '五', '六', '七', '八', '九'];
+    let mut chars = level.chars();
+    if let (Some(numeral), None) = (chars.next(), chars.next()) {
+        if let Some(index) = NUMERALS.iter().position(|&n| n == numeral) {
+            return Some(index as u8 + 1);
+        }
+    }
+
+    const NAMES: [(&str, u8); 9] = [
+        ("novice", 0),
+        ("準備", 0),
+        ("入門", 1),
+        ("基礎", 2),
+        ("進階", 3),
+        ("高階", 4),
+        ("流利", 5),
+        ("精通", 6),
+        ("高等", 7),
+    ];
+    NAMES
+        .iter()
+        .find(|(name, _)| level.starts_with(name))
+        .map(|&(_, level)| level)
+}
+
+/// The level in a file name such as `HSK4` or `tocfl-level-2`: its last
+/// number, when that is a level
+fn level_from_file_name(stem: &str) -> Option<u8> {
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    stem[start..end]
+        .parse()
+        .ok()
+        .filter(|level| (1..=9).contains(level))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(system: LevelSystem, text: &str, default_level: Option<u8>) -> LevelList {
+        let mut list = LevelList::new(system);
+        list.parse(text, default_level);
+        list
+    }
+
+    #[test]
+    fn reads_level_columns_in_their_usual_spellings() {
+        let hsk = LevelSystem::Hsk3;
+        assert_eq!(parse_level("3", hsk), Some(3));
+        assert_eq!(parse_level("HSK3", hsk), Some(3));
+        assert_eq!(parse_level("hsk 3", hsk), Some(3));
+        assert_eq!(parse_level("Level 2", hsk), Some(2));
+        assert_eq!(parse_level("7-9", hsk), Some(7));
+        assert_eq!(parse_level("三级", hsk), Some(3));
+        assert_eq!(parse_level("高等", hsk), Some(7));
+        assert_eq!(parse_level("10", hsk), None);
+        assert_eq!(parse_level("ài", hsk), None);
+
+        // Bands only read as levels on TOCFL lists; elsewhere they are pinyin
+        assert_eq!(parse_level("A1", LevelSystem::Tocfl), Some(1));
+        assert_eq!(parse_level("B2", LevelSystem::Tocfl), Some(4));
+        assert_eq!(parse_level("C2", LevelSystem::Tocfl), Some(6));
+        assert_eq!(parse_level("Novice", LevelSystem::Tocfl), Some(0));
+        assert_eq!(parse_level("進階", LevelSystem::Tocfl), Some(3));
+        assert_eq!(parse_level("a1", hsk), None);
+    }
+
+    #[test]
+    fn splits_headword_variants_and_drops_notes() {
+        assert_eq!(headwords("爱/愛"), ["爱", "愛"]);
+        assert_eq!(headwords("打1"), ["打"]);
+        assert_eq!(headwords("会（助动）"), ["会"]);
+        assert_eq!(headwords("OK"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn takes_the_level_from_file_names() {
+        assert_eq!(level_from_file_name("HSK4"), Some(4));
+        assert_eq!(level_from_file_name("tocfl-level-2"), Some(2));
+        assert_eq!(level_from_file_name("hsk3-2021-level-5"), Some(5));
+        assert_eq!(level_from_file_name("words"), None);
+        assert_eq!(level_from_file_name("list10"), None);
+    }
+
+    #[test]
+    fn parses_lists_with_and_without_a_level_column() {
+        let hsk4 = list(LevelSystem::Hsk3, "\u{feff}# HSK 4\n爱\n爸爸\n", Some(4));
+        assert_eq!(hsk4.level("爱"), Some(4));
+        assert_eq!(hsk4.level("爸爸"), Some(4));
+        assert_eq!(hsk4.len(), 2);
+
+        let columns = list(LevelSystem::Hsk3, "爱,HSK1\n爸爸\tbàba\t2\n", None);
+        assert_eq!(columns.level("爱"), Some(1));
+        assert_eq!(columns.level("爸爸"), Some(2));
+
+        // Without a level column or a file level the line is skipped
+        assert!(list(LevelSystem::Hsk3, "爱\n", None).is_empty());
+    }
+
+    #[test]
+    fn reads_bands_only_on_tocfl_lists() {
+        let tocfl = list(LevelSystem::Tocfl, "愛\tA1\n", None);
+        assert_eq!(tocfl.level("愛"), Some(1));
+
+        // On HSK lists `a1` is the pinyin of 爱, so the number column counts
+        let hsk = list(LevelSystem::Hsk2, "爱\ta1\t3\n", None);
+        assert_eq!(hsk.level("爱"), Some(3));
+        assert!(list(LevelSystem::Hsk2, "爱\ta1\n", None).is_empty());
+    }
+
+    #[test]
+    fn clamps_levels_to_the_scale_and_keeps_the_lowest() {
+        let hsk3 = list(LevelSystem::Hsk3, "爱 7-9\n笔 9\n书 5\n书 2\n", None);
+        assert_eq!(hsk3.level("爱"), Some(7));
+        assert_eq!(hsk3.level("笔"), Some(7));
+        assert_eq!(hsk3.level("书"), Some(2));
+        assert_eq!(LevelSystem::Hsk3.label(7), "HSK 7-9");
+
+        let hsk2 = list(LevelSystem::Hsk2, "爱 7-9\n", None);
+        assert_eq!(hsk2.level("爱"), Some(6));
+        assert_eq!(LevelSystem::Tocfl.label(3), "TOCFL B1");
+        assert_eq!(LevelSystem::Tocfl.label(0), "TOCFL Novice");
+    }
+
+    #[test]
+    fn known_words_take_the_first_column() {
+        let known = KnownWords::parse("#known\n爱\tài\tlove\n中国/中國,zhōngguó\n\n");
+        assert!(known.contains("爱"));
+        assert!(known.contains("中國"));
+        assert!(!known.contains("ài"));
+        assert_eq!(known.len(), 3);
+    }
+}
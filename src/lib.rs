@@ -1,5 +1,22 @@
+pub mod binary_format;
 pub mod dictionary;
+pub mod epub;
+pub mod flashcards;
+pub mod html;
+pub mod import;
+pub mod ipa;
+pub mod jyutping;
+pub mod latex;
+pub mod levels;
+pub mod markup;
+pub mod pinyin;
+pub mod polyphone;
 pub mod production_annotator;
+pub mod report;
+pub mod romanization;
+pub mod sandhi;
+pub mod segmenter;
+pub mod subtitles;
 
 // Re-export the external normalizer for convenience
 pub use zho_text_normalizer::types::{ChangeType, NormalizationConfig, TextChange};
@@ -1,9 +1,16 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Arg, ArgMatches, Command};
 use std::io::{self, Read};
+use std::path::Path;
+use zho_annotator::flashcards::{self, CardOrder, Dedupe, FlashcardFormat};
+use zho_annotator::levels::{KnownWords, LevelList, LevelSystem};
+use zho_annotator::polyphone::PolyphoneRules;
 use zho_annotator::production_annotator::{
-    AnnotationConfig, AnnotationStyle, OutputFormat, ProductionAnnotator,
+    AnnotationConfig, AnnotationStyle, HtmlTheme, LatexMarkup, OutputFormat, ProductionAnnotator,
+    RubyLayout, SegmenterKind, SubtitleMode, ToneNotation,
 };
+use zho_annotator::report;
+use zho_annotator::subtitles::SubtitleFormat;
 use zho_annotator::{Script, TextNormalizer};
 
 fn main() -> Result<()> {
@@ -47,14 +54,140 @@ fn main() -> Result<()> {
                 .long("format")
                 .value_name("FORMAT")
                 .default_value("inline")
-                .help("Output format: inline, json, brackets, ruby, table, rows"),
+                .help(
+                    "Output format: inline, json, brackets, ruby, table, rows, html, epub, latex",
+                ),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("Write the annotated result to a file instead of the terminal"),
         )
         .arg(
             Arg::new("style")
                 .long("style")
                 .value_name("STYLE")
                 .default_value("pinyin")
-                .help("Annotation style: pinyin, zhuyin, both"),
+                .help(
+                    "Annotation style: pinyin, zhuyin, both, numbered, toneless, first-letter, \
+                     wade-giles, yale, gwoyeu-romatzyh, tongyong, mps2, ipa, \
+                     jyutping, cantonese-yale (Cantonese styles need a dictionary built \
+                     with Cantonese readings)",
+                ),
+        )
+        .arg(
+            Arg::new("ipa-tones")
+                .long("ipa-tones")
+                .value_name("NOTATION")
+                .default_value("letters")
+                .help("IPA tone notation: letters (˨˩˦), numbers (²¹⁴)"),
+        )
+        .arg(
+            Arg::new("ruby-layout")
+                .long("ruby-layout")
+                .value_name("LAYOUT")
+                .default_value("character")
+                .help(
+                    "Ruby markup for multi-character words: character (one <rt> per \
+                     character), word (<rb>/<rt> pairs grouped per word)",
+                ),
+        )
+        .arg(
+            Arg::new("tone-colors")
+                .long("tone-colors")
+                .help("Color characters and readings by tone in HTML output (Pleco colors)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("html-theme")
+                .long("html-theme")
+                .value_name("THEME")
+                .default_value("light")
+                .help("Page colors of HTML output: light, dark, sepia"),
+        )
+        .arg(
+            Arg::new("toggle-readings")
+                .long("toggle-readings")
+                .help("Hide readings in HTML output until a word is clicked")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("title")
+                .long("title")
+                .value_name("TITLE")
+                .help("Title of HTML and EPUB output [default: first line of the text]"),
+        )
+        .arg(
+            Arg::new("latex-markup")
+                .long("latex-markup")
+                .value_name("MARKUP")
+                .default_value("xpinyin")
+                .help(
+                    "LaTeX markup for annotated words: xpinyin (\\xpinyin, pinyin styles), \
+                     ruby (\\ruby per character, any style)",
+                ),
+        )
+        .arg(
+            Arg::new("latex-document")
+                .long("latex-document")
+                .help("Wrap LaTeX output in a compilable XeLaTeX document using xeCJK")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("segmenter")
+                .long("segmenter")
+                .value_name("SEGMENTER")
+                .default_value("forward")
+                .help("Word segmentation: forward, backward, bidirectional, max-probability"),
+        )
+        .arg(
+            Arg::new("polyphone-rules")
+                .long("polyphone-rules")
+                .value_name("PATH")
+                .help("JSON file of polyphone rules, replacing the built-in rules"),
+        )
+        .arg(
+            Arg::new("levels")
+                .long("levels")
+                .value_name("PATH")
+                .action(clap::ArgAction::Append)
+                .help(
+                    "HSK or TOCFL word list to tag words with levels (may be given more than \
+                     once)",
+                ),
+        )
+        .arg(
+            Arg::new("level-system")
+                .long("level-system")
+                .value_name("SYSTEM")
+                .default_value("hsk3")
+                .help("Scale of the --levels lists: hsk2, hsk3, tocfl"),
+        )
+        .arg(
+            Arg::new("annotate-above-level")
+                .long("annotate-above-level")
+                .value_name("N")
+                .help("Only annotate words above level N (needs --levels)"),
+        )
+        .arg(
+            Arg::new("known-words")
+                .long("known-words")
+                .value_name("PATH")
+                .help("Word list (first column per line) of words to leave unannotated"),
+        )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .help("Explain how each polyphone reading was chosen")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("tone-sandhi")
+                .long("tone-sandhi")
+                .help("Show spoken tones: 一/不 changes and third-tone sandhi")
+                .action(clap::ArgAction::SetTrue),
         )
         .arg(
             Arg::new("confidence")
@@ -75,6 +208,76 @@ fn main() -> Result<()> {
                 .help("Show confidence scores")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("show-definitions")
+                .long("show-definitions")
+                .help("Show English definitions (JSON, table and ruby tooltips)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("input-format")
+                .long("input-format")
+                .value_name("FORMAT")
+                .help(
+                    "Input markup: text, html (annotate text nodes only), markdown (annotate \
+                     prose only) [default: from the --file extension, else text]",
+                ),
+        )
+        .arg(
+            Arg::new("subtitles")
+                .long("subtitles")
+                .value_name("FORMAT")
+                .help(
+                    "Read the input as subtitles: srt, vtt, ass [default: from the --file \
+                     extension]",
+                ),
+        )
+        .arg(
+            Arg::new("subtitle-mode")
+                .long("subtitle-mode")
+                .value_name("MODE")
+                .default_value("line")
+                .help(
+                    "Where subtitle readings go: line (under the text), ruby (WebVTT), \
+                     track (separate ASS style)",
+                ),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .help(
+                    "Report on the text instead of annotating it: counts, level distribution, \
+                     readability (a table, or JSON with --format json)",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("top")
+                .long("top")
+                .value_name("N")
+                .default_value("20")
+                .help("Number of frequent words in the report"),
+        )
+        .arg(
+            Arg::new("export")
+                .long("export")
+                .value_name("FORMAT")
+                .help("Export the vocabulary as flashcards instead: anki (TSV), pleco"),
+        )
+        .arg(
+            Arg::new("dedupe")
+                .long("dedupe")
+                .value_name("MODE")
+                .default_value("word")
+                .help("One flashcard per: word, reading (word and pinyin), none (occurrence)"),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .value_name("ORDER")
+                .default_value("appearance")
+                .help("Flashcard order: appearance, frequency, pinyin"),
+        )
         .arg(
             Arg::new("traditional")
                 .long("traditional")
@@ -98,9 +301,35 @@ fn main() -> Result<()> {
     let config = parse_config(&matches)?;
     let dict_path = matches.get_one::<String>("dict").unwrap();
 
+    if matches!(config.output_format, OutputFormat::Epub) && !matches.contains_id("output") {
+        eprintln!("❌ Error: EPUB output needs a file, use --output book.epub");
+        return Ok(());
+    }
+
     // Initialize annotator
     println!("🚀 Initializing Chinese Text Annotator...");
-    let annotator = ProductionAnnotator::new(dict_path, config.clone())?;
+    let mut annotator = ProductionAnnotator::new(dict_path, config.clone())?;
+    if let Some(path) = matches.get_one::<String>("polyphone-rules") {
+        let rules = PolyphoneRules::load(path)?;
+        println!("📏 Loaded {} polyphone rules from {}", rules.len(), path);
+        annotator = annotator.with_polyphone_rules(rules);
+    }
+    if let Some(paths) = matches.get_many::<String>("levels") {
+        let system = parse_level_system(&matches);
+        let mut levels = LevelList::new(system);
+        for path in paths {
+            let count = levels.load(path)?;
+            println!("🎓 Loaded {} {} words from {}", count, system.name(), path);
+        }
+        annotator = annotator.with_levels(levels);
+    } else if config.annotate_above_level.is_some() {
+        eprintln!("❌ --annotate-above-level needs a level list, use --levels");
+    }
+    if let Some(path) = matches.get_one::<String>("known-words") {
+        let known_words = KnownWords::load(path)?;
+        println!("✅ Loaded {} known words from {}", known_words.len(), path);
+        annotator = annotator.with_known_words(known_words);
+    }
     let (dict_entries, model_info) = annotator.get_stats();
 
     println!("✅ Ready! Dictionary: {} entries", dict_entries);
@@ -117,37 +346,132 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    // Apply text normalization if requested
-    println!("🔧 Applying text normalization...");
     let normalizer = TextNormalizer::new();
-    let target_script = if config.use_traditional {
-        Some(Script::TraditionalChinese)
-    } else {
-        Some(Script::SimplifiedChinese)
+    let normalize = |text: &str| normalize_text(&normalizer, text, config.use_traditional);
+
+    // Marked-up input is annotated in place, normalizing only its text
+    let input_format = match matches.get_one::<String>("input-format") {
+        Some(name) => name.clone(),
+        None => matches
+            .get_one::<String>("file")
+            .and_then(|file| Path::new(file).extension())
+            .and_then(|extension| extension.to_str())
+            .map(|extension| match extension.to_lowercase().as_str() {
+                "html" | "htm" | "xhtml" => "html".to_string(),
+                "md" | "markdown" => "markdown".to_string(),
+                _ => "text".to_string(),
+            })
+            .unwrap_or_else(|| "text".to_string()),
     };
-    let normalized = normalizer.normalize(&input_text, target_script);
-
-    if !normalized.changes.is_empty() {
-        println!("📝 Normalization changes:");
-        for change in &normalized.changes {
-            println!(
-                "  {} → {} ({})",
-                change.original_char, change.normalized_char, change.reason
+    // Reports, flashcards and books are built from plain text, while marked-up
+    // input is written back with its markup
+    let text_only = matches.get_flag("report")
+        || matches.contains_id("export")
+        || matches!(config.output_format, OutputFormat::Epub);
+    match input_format.as_str() {
+        "text" => {}
+        "html" | "markdown" if text_only => {
+            eprintln!(
+                "❌ Error: --report, --export and --format epub need text input, not {}",
+                input_format
             );
+            return Ok(());
         }
-        input_text = normalized.normalized;
-    } else {
+        "html" => {
+            println!("\n🌐 Annotating HTML text nodes...");
+            let output = annotator.annotate_html(&input_text, normalize)?;
+            write_output(matches.get_one::<String>("output"), &output)?;
+            return Ok(());
+        }
+        "markdown" => {
+            println!("\n📝 Annotating Markdown prose...");
+            let output = annotator.annotate_markdown(&input_text, normalize)?;
+            write_output(matches.get_one::<String>("output"), &output)?;
+            return Ok(());
+        }
+        _ => {
+            eprintln!("❌ Error: Unknown input format '{}'", input_format);
+            return Ok(());
+        }
+    }
+
+    // Subtitles keep their own layout and skip the regular output formats;
+    // only their dialogue is normalized
+    let subtitle_format = match matches.get_one::<String>("subtitles") {
+        Some(name) => match SubtitleFormat::from_name(name) {
+            Some(format) => Some(format),
+            None => {
+                eprintln!("❌ Error: Unknown subtitle format '{}'", name);
+                return Ok(());
+            }
+        },
+        None => matches
+            .get_one::<String>("file")
+            .and_then(SubtitleFormat::from_path),
+    };
+    if let Some(format) = subtitle_format {
+        println!("\n🎬 Annotating {} subtitles...", format.name());
+        let output = annotator.annotate_subtitles(&input_text, format, normalize)?;
+        write_output(matches.get_one::<String>("output"), &output)?;
+        return Ok(());
+    }
+
+    // Apply text normalization if requested
+    println!("🔧 Applying text normalization...");
+    let normalized = normalize(&input_text);
+    if normalized == input_text {
         println!("✅ No normalization needed");
     }
+    input_text = normalized;
 
     // Annotate text
     println!("\n🔤 Processing text...");
     let segments = annotator.annotate(&input_text)?;
 
     // Output results
-    let output = annotator.format_output(&segments);
-    println!("\n📝 Annotated Result:");
-    println!("{}", output);
+    let output_path = matches.get_one::<String>("output");
+    if matches.get_flag("report") {
+        let top = matches
+            .get_one::<String>("top")
+            .unwrap()
+            .parse()
+            .unwrap_or_else(|_| {
+                eprintln!("❌ Invalid number of top words. Using 20");
+                20
+            });
+        let report = report::build(&segments, annotator.levels(), top);
+        println!("\n📋 Text report");
+        let output = match config.output_format {
+            OutputFormat::Json => report.to_json(),
+            _ => report.to_table(),
+        };
+        write_output(output_path, &output)?;
+    } else if let Some(name) = matches.get_one::<String>("export") {
+        let format = match name.as_str() {
+            "anki" => FlashcardFormat::Anki,
+            "pleco" => FlashcardFormat::Pleco,
+            _ => {
+                eprintln!("❌ Error: Unknown flashcard format '{}'", name);
+                return Ok(());
+            }
+        };
+        let (dedupe, order) = parse_flashcard_options(&matches);
+        let mut cards = flashcards::collect(&segments, dedupe);
+        flashcards::sort(&mut cards, order);
+        println!("\n🗂️  {} flashcards", cards.len());
+        write_output(output_path, &flashcards::render(&cards, format))?;
+    } else if let (OutputFormat::Epub, Some(path)) = (&config.output_format, output_path) {
+        let book = annotator.format_epub(&segments)?;
+        std::fs::write(path, book).with_context(|| format!("Failed to write {}", path))?;
+        println!("\n📚 EPUB written to {}", path);
+    } else {
+        write_output(output_path, &annotator.format_output(&segments))?;
+    }
+
+    if config.explain && !matches!(config.output_format, OutputFormat::Json) {
+        println!("\n🔍 Reading decisions:");
+        print!("{}", annotator.format_decisions(&segments));
+    }
 
     // Show statistics
     let chinese_segments = segments.iter().filter(|s| s.is_chinese).count();
@@ -183,6 +507,28 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Normalize text to the target script, listing what changed
+fn normalize_text(normalizer: &TextNormalizer, text: &str, use_traditional: bool) -> String {
+    let target_script = if use_traditional {
+        Some(Script::TraditionalChinese)
+    } else {
+        Some(Script::SimplifiedChinese)
+    };
+    let normalized = normalizer.normalize(text, target_script);
+    if normalized.changes.is_empty() {
+        return text.to_string();
+    }
+
+    println!("📝 Normalization changes:");
+    for change in &normalized.changes {
+        println!(
+            "  {} → {} ({})",
+            change.original_char, change.normalized_char, change.reason
+        );
+    }
+    normalized.normalized
+}
+
 fn parse_config(matches: &ArgMatches) -> Result<AnnotationConfig> {
     let output_format = match matches.get_one::<String>("format").unwrap().as_str() {
         "inline" => OutputFormat::Inline,
@@ -191,6 +537,9 @@ fn parse_config(matches: &ArgMatches) -> Result<AnnotationConfig> {
         "ruby" => OutputFormat::Ruby,
         "table" => OutputFormat::Table,
         "rows" => OutputFormat::Rows,
+        "html" => OutputFormat::Html,
+        "epub" => OutputFormat::Epub,
+        "latex" => OutputFormat::Latex,
         _ => {
             eprintln!("❌ Invalid format. Using 'inline'");
             OutputFormat::Inline
@@ -201,12 +550,92 @@ fn parse_config(matches: &ArgMatches) -> Result<AnnotationConfig> {
         "pinyin" => AnnotationStyle::Pinyin,
         "zhuyin" => AnnotationStyle::Zhuyin,
         "both" => AnnotationStyle::Both,
+        "numbered" => AnnotationStyle::PinyinNumbered,
+        "toneless" => AnnotationStyle::PinyinToneless,
+        "first-letter" => AnnotationStyle::PinyinFirstLetter,
+        "wade-giles" => AnnotationStyle::WadeGiles,
+        "yale" => AnnotationStyle::MandarinYale,
+        "gwoyeu-romatzyh" => AnnotationStyle::GwoyeuRomatzyh,
+        "tongyong" => AnnotationStyle::Tongyong,
+        "mps2" => AnnotationStyle::Mps2,
+        "ipa" => AnnotationStyle::Ipa,
+        "jyutping" => AnnotationStyle::Jyutping,
+        "cantonese-yale" => AnnotationStyle::Yale,
         _ => {
             eprintln!("❌ Invalid style. Using 'pinyin'");
             AnnotationStyle::Pinyin
         }
     };
 
+    let ipa_tones = match matches.get_one::<String>("ipa-tones").unwrap().as_str() {
+        "letters" => ToneNotation::Letters,
+        "numbers" => ToneNotation::Superscript,
+        _ => {
+            eprintln!("❌ Invalid IPA tone notation. Using 'letters'");
+            ToneNotation::Letters
+        }
+    };
+
+    let ruby_layout = match matches.get_one::<String>("ruby-layout").unwrap().as_str() {
+        "character" => RubyLayout::PerCharacter,
+        "word" => RubyLayout::Word,
+        _ => {
+            eprintln!("❌ Invalid ruby layout. Using 'character'");
+            RubyLayout::PerCharacter
+        }
+    };
+
+    let html_theme = match matches.get_one::<String>("html-theme").unwrap().as_str() {
+        "light" => HtmlTheme::Light,
+        "dark" => HtmlTheme::Dark,
+        "sepia" => HtmlTheme::Sepia,
+        _ => {
+            eprintln!("❌ Invalid HTML theme. Using 'light'");
+            HtmlTheme::Light
+        }
+    };
+
+    let latex_markup = match matches.get_one::<String>("latex-markup").unwrap().as_str() {
+        "xpinyin" => LatexMarkup::Xpinyin,
+        "ruby" => LatexMarkup::Ruby,
+        _ => {
+            eprintln!("❌ Invalid LaTeX markup. Using 'xpinyin'");
+            LatexMarkup::Xpinyin
+        }
+    };
+
+    let subtitle_mode = match matches.get_one::<String>("subtitle-mode").unwrap().as_str() {
+        "line" => SubtitleMode::Line,
+        "ruby" => SubtitleMode::Ruby,
+        "track" => SubtitleMode::Track,
+        _ => {
+            eprintln!("❌ Invalid subtitle mode. Using 'line'");
+            SubtitleMode::Line
+        }
+    };
+
+    let segmenter = match matches.get_one::<String>("segmenter").unwrap().as_str() {
+        "forward" => SegmenterKind::ForwardMaximum,
+        "backward" => SegmenterKind::BackwardMaximum,
+        "bidirectional" => SegmenterKind::Bidirectional,
+        "max-probability" => SegmenterKind::MaxProbability,
+        _ => {
+            eprintln!("❌ Invalid segmenter. Using 'forward'");
+            SegmenterKind::ForwardMaximum
+        }
+    };
+
+    let annotate_above_level =
+        matches
+            .get_one::<String>("annotate-above-level")
+            .and_then(|level| match level.parse::<u8>() {
+                Ok(level) => Some(level),
+                Err(_) => {
+                    eprintln!("❌ Invalid level. Annotating all levels");
+                    None
+                }
+            });
+
     let confidence_threshold: f32 = matches
         .get_one::<String>("confidence")
         .unwrap()
@@ -223,9 +652,74 @@ fn parse_config(matches: &ArgMatches) -> Result<AnnotationConfig> {
         show_alternatives: matches.get_flag("show-alternatives"),
         show_confidence: matches.get_flag("show-confidence"),
         use_traditional: matches.get_flag("traditional"),
+        show_definitions: matches.get_flag("show-definitions"),
+        segmenter,
+        explain: matches.get_flag("explain"),
+        tone_sandhi: matches.get_flag("tone-sandhi"),
+        ipa_tones,
+        ruby_layout,
+        tone_colors: matches.get_flag("tone-colors"),
+        html_theme,
+        toggle_readings: matches.get_flag("toggle-readings"),
+        title: matches.get_one::<String>("title").cloned(),
+        latex_markup,
+        latex_document: matches.get_flag("latex-document"),
+        subtitle_mode,
+        annotate_above_level,
     })
 }
 
+fn parse_level_system(matches: &ArgMatches) -> LevelSystem {
+    match matches.get_one::<String>("level-system").unwrap().as_str() {
+        "hsk2" => LevelSystem::Hsk2,
+        "hsk3" => LevelSystem::Hsk3,
+        "tocfl" => LevelSystem::Tocfl,
+        _ => {
+            eprintln!("❌ Invalid level system. Using 'hsk3'");
+            LevelSystem::Hsk3
+        }
+    }
+}
+
+fn parse_flashcard_options(matches: &ArgMatches) -> (Dedupe, CardOrder) {
+    let dedupe = match matches.get_one::<String>("dedupe").unwrap().as_str() {
+        "word" => Dedupe::Word,
+        "reading" => Dedupe::Reading,
+        "none" => Dedupe::None,
+        _ => {
+            eprintln!("❌ Invalid dedupe mode. Using 'word'");
+            Dedupe::Word
+        }
+    };
+
+    let order = match matches.get_one::<String>("sort").unwrap().as_str() {
+        "appearance" => CardOrder::Appearance,
+        "frequency" => CardOrder::Frequency,
+        "pinyin" => CardOrder::Pinyin,
+        _ => {
+            eprintln!("❌ Invalid sort order. Using 'appearance'");
+            CardOrder::Appearance
+        }
+    };
+
+    (dedupe, order)
+}
+
+/// Write the result to the `--output` file, or print it
+fn write_output(path: Option<&String>, output: &str) -> Result<()> {
+    match path {
+        Some(path) => {
+            std::fs::write(path, output).with_context(|| format!("Failed to write {}", path))?;
+            println!("\n💾 Annotated result written to {}", path);
+        }
+        None => {
+            println!("\n📝 Annotated Result:");
+            println!("{}", output);
+        }
+    }
+    Ok(())
+}
+
 fn get_input_text(matches: &ArgMatches) -> Result<String> {
     if let Some(text) = matches.get_one::<String>("text") {
         Ok(text.clone())
@@ -259,16 +753,30 @@ fn show_examples() {
     println!("🎯 Different Output Formats:");
     println!("  zho-annotator -t \"我爱中国\" --format json");
     println!("  zho-annotator -t \"我爱中国\" --format brackets");
-    println!("  zho-annotator -t \"我爱中国\" --format ruby\n");
+    println!("  zho-annotator -t \"我爱中国\" --format ruby");
+    println!("  zho-annotator -t \"我爱中国\" --format ruby --ruby-layout word\n");
 
     println!("🔤 Annotation Styles:");
     println!("  zho-annotator -t \"我爱中国\" --style pinyin");
     println!("  zho-annotator -t \"我爱中国\" --style zhuyin");
-    println!("  zho-annotator -t \"我爱中国\" --style both\n");
+    println!("  zho-annotator -t \"我爱中国\" --style both");
+    println!("  zho-annotator -t \"我爱中国\" --style numbered      # wo3 ai4 zhong1guo2");
+    println!("  zho-annotator -t \"我爱中国\" --style first-letter  # w a zg");
+    println!("  zho-annotator -t \"我爱中国\" --style wade-giles    # wo³ ai⁴ chung¹kuo²");
+    println!("  zho-annotator -t \"你好\" --style ipa                # ni˨˩˦xɑʊ˨˩˦\n");
+
+    println!("🇭🇰 Cantonese (needs a dictionary built with --cc-canto or --unihan):");
+    println!("  zho-annotator -t \"你好\" --style jyutping           # 你好(nei5hou2)");
+    println!("  zho-annotator -t \"你好\" --style cantonese-yale     # 你好(néihhóu)");
+    println!("  zho-annotator -t \"廣東話\" --style jyutping --format ruby\n");
+
+    println!("✂️  Segmentation (needs a dictionary built with --frequency):");
+    println!("  zho-annotator -t \"研究生命起源\" --segmenter max-probability\n");
 
     println!("⚙️  Advanced Options:");
     println!("  zho-annotator -t \"我爱中国\" --show-confidence --show-alternatives");
     println!("  zho-annotator -t \"我爱中国\" --confidence 0.7");
+    println!("  zho-annotator -t \"我爱中国\" --format table --show-definitions");
     println!("  zho-annotator -f input.txt --format table > output.tsv\n");
 
     println!("📄 File Processing:");
@@ -277,7 +785,44 @@ fn show_examples() {
     println!("  echo \"你好世界\" | zho-annotator --stdin --format json\n");
 
     println!("🎨 HTML Output:");
-    println!("  zho-annotator -t \"学习中文\" --format ruby > output.html\n");
+    println!("  zho-annotator -t \"学习中文\" --format ruby > output.html");
+    println!("  zho-annotator -f story.txt --format html --tone-colors -o story.html");
+    println!("  zho-annotator -f story.txt --format html --html-theme sepia --toggle-readings -o story.html\n");
+
+    println!("📐 LaTeX (XeLaTeX with xeCJK):");
+    println!(
+        "  zho-annotator -t \"中国\" --format latex            # \\xpinyin{{中国}}{{zhong1 guo2}}"
+    );
+    println!("  zho-annotator -t \"中国\" --format latex --latex-markup ruby --style zhuyin");
+    println!("  zho-annotator -f worksheet.txt --format latex --latex-document -o worksheet.tex\n");
+
+    println!("🌐 Annotating HTML and Markdown in place (tags, code and URLs are kept):");
+    println!("  zho-annotator -f page.html -o page.annotated.html");
+    println!("  zho-annotator -f export.xml --input-format html --format brackets");
+    println!("  zho-annotator -f lesson.md --format ruby -o lesson.annotated.md\n");
+
+    println!("🎬 Subtitles (timing and styling are kept):");
+    println!("  zho-annotator -f episode.srt -o episode.zh-pinyin.srt");
+    println!("  zho-annotator -f episode.vtt --subtitle-mode ruby -o episode.ruby.vtt");
+    println!("  zho-annotator -f episode.ass --subtitle-mode track -o episode.pinyin.ass\n");
+
+    println!("🎓 Learner levels (readings only for new words):");
+    println!("  zho-annotator -f story.txt --levels hsk3.tsv --annotate-above-level 3");
+    println!("  zho-annotator -f story.txt --levels HSK1.txt --levels HSK2.txt --level-system hsk2 --annotate-above-level 2");
+    println!("  zho-annotator -f story.txt --known-words my-vocab.txt --format ruby\n");
+
+    println!("📋 Text Report (readability and vocabulary profile):");
+    println!("  zho-annotator -f article.txt --report --levels hsk3.tsv");
+    println!(
+        "  zho-annotator -f article.txt --report --format json --top 50 -o article-report.json\n"
+    );
+
+    println!("🗂️  Flashcards (vocabulary with an example sentence):");
+    println!("  zho-annotator -f lesson.txt --export anki -o lesson-vocab.tsv");
+    println!("  zho-annotator -f lesson.txt --export pleco --dedupe reading --sort pinyin -o vocab.txt\n");
+
+    println!("📚 E-books:");
+    println!("  zho-annotator -f novel.txt --format epub --title \"小说\" -o novel.epub\n");
 
     println!("📊 Analysis Mode:");
     println!("  zho-annotator -t \"复杂的句子\" --format table --show-confidence\n");
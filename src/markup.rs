@@ -0,0 +1,526 @@
+//! Annotating marked-up input in place.
+//!
+//! HTML documents are scanned into tags, comments and text; only text nodes
+//! are annotated, and everything else is copied byte for byte, so the
+//! document keeps its structure, attributes and formatting. Text inside
+//! `script`, `style`, `code`, existing `ruby` and a few other elements (see
+//! [`SKIPPED_ELEMENTS`]), or inside any element with `translate="no"`, is left
+//! alone.
+//!
+//! Markdown is handled the same way: prose is annotated, while front matter,
+//! fenced and indented code, inline code, HTML blocks and inline tags, link
+//! destinations, images, reference definitions and bare URLs are copied as
+//! they are.
+
+use anyhow::Result;
+
+/// Elements whose text is never annotated
+pub const SKIPPED_ELEMENTS: [&str; 13] = [
+    "script", "style", "code", "kbd", "samp", "pre", "ruby", "rt", "rp", "textarea", "title",
+    "svg", "math",
+];
+
+/// Elements whose content is raw text: no tags inside until the end tag
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "textarea", "title"];
+
+/// Elements without an end tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Annotate the text nodes of an HTML document or fragment, replacing each
+/// with what `annotate` returns for it. Whitespace-only text is kept as is.
+pub fn annotate_html<F>(html: &str, mut annotate: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut output = String::with_capacity(html.len() * 2);
+    // Open elements, and whether text inside them is left alone
+    let mut open: Vec<(String, bool)> = Vec::new();
+    let mut i = 0;
+
+    while i < html.len() {
+        let rest = &html[i..];
+
+        let Some(tag_len) = markup_len(rest) else {
+            // Text up to the next thing that looks like markup
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .filter(|&(_, c)| c == '<')
+                .map(|(pos, _)| pos)
+                .find(|&pos| markup_len(&rest[pos..]).is_some())
+                .unwrap_or(rest.len());
+            let text = &rest[..end];
+            let skipped = open.last().is_some_and(|&(_, skip)| skip);
+            if skipped || text.trim().is_empty() {
+                output.push_str(text);
+            } else {
+                output.push_str(&annotate(text)?);
+            }
+            i += end;
+            continue;
+        };
+
+        let tag = &rest[..tag_len];
+        output.push_str(tag);
+        i += tag_len;
+
+        if let Some(name) = end_tag_name(tag) {
+            if let Some(pos) = open.iter().rposition(|(open_name, _)| *open_name == name) {
+                open.truncate(pos);
+            }
+            continue;
+        }
+        let Some(name) = start_tag_name(tag) else {
+            continue; // comment, doctype or processing instruction
+        };
+        if VOID_ELEMENTS.contains(&name.as_str()) || tag.ends_with("/>") {
+            continue;
+        }
+
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            // Copy the content up to the end tag without looking inside
+            let content = &html[i..];
+            let end = find_ignore_case(content, &format!("</{}", name)).unwrap_or(content.len());
+            output.push_str(&content[..end]);
+            i += end;
+        }
+
+        let skip = open.last().is_some_and(|&(_, skip)| skip)
+            || SKIPPED_ELEMENTS.contains(&name.as_str())
+            || is_untranslated(tag);
+        open.push((name, skip));
+    }
+
+    Ok(output)
+}
+
+/// Length of the tag, comment, doctype or processing instruction at the
+/// start of `text`, or `None` if it does not start with markup
+fn markup_len(text: &str) -> Option<usize> {
+    if let Some(comment) = text.strip_prefix("<!--") {
+        return Some(comment.find("-->").map_or(text.len(), |end| end + 7));
+    }
+    let mut chars = text.chars();
+    if chars.next() != Some('<') {
+        return None;
+    }
+    match chars.next()? {
+        '!' | '?' => Some(text.find('>').map_or(text.len(), |end| end + 1)),
+        '/' if chars.next()?.is_ascii_alphabetic() => {
+            Some(text.find('>').map_or(text.len(), |end| end + 1))
+        }
+        c if c.is_ascii_alphabetic() => Some(start_tag_len(text)),
+        _ => None,
+    }
+}
+
+/// Length of a start tag, where `>` inside quoted attribute values does not
+/// end the tag
+fn start_tag_len(text: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Lowercase element name of a start tag
+fn start_tag_name(tag: &str) -> Option<String> {
+    let name = tag.strip_prefix('<')?;
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(element_name(name))
+}
+
+/// Lowercase element name of an end tag
+fn end_tag_name(tag: &str) -> Option<String> {
+    Some(element_name(tag.strip_prefix("</")?))
+}
+
+fn element_name(text: &str) -> String {
+    text.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == ':')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Whether a start tag has `translate="no"`
+fn is_untranslated(tag: &str) -> bool {
+    let tag = tag.to_ascii_lowercase();
+    ["translate=\"no\"", "translate='no'", "translate=no"]
+        .iter()
+        .any(|attribute| tag.contains(attribute))
+}
+
+/// Byte position of `needle` in `haystack`, ignoring ASCII case
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    let needle = needle.as_bytes();
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// Annotate the prose of a Markdown document, replacing each run of text
+/// with what `annotate` returns for it
+pub fn annotate_markdown<F>(markdown: &str, mut annotate: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut output = String::with_capacity(markdown.len() * 2);
+    let lines: Vec<&str> = markdown.split_inclusive('\n').collect();
+    let mut i = 0;
+
+    // YAML (`---`) or TOML (`+++`) front matter
+    if let Some(marker) = lines
+        .first()
+        .map(|line| line.trim_end())
+        .filter(|line| *line == "---" || *line == "+++")
+    {
+        if let Some(end) = lines[1..].iter().position(|line| line.trim_end() == marker) {
+            i = end + 2;
+            output.extend(lines[..i].iter().copied());
+        }
+    }
+
+    let mut previous_blank = true;
+    let mut in_list = false;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+
+        if line.trim().is_empty() {
+            output.push_str(line);
+            previous_blank = true;
+            i += 1;
+            continue;
+        }
+
+        // Fenced code block: up to a closing fence of the same kind
+        if let Some(fence) = code_fence(trimmed).filter(|_| indent < 4) {
+            output.push_str(line);
+            i += 1;
+            while i < lines.len() {
+                output.push_str(lines[i]);
+                i += 1;
+                let closing = lines[i - 1].trim();
+                if closing.starts_with(&fence)
+                    && closing
+                        .trim_start_matches(fence.chars().next().unwrap())
+                        .is_empty()
+                {
+                    break;
+                }
+            }
+            previous_blank = false;
+            continue;
+        }
+
+        // Indented code block, unless it continues a list item
+        let indented = indent >= 4 || line.starts_with('\t');
+        if indented && previous_blank && !in_list {
+            while i < lines.len()
+                && (lines[i].starts_with("    ")
+                    || lines[i].starts_with('\t')
+                    || lines[i].trim().is_empty())
+            {
+                output.push_str(lines[i]);
+                i += 1;
+            }
+            previous_blank = true;
+            continue;
+        }
+
+        // HTML block: up to the next blank line
+        if indent < 4 && is_html_block_start(trimmed) {
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                output.push_str(lines[i]);
+                i += 1;
+            }
+            previous_blank = false;
+            continue;
+        }
+
+        if !indented {
+            in_list = is_list_item(trimmed);
+        }
+        previous_blank = false;
+        i += 1;
+
+        // Link reference definition: `[id]: url "title"`
+        if indent < 4 && is_reference_definition(trimmed) {
+            output.push_str(line);
+            continue;
+        }
+
+        annotate_inline(line, &mut output, &mut annotate)?;
+    }
+
+    Ok(output)
+}
+
+/// The opening fence of a fenced code block: three or more backticks or tildes
+fn code_fence(line: &str) -> Option<String> {
+    let marker = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = line.chars().take_while(|&c| c == marker).count();
+    (len >= 3).then(|| marker.to_string().repeat(len))
+}
+
+/// Whether a line opens an HTML block: a tag or comment at the start
+fn is_html_block_start(line: &str) -> bool {
+    line.starts_with("<!--")
+        || markup_len(line).is_some()
+            && (line.starts_with("</")
+                || line.starts_with('<')
+                    && line[1..].starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+fn is_list_item(line: &str) -> bool {
+    if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        return rest.starts_with(' ');
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") "))
+}
+
+fn is_reference_definition(line: &str) -> bool {
+    line.starts_with('[')
+        && !line.starts_with("[^")
+        && line.contains("]:")
+        && line
+            .find("]:")
+            .is_some_and(|end| !line[1..end].contains(']'))
+}
+
+/// Annotate the text of one line of Markdown, copying inline code, HTML,
+/// autolinks, link destinations, images, escapes and bare URLs unchanged
+fn annotate_inline<F>(line: &str, output: &mut String, annotate: &mut F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < line.len() {
+        let rest = &line[i..];
+        let raw_len = inline_raw_len(rest);
+
+        // Link text is annotated; its destination is not
+        let link = if raw_len.is_none() && rest.starts_with('[') {
+            link_parts(rest)
+        } else {
+            None
+        };
+
+        if raw_len.is_none() && link.is_none() {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        }
+
+        push_text(&line[text_start..i], output, annotate)?;
+        match (raw_len, link) {
+            (Some(len), _) => {
+                output.push_str(&rest[..len]);
+                i += len;
+            }
+            (None, Some((text_len, len))) => {
+                output.push('[');
+                annotate_inline(&rest[1..1 + text_len], output, annotate)?;
+                output.push_str(&rest[1 + text_len..len]);
+                i += len;
+            }
+            (None, None) => unreachable!(),
+        }
+        text_start = i;
+    }
+    push_text(&line[text_start..], output, annotate)
+}
+
+/// Annotate a run of prose, keeping whitespace-only runs as they are
+fn push_text<F>(text: &str, output: &mut String, annotate: &mut F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    if text.trim().is_empty() {
+        output.push_str(text);
+    } else {
+        output.push_str(&annotate(text)?);
+    }
+    Ok(())
+}
+
+/// Length of inline Markdown at the start of `text` that is copied as is:
+/// a code span, an image, an autolink or HTML tag, an escape or a bare URL
+fn inline_raw_len(text: &str) -> Option<usize> {
+    if text.starts_with('`') {
+        let ticks = text.chars().take_while(|&c| c == '`').count();
+        let closing = "`".repeat(ticks);
+        return Some(
+            text[ticks..]
+                .find(&closing)
+                .map_or(ticks, |end| ticks + end + ticks),
+        );
+    }
+    if text.starts_with("![") {
+        return link_parts(&text[1..]).map(|(_, len)| len + 1);
+    }
+    if text.starts_with('<') {
+        if let Some(end) = text.find('>') {
+            let inner = &text[1..end];
+            let autolink = inner.contains("://") || inner.contains('@') && !inner.contains(' ');
+            if autolink {
+                return Some(end + 1);
+            }
+        }
+        return markup_len(text);
+    }
+    if let Some(escaped) = text.strip_prefix('\\') {
+        return escaped.chars().next().map(|c| 1 + c.len_utf8());
+    }
+    if ["http://", "https://", "www."]
+        .iter()
+        .any(|p| text.starts_with(p))
+    {
+        return Some(
+            text.find(|c: char| {
+                c.is_whitespace() || matches!(c, '<' | '>' | '"' | '）' | '，' | '。')
+            })
+            .unwrap_or(text.len()),
+        );
+    }
+    None
+}
+
+/// For a link starting at `[`: the length of its text and of the whole link,
+/// `[text](destination)` or `[text][label]`
+fn link_parts(text: &str) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut close = None;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+    let after = &text[close + 1..];
+
+    let end = if after.starts_with('(') {
+        let mut depth = 0;
+        after.char_indices().find_map(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(i + 1)
+        })?
+    } else if after.starts_with('[') {
+        after.find(']')? + 1
+    } else {
+        return None;
+    };
+
+    Some((close - 1, close + 1 + end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in annotator that marks each run of text it is given
+    fn mark(text: &str) -> Result<String> {
+        Ok(format!("[{}]", text))
+    }
+
+    fn html(input: &str) -> String {
+        annotate_html(input, mark).unwrap()
+    }
+
+    fn markdown(input: &str) -> String {
+        annotate_markdown(input, mark).unwrap()
+    }
+
+    #[test]
+    fn html_annotates_text_nodes_only() {
+        assert_eq!(
+            html("<p class=\"中文\" title='a>b'>我爱<b>中国</b>！</p>"),
+            "<p class=\"中文\" title='a>b'>[我爱]<b>[中国]</b>[！]</p>"
+        );
+        assert_eq!(html("<br>你好<!-- 注释 -->\n"), "<br>[你好]<!-- 注释 -->\n");
+        assert_eq!(html("5 &lt; 6 &amp; 七"), "[5 &lt; 6 &amp; 七]");
+    }
+
+    #[test]
+    fn html_skips_scripts_code_ruby_and_untranslated_elements() {
+        let skipped = [
+            "<script>if (a < b) { s = \"<p>中国</p>\"; }</script>",
+            "<style>p::after { content: \"中国\"; }</style>",
+            "<title>中国</title>",
+            "<pre><code>let 中国 = 1;</code></pre>",
+            "<ruby>中<rt>zhōng</rt></ruby>",
+            "<div translate=\"no\">中国 <span>世界</span></div>",
+            "<TEXTAREA>中国</TEXTAREA>",
+        ];
+        for input in skipped {
+            assert_eq!(html(input), input);
+        }
+        assert_eq!(
+            html("<div translate=\"no\">中国</div><p>世界</p>"),
+            "<div translate=\"no\">中国</div><p>[世界]</p>"
+        );
+        assert_eq!(
+            html("<p>一<br/>二<img src=\"图.png\">三</p>"),
+            "<p>[一]<br/>[二]<img src=\"图.png\">[三]</p>"
+        );
+    }
+
+    #[test]
+    fn markdown_skips_front_matter_code_and_html_blocks() {
+        let input = "---\ntitle: 中国\n---\n# 你好\n\n```rust\nlet 中国 = 1;\n```\n\n    缩进代码\n\n<div>\n中国\n</div>\n\n世界\n";
+        assert_eq!(
+            markdown(input),
+            "---\ntitle: 中国\n---\n[# 你好\n]\n```rust\nlet 中国 = 1;\n```\n\n    缩进代码\n\n<div>\n中国\n</div>\n\n[世界\n]"
+        );
+    }
+
+    #[test]
+    fn markdown_keeps_link_destinations_code_spans_and_urls() {
+        assert_eq!(
+            markdown("见[中国](https://例子.中国/ \"中国\")和`代码`。\n"),
+            "[见][[中国]](https://例子.中国/ \"中国\")[和]`代码`[。\n]"
+        );
+        assert_eq!(
+            markdown("![图片](图.png) <https://中国.cn> 访问 https://例子.中国/页，好\n"),
+            "![图片](图.png) <https://中国.cn>[ 访问 ]https://例子.中国/页[，好\n]"
+        );
+        assert_eq!(
+            markdown("[注]: https://例子.中国\n"),
+            "[注]: https://例子.中国\n"
+        );
+        assert_eq!(markdown("\\*你好\\*\n"), "\\*[你好]\\*\n");
+    }
+
+    #[test]
+    fn markdown_list_continuations_are_prose() {
+        assert_eq!(markdown("- 第一\n\n    继续\n"), "[- 第一\n]\n[    继续\n]");
+    }
+}
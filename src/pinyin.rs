@@ -0,0 +1,660 @@
+//! Pinyin syllable parsing and conversion between spellings.
+//!
+//! A [`Syllable`] can be parsed from tone-marked (`zhōng`), numbered
+//! (`zhong1`, `nu:3`, `lv4`) or toneless pinyin, and decomposed into an
+//! initial and a canonical final so other notations can be derived from it.
+//! The string converters at the end of the module accept any of those
+//! spellings, so tone marks and tone numbers convert in both directions;
+//! [`align_syllables`] and [`convert_syllables`] also line a reading up with
+//! the word it reads, so Latin letters in the word stay as written.
+
+/// Vowels that carry tone marks, with their marked forms for tones 1-4
+pub(crate) const TONE_MARKS: [(char, [char; 4]); 6] = [
+    ('a', ['ā', 'á', 'ǎ', 'à']),
+    ('e', ['ē', 'é', 'ě', 'è']),
+    ('i', ['ī', 'í', 'ǐ', 'ì']),
+    ('o', ['ō', 'ó', 'ǒ', 'ò']),
+    ('u', ['ū', 'ú', 'ǔ', 'ù']),
+    ('ü', ['ǖ', 'ǘ', 'ǚ', 'ǜ']),
+];
+
+/// Precomposed marked letters outside the main vowel table
+const EXTRA_MARKS: [(char, char, u8); 6] = [
+    ('ế', 'ê', 2),
+    ('ề', 'ê', 4),
+    ('ḿ', 'm', 2),
+    ('ń', 'n', 2),
+    ('ň', 'n', 3),
+    ('ǹ', 'n', 4),
+];
+
+/// Combining diacritics for tones 1-4
+pub(crate) const COMBINING_TONES: [char; 4] = ['\u{0304}', '\u{0301}', '\u{030C}', '\u{0300}'];
+
+/// Initials, longest first so `zh` wins over `z`
+const INITIALS: [&str; 21] = [
+    "zh", "ch", "sh", "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "h", "j", "q", "x", "r",
+    "z", "c", "s",
+];
+
+/// Canonical finals, with y/w spellings and the iu/ui/un abbreviations expanded
+const FINALS: [&str; 41] = [
+    "a", "o", "e", "ê", "ai", "ei", "ao", "ou", "an", "en", "ang", "eng", "ong", "er", "i", "ia",
+    "io", "ie", "iao", "iou", "ian", "in", "iang", "ing", "iong", "u", "ua", "uo", "uai", "uei",
+    "uan", "uen", "uang", "ueng", "ü", "üe", "üan", "ün", "m", "n", "ng",
+];
+
+/// A single pinyin syllable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Syllable {
+    /// Written form without tone: lowercase, `ü` spelled out, erhua `r` kept
+    pub spelling: String,
+    /// Initial consonant, empty for zero-initial syllables
+    pub initial: &'static str,
+    /// Canonical final (`iou`, `uei`, `ün`, ...), without the erhua `r`
+    pub final_: &'static str,
+    /// Tone 1-4, or 5 for the neutral tone
+    pub tone: u8,
+    /// Whether the syllable carries an erhua `r` (a bare `r` syllable has an empty final)
+    pub erhua: bool,
+    /// Whether the syllable was written with a capital letter (proper nouns)
+    pub capitalized: bool,
+}
+
+impl Syllable {
+    /// Parse a tone-marked, numbered or toneless syllable. Returns `None` for
+    /// anything that is not pinyin (punctuation, Latin words, digits).
+    pub fn parse(token: &str) -> Option<Syllable> {
+        let first = token.chars().next()?;
+        let capitalized = first.is_uppercase();
+
+        let lower = token.to_lowercase();
+        let mut spelling = String::new();
+        let mut tone = None;
+        let mut chars = lower.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if let Some(digit) = c.to_digit(10) {
+                // Tone numbers only appear at the end; 0 and 5 are both neutral
+                if chars.peek().is_some() || digit > 5 || tone.is_some() {
+                    return None;
+                }
+                tone = Some(if digit == 0 { 5 } else { digit as u8 });
+            } else if c == ':' && spelling.ends_with('u') {
+                spelling.pop();
+                spelling.push('ü');
+            } else if c == 'v' {
+                spelling.push('ü');
+            } else if let Some(t) = COMBINING_TONES.iter().position(|&m| m == c) {
+                if tone.replace(t as u8 + 1).is_some() {
+                    return None;
+                }
+            } else if let Some((plain, t)) = unmark(c) {
+                if tone.replace(t).is_some() {
+                    return None;
+                }
+                spelling.push(plain);
+            } else if c.is_ascii_lowercase() || c == 'ü' || c == 'ê' {
+                spelling.push(c);
+            } else {
+                return None;
+            }
+        }
+
+        // Tone numbers alone (`4` of `A4`) spell nothing
+        if spelling.is_empty() {
+            return None;
+        }
+
+        let (core, erhua) = match spelling.as_str() {
+            "r" => ("", true),
+            "er" => ("er", false),
+            s => match s.strip_suffix('r') {
+                Some(core) if !core.is_empty() => (core, true),
+                _ => (s, false),
+            },
+        };
+
+        let (initial, final_) = if core.is_empty() {
+            ("", "")
+        } else {
+            split_syllable(core)?
+        };
+
+        Some(Syllable {
+            spelling,
+            initial,
+            final_,
+            tone: tone.unwrap_or(5),
+            erhua,
+            capitalized,
+        })
+    }
+
+    /// Tone-marked pinyin, e.g. `zhōng`
+    pub fn marked(&self) -> String {
+        let marked = place_tone_mark(&self.spelling, self.tone);
+        self.restore_case(marked)
+    }
+
+    /// Numbered pinyin, e.g. `zhong1`, `nv3`, `de5`: `v` stands for `ü`
+    /// and the neutral tone is 5
+    pub fn numbered(&self) -> String {
+        let numbered = format!("{}{}", self.spelling.replace('ü', "v"), self.tone);
+        self.restore_case(numbered)
+    }
+
+    /// Pinyin without tones, e.g. `zhong`, `nü`
+    pub fn toneless(&self) -> String {
+        self.restore_case(self.spelling.clone())
+    }
+
+    /// First letter of the spelling, lowercase, e.g. `z` for `Zhōng`
+    pub fn first_letter(&self) -> char {
+        match self.spelling.chars().next() {
+            Some('ê') => 'e',
+            Some(c) => c,
+            None => 'r',
+        }
+    }
+
+    /// Zhuyin (bopomofo), e.g. `ㄓㄨㄥ`
+    pub fn zhuyin(&self) -> String {
+        if self.initial.is_empty() && self.final_.is_empty() {
+            return "ㄦ".to_string();
+        }
+
+        let mut result = String::new();
+        if self.tone == 5 {
+            result.push('˙');
+        }
+        result.push_str(zhuyin_initial(self.initial));
+        if !self.is_apical() {
+            result.push_str(zhuyin_final(self.final_));
+        }
+        match self.tone {
+            2 => result.push('ˊ'),
+            3 => result.push('ˇ'),
+            4 => result.push('ˋ'),
+            _ => {}
+        }
+        if self.erhua {
+            result.push('ㄦ');
+        }
+        result
+    }
+
+    /// Whether this is one of zhi/chi/shi/ri/zi/ci/si, whose written `i` is
+    /// not a real vowel
+    pub fn is_apical(&self) -> bool {
+        self.final_ == "i" && matches!(self.initial, "zh" | "ch" | "sh" | "r" | "z" | "c" | "s")
+    }
+
+    fn restore_case(&self, s: String) -> String {
+        if !self.capitalized {
+            return s;
+        }
+        let mut chars = s.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => s,
+        }
+    }
+}
+
+/// Split a toneless spelling (without erhua `r`) into initial and canonical final
+fn split_syllable(core: &str) -> Option<(&'static str, &'static str)> {
+    // Syllabic nasals in interjections (呣 m, 嗯 n/ng, 哼 hng)
+    match core {
+        "m" | "n" | "ng" => return Some(("", canonical_final(core)?)),
+        "hm" | "hng" => return Some(("h", canonical_final(&core[1..])?)),
+        _ => {}
+    }
+
+    if let Some(rest) = core.strip_prefix('y') {
+        let final_ = match rest {
+            "i" => "i",
+            "a" => "ia",
+            "o" => "io",
+            "e" => "ie",
+            "ao" => "iao",
+            "ou" => "iou",
+            "an" => "ian",
+            "in" => "in",
+            "ang" => "iang",
+            "ing" => "ing",
+            "ong" => "iong",
+            "u" | "ü" => "ü",
+            "ue" | "üe" => "üe",
+            "uan" | "üan" => "üan",
+            "un" | "ün" => "ün",
+            _ => return None,
+        };
+        return Some(("", final_));
+    }
+
+    if let Some(rest) = core.strip_prefix('w') {
+        let final_ = match rest {
+            "u" => "u",
+            "a" => "ua",
+            "o" => "uo",
+            "ai" => "uai",
+            "ei" => "uei",
+            "an" => "uan",
+            "en" => "uen",
+            "ang" => "uang",
+            "eng" => "ueng",
+            _ => return None,
+        };
+        return Some(("", final_));
+    }
+
+    let initial = INITIALS
+        .iter()
+        .find(|i| core.starts_with(*i))
+        .copied()
+        .unwrap_or("");
+    let rest = &core[initial.len()..];
+    if rest.is_empty() {
+        return None;
+    }
+
+    let palatal = matches!(initial, "j" | "q" | "x");
+    let expanded = match rest {
+        "iu" => "iou",
+        "ui" => "uei",
+        "un" if palatal => "ün",
+        "un" => "uen",
+        "u" if palatal => "ü",
+        "ue" if palatal || matches!(initial, "l" | "n") => "üe",
+        "uan" if palatal => "üan",
+        other => other,
+    };
+
+    Some((initial, canonical_final(expanded)?))
+}
+
+fn canonical_final(final_: &str) -> Option<&'static str> {
+    FINALS.iter().find(|f| **f == final_).copied()
+}
+
+/// Strip a precomposed tone mark, returning the plain letter and tone
+fn unmark(c: char) -> Option<(char, u8)> {
+    for (plain, marked) in TONE_MARKS {
+        if let Some(i) = marked.iter().position(|&m| m == c) {
+            return Some((plain, i as u8 + 1));
+        }
+    }
+    EXTRA_MARKS
+        .iter()
+        .find(|(marked, _, _)| *marked == c)
+        .map(|&(_, plain, tone)| (plain, tone))
+}
+
+/// Put the tone mark on the right letter of a toneless spelling: `a` or `e`
+/// if present, the `o` of `ou`, otherwise the last vowel (or a syllabic nasal)
+fn place_tone_mark(spelling: &str, tone: u8) -> String {
+    if !(1..=4).contains(&tone) {
+        return spelling.to_string();
+    }
+
+    let chars: Vec<char> = spelling.chars().collect();
+    let Some(target) = tone_vowel(&chars) else {
+        return spelling.to_string();
+    };
+
+    let mut result = String::with_capacity(spelling.len() + 2);
+    for (i, &c) in chars.iter().enumerate() {
+        if i != target {
+            result.push(c);
+            continue;
+        }
+        match TONE_MARKS.iter().find(|(plain, _)| *plain == c) {
+            Some((_, marked)) => result.push(marked[tone as usize - 1]),
+            None => {
+                result.push(c);
+                result.push(COMBINING_TONES[tone as usize - 1]);
+            }
+        }
+    }
+    result
+}
+
+/// Index of the letter that carries the tone mark: `a` or `e` first, the
+/// `o` of `ou`, otherwise the last of `i`, `o`, `u`, `ü`, then a syllabic
+/// nasal. Romanizations with pinyin-like vowels place their marks the same way.
+pub(crate) fn tone_vowel(chars: &[char]) -> Option<usize> {
+    chars
+        .iter()
+        .position(|&c| matches!(c, 'a' | 'e' | 'ê'))
+        .or_else(|| chars.windows(2).position(|pair| pair == ['o', 'u']))
+        .or_else(|| {
+            chars
+                .iter()
+                .rposition(|&c| matches!(c, 'i' | 'o' | 'u' | 'ü'))
+        })
+        .or_else(|| chars.iter().position(|&c| matches!(c, 'm' | 'n')))
+}
+
+fn zhuyin_initial(initial: &str) -> &'static str {
+    match initial {
+        "b" => "ㄅ",
+        "p" => "ㄆ",
+        "m" => "ㄇ",
+        "f" => "ㄈ",
+        "d" => "ㄉ",
+        "t" => "ㄊ",
+        "n" => "ㄋ",
+        "l" => "ㄌ",
+        "g" => "ㄍ",
+        "k" => "ㄎ",
+        "h" => "ㄏ",
+        "j" => "ㄐ",
+        "q" => "ㄑ",
+        "x" => "ㄒ",
+        "zh" => "ㄓ",
+        "ch" => "ㄔ",
+        "sh" => "ㄕ",
+        "r" => "ㄖ",
+        "z" => "ㄗ",
+        "c" => "ㄘ",
+        "s" => "ㄙ",
+        _ => "",
+    }
+}
+
+fn zhuyin_final(final_: &str) -> &'static str {
+    match final_ {
+        "a" => "ㄚ",
+        "o" => "ㄛ",
+        "e" => "ㄜ",
+        "ê" => "ㄝ",
+        "ai" => "ㄞ",
+        "ei" => "ㄟ",
+        "ao" => "ㄠ",
+        "ou" => "ㄡ",
+        "an" => "ㄢ",
+        "en" => "ㄣ",
+        "ang" => "ㄤ",
+        "eng" => "ㄥ",
+        "ong" => "ㄨㄥ",
+        "er" => "ㄦ",
+        "i" => "ㄧ",
+        "ia" => "ㄧㄚ",
+        "io" => "ㄧㄛ",
+        "ie" => "ㄧㄝ",
+        "iao" => "ㄧㄠ",
+        "iou" => "ㄧㄡ",
+        "ian" => "ㄧㄢ",
+        "in" => "ㄧㄣ",
+        "iang" => "ㄧㄤ",
+        "ing" => "ㄧㄥ",
+        "iong" => "ㄩㄥ",
+        "u" => "ㄨ",
+        "ua" => "ㄨㄚ",
+        "uo" => "ㄨㄛ",
+        "uai" => "ㄨㄞ",
+        "uei" => "ㄨㄟ",
+        "uan" => "ㄨㄢ",
+        "uen" => "ㄨㄣ",
+        "uang" => "ㄨㄤ",
+        "ueng" => "ㄨㄥ",
+        "ü" => "ㄩ",
+        "üe" => "ㄩㄝ",
+        "üan" => "ㄩㄢ",
+        "ün" => "ㄩㄣ",
+        "m" => "ㄇ",
+        "n" => "ㄋ",
+        "ng" => "ㄫ",
+        _ => "",
+    }
+}
+
+/// Parse a token of a pinyin string. Capital letters with no tone (`O K` of
+/// `kǎ lā O K`) are Latin letters read as written, not syllables.
+fn parse_token(token: &str) -> Option<Syllable> {
+    if token.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    Syllable::parse(token)
+}
+
+/// Apply a conversion to every syllable of a space-separated pinyin string,
+/// passing non-pinyin tokens through unchanged
+pub fn map_syllables<F>(pinyin: &str, convert: F) -> String
+where
+    F: Fn(&Syllable) -> String,
+{
+    pinyin
+        .split_whitespace()
+        .map(|token| match parse_token(token) {
+            Some(syllable) => convert(&syllable),
+            None => token.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Convert numbered pinyin (`zhong1 guo2`, `nu:3`) to tone marks (`zhōng guó`, `nǚ`)
+pub fn numbered_to_marked(pinyin: &str) -> String {
+    map_syllables(pinyin, Syllable::marked)
+}
+
+/// Convert pinyin in any spelling to numbered pinyin (`nǚ hái` → `nv3 hai2`)
+pub fn to_numbered(pinyin: &str) -> String {
+    map_syllables(pinyin, Syllable::numbered)
+}
+
+/// Strip tones from pinyin in any spelling (`nǚ hái` → `nü hai`)
+pub fn to_toneless(pinyin: &str) -> String {
+    map_syllables(pinyin, Syllable::toneless)
+}
+
+/// First letters of each syllable, lowercase and unspaced (`nǐ hǎo` → `nh`).
+/// Non-pinyin tokens such as `OK` are kept whole.
+pub fn to_first_letters(pinyin: &str) -> String {
+    pinyin
+        .split_whitespace()
+        .map(|token| match parse_token(token) {
+            Some(syllable) => syllable.first_letter().to_string(),
+            None => token.to_lowercase(),
+        })
+        .collect()
+}
+
+/// Derive zhuyin from pinyin in any spelling (`zhōng guó` → `ㄓㄨㄥ ㄍㄨㄛˊ`)
+pub fn to_zhuyin(pinyin: &str) -> String {
+    map_syllables(pinyin, Syllable::zhuyin)
+}
+
+/// Pair the characters of a word with the syllables of its reading. A run of
+/// ASCII letters and digits takes one syllable (`A4纸` → `A4 zhǐ`), or one
+/// per letter when that is what lines up (`卡拉OK` → `kǎ lā O K`). If that
+/// leaves more characters than syllables, an erhua 儿 joins the character
+/// before it when that syllable ends in `r` (`点儿` → `diǎnr`). Returns `None`
+/// when they still do not line up.
+pub fn align_syllables<'a>(text: &'a str, reading: &'a str) -> Option<Vec<(&'a str, &'a str)>> {
+    let syllables: Vec<&str> = reading.split_whitespace().collect();
+
+    let units = [(false, false), (false, true), (true, false), (true, true)]
+        .into_iter()
+        .map(|(split_latin, join_erhua)| word_units(text, &syllables, split_latin, join_erhua))
+        .find(|units| units.len() == syllables.len())?;
+
+    Some(
+        units
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .zip(syllables)
+            .collect(),
+    )
+}
+
+/// Byte ranges of the units of `text` that take one syllable each
+fn word_units(
+    text: &str,
+    syllables: &[&str],
+    split_latin: bool,
+    join_erhua: bool,
+) -> Vec<(usize, usize)> {
+    let mut units: Vec<(usize, usize)> = Vec::new();
+
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        let joins = match units.last() {
+            Some(&(start, last_end)) if c.is_ascii_alphanumeric() && !split_latin => text
+                [start..last_end]
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric()),
+            Some(_) if join_erhua && matches!(c, '儿' | '兒') => syllables
+                .get(units.len() - 1)
+                .is_some_and(|syllable| syllable.ends_with('r')),
+            _ => false,
+        };
+
+        match units.last_mut() {
+            Some(last) if joins => last.1 = end,
+            _ => units.push((i, end)),
+        }
+    }
+
+    units
+}
+
+/// Convert each syllable of a word's reading, keeping the tokens that line
+/// up with a run of ASCII letters and digits in the word as written (`A4` of
+/// `A4纸 A4 zhi3`), like any other token that is not pinyin
+pub fn convert_syllables<F>(text: &str, reading: &str, convert: F) -> Vec<String>
+where
+    F: Fn(&Syllable) -> String,
+{
+    let latin: Vec<bool> = align_syllables(text, reading)
+        .map(|pairs| {
+            pairs
+                .iter()
+                .map(|(unit, _)| unit.chars().all(|c| c.is_ascii_alphanumeric()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    reading
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, token)| {
+            match Syllable::parse(token).filter(|_| !latin.get(i).copied().unwrap_or(false)) {
+                Some(syllable) => convert(&syllable),
+                None => token.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(token: &str) -> Syllable {
+        Syllable::parse(token).unwrap_or_else(|| panic!("{} should parse", token))
+    }
+
+    #[test]
+    fn parses_marked_numbered_and_toneless_spellings() {
+        for token in ["zhōng", "zhong1", "Zhong1", "zhōng"] {
+            let syllable = parse(token);
+            assert_eq!((syllable.initial, syllable.final_), ("zh", "ong"));
+            assert_eq!(syllable.tone, 1);
+        }
+        assert!(parse("Zhong1").capitalized);
+        assert_eq!(parse("zhong").tone, 5);
+        assert_eq!(parse("de0").tone, 5);
+    }
+
+    #[test]
+    fn expands_abbreviated_finals_and_u_umlaut() {
+        assert_eq!(parse("liu2").final_, "iou");
+        assert_eq!(parse("gui4").final_, "uei");
+        assert_eq!(parse("dun4").final_, "uen");
+        assert_eq!(parse("you3").final_, "iou");
+        assert_eq!(parse("wen2").final_, "uen");
+        assert_eq!(parse("ju2").final_, "ü");
+        assert_eq!(parse("yuan2").final_, "üan");
+
+        for token in ["nu:3", "nv3", "nǚ"] {
+            let syllable = parse(token);
+            assert_eq!((syllable.spelling.as_str(), syllable.tone), ("nü", 3));
+        }
+    }
+
+    #[test]
+    fn parses_erhua() {
+        let syllable = parse("dianr3");
+        assert_eq!((syllable.final_, syllable.erhua), ("ian", true));
+        assert_eq!(parse("er2").final_, "er");
+        assert!(!parse("er2").erhua);
+
+        let bare = parse("r5");
+        assert!(bare.erhua);
+        assert_eq!(bare.zhuyin(), "ㄦ");
+        assert_eq!(bare.first_letter(), 'r');
+    }
+
+    #[test]
+    fn rejects_tokens_that_are_not_pinyin() {
+        for token in ["", "4", "45", "x2x", "zhong12", "abc", "OK", "，", "中"] {
+            assert_eq!(Syllable::parse(token), None, "{:?}", token);
+        }
+    }
+
+    #[test]
+    fn converts_between_spellings() {
+        assert_eq!(numbered_to_marked("zhong1 guo2"), "zhōng guó");
+        assert_eq!(numbered_to_marked("nu:3 hai2"), "nǚ hái");
+        assert_eq!(numbered_to_marked("lve4"), "lüè");
+        assert_eq!(numbered_to_marked("liu2 gui4"), "liú guì");
+        assert_eq!(to_numbered("nǚ hái"), "nv3 hai2");
+        assert_eq!(to_numbered("Běi jīng"), "Bei3 jing1");
+        assert_eq!(to_numbered("kǎ lā O K"), "ka3 la1 O K");
+        assert_eq!(to_numbered("E2 guo2"), "E2 guo2");
+        assert_eq!(to_toneless("nǚ hái"), "nü hai");
+        assert_eq!(to_first_letters("nǐ hǎo"), "nh");
+        assert_eq!(to_first_letters("T xù"), "tx");
+        assert_eq!(to_zhuyin("zhōng guó"), "ㄓㄨㄥ ㄍㄨㄛˊ");
+        assert_eq!(to_zhuyin("shì de"), "ㄕˋ ˙ㄉㄜ");
+        assert_eq!(to_zhuyin("T xù"), "T ㄒㄩˋ");
+        assert_eq!(to_zhuyin("kǎ lā O K"), "ㄎㄚˇ ㄌㄚ O K");
+    }
+
+    #[test]
+    fn aligns_syllables_with_characters_erhua_and_latin_runs() {
+        assert_eq!(
+            align_syllables("中国", "zhōng guó").unwrap(),
+            [("中", "zhōng"), ("国", "guó")]
+        );
+        assert_eq!(
+            align_syllables("一点儿", "yī diǎnr").unwrap(),
+            [("一", "yī"), ("点儿", "diǎnr")]
+        );
+        assert_eq!(align_syllables("哪儿", "nǎr").unwrap(), [("哪儿", "nǎr")]);
+        assert_eq!(align_syllables("中国", "zhōng"), None);
+        assert_eq!(
+            align_syllables("A4纸", "A4 zhǐ").unwrap(),
+            [("A4", "A4"), ("纸", "zhǐ")]
+        );
+        assert_eq!(
+            align_syllables("卡拉OK", "kǎ lā O K").unwrap(),
+            [("卡", "kǎ"), ("拉", "lā"), ("O", "O"), ("K", "K")]
+        );
+    }
+
+    #[test]
+    fn converts_syllables_but_not_latin_letters() {
+        assert_eq!(
+            convert_syllables("卡拉OK", "kǎ lā O K", Syllable::zhuyin),
+            ["ㄎㄚˇ", "ㄌㄚ", "O", "K"]
+        );
+        assert_eq!(
+            convert_syllables("A4纸", "A4 zhi3", Syllable::marked),
+            ["A4", "zhǐ"]
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! Context rules for choosing among the readings of a polyphone (多音字).
+//!
+//! Rules are read from a JSON file such as
+//!
+//! ```json
+//! {
+//!   "rules": [
+//!     { "word": "还", "reading": "hái", "next": ["是", "有", "要"], "note": "adverb" },
+//!     { "word": "还", "reading": "huán", "previous": ["归", "偿"] },
+//!     { "word": "还", "reading": "hái" }
+//!   ]
+//! }
+//! ```
+//!
+//! A rule applies to segments whose text equals `word`. It matches when the
+//! previous segment ends with one of `previous` and the next segment starts
+//! with one of `next`; an empty list matches any neighbor, so a rule with
+//! neither list is an unconditional default. With `"exact": true` a neighbor
+//! must equal a trigger instead, so `"previous": ["当"]` matches 当 地 but not
+//! 相当 地. Rules are tried in file order and the first match whose reading
+//! the dictionary actually lists wins. Readings may be written with tone
+//! marks or tone numbers.
+//!
+//! Rules with `"language": "cantonese"` choose among an entry's Jyutping
+//! readings instead, and are only consulted for Cantonese annotation:
+//!
+//! ```json
+//! { "word": "行", "language": "cantonese", "reading": "hong4", "previous": ["銀"] }
+//! ```
+
+use crate::pinyin::numbered_to_marked;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Rules shipped with the crate, used unless a rules file is given
+const BUILTIN_RULES: &str = include_str!("../data/polyphone_rules.json");
+
+/// The reading a rule chooses: Mandarin pinyin or Cantonese Jyutping
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleLanguage {
+    #[default]
+    Mandarin,
+    Cantonese,
+}
+
+impl RuleLanguage {
+    pub fn is_mandarin(&self) -> bool {
+        matches!(self, RuleLanguage::Mandarin)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolyphoneRule {
+    /// Segment text the rule applies to
+    pub word: String,
+    /// Whether `reading` is pinyin or Jyutping
+    #[serde(default, skip_serializing_if = "RuleLanguage::is_mandarin")]
+    pub language: RuleLanguage,
+    /// Reading to choose
+    pub reading: String,
+    /// Endings of the previous segment that trigger the rule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous: Vec<String>,
+    /// Beginnings of the next segment that trigger the rule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub next: Vec<String>,
+    /// Whether neighbors must equal a trigger rather than end or start with it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exact: bool,
+    /// Free-form explanation shown in the decision log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl PolyphoneRule {
+    fn matches(&self, previous: &str, next: &str) -> bool {
+        let previous_matches = |p: &String| {
+            if self.exact {
+                previous == p
+            } else {
+                previous.ends_with(p.as_str())
+            }
+        };
+        let next_matches = |n: &String| {
+            if self.exact {
+                next == n
+            } else {
+                next.starts_with(n.as_str())
+            }
+        };
+
+        (self.previous.is_empty() || self.previous.iter().any(previous_matches))
+            && (self.next.is_empty() || self.next.iter().any(next_matches))
+    }
+
+    /// One-line description, e.g. `还 → hái when next is 是/有/要 (adverb)`
+    pub fn describe(&self) -> String {
+        let mut description = format!("{} → {}", self.word, self.reading);
+        let mut conditions = Vec::new();
+        if !self.previous.is_empty() {
+            conditions.push(format!("previous is {}", self.previous.join("/")));
+        }
+        if !self.next.is_empty() {
+            conditions.push(format!("next is {}", self.next.join("/")));
+        }
+        if !conditions.is_empty() {
+            description.push_str(" when ");
+            description.push_str(&conditions.join(" and "));
+        }
+        if let Some(note) = &self.note {
+            description.push_str(&format!(" ({})", note));
+        }
+        description
+    }
+}
+
+#[derive(Deserialize)]
+struct RuleFile {
+    rules: Vec<PolyphoneRule>,
+}
+
+/// An ordered set of polyphone rules, indexed by word
+#[derive(Debug, Clone)]
+pub struct PolyphoneRules {
+    rules: Vec<PolyphoneRule>,
+    /// Rule indexes per word and language, in file order
+    by_word: HashMap<(String, RuleLanguage), Vec<usize>>,
+    /// Each rule's reading in normalized form
+    readings: Vec<String>,
+}
+
+impl PolyphoneRules {
+    pub fn new(rules: Vec<PolyphoneRule>) -> Self {
+        let mut by_word: HashMap<(String, RuleLanguage), Vec<usize>> = HashMap::new();
+        for (index, rule) in rules.iter().enumerate() {
+            by_word
+                .entry((rule.word.clone(), rule.language))
+                .or_default()
+                .push(index);
+        }
+        let readings = rules
+            .iter()
+            .map(|rule| normalize_reading(&rule.reading, rule.language))
+            .collect();
+
+        Self {
+            rules,
+            by_word,
+            readings,
+        }
+    }
+
+    /// The rules shipped in `data/polyphone_rules.json`
+    pub fn builtin() -> Self {
+        Self::from_reader(BUILTIN_RULES.as_bytes()).expect("built-in polyphone rules are valid")
+    }
+
+    /// Read rules from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+        Self::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to read polyphone rules {}", path.as_ref().display()))
+    }
+
+    /// Read rules from JSON text
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let file: RuleFile = serde_json::from_reader(reader)?;
+        Ok(Self::new(file.rules))
+    }
+
+    pub fn rules(&self) -> &[PolyphoneRule] {
+        &self.rules
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Find the first `language` rule for `word` that matches its neighbors
+    /// and names one of the `candidates` readings. Returns the rule index and
+    /// candidate index.
+    pub fn select(
+        &self,
+        language: RuleLanguage,
+        word: &str,
+        previous: &str,
+        next: &str,
+        candidates: &[&str],
+    ) -> Option<(usize, usize)> {
+        let indexes = self.by_word.get(&(word.to_string(), language))?;
+
+        indexes.iter().find_map(|&index| {
+            if !self.rules[index].matches(previous, next) {
+                return None;
+            }
+            candidates
+                .iter()
+                .position(|reading| normalize_reading(reading, language) == self.readings[index])
+                .map(|candidate| (index, candidate))
+        })
+    }
+}
+
+impl Default for PolyphoneRules {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Why a reading was chosen for a segment with several readings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DecisionReason {
+    /// A polyphone rule matched
+    Rule { index: usize, rule: String },
+    /// No rule matched; the first entry whose headword is written in the
+    /// input's script
+    ScriptMatch,
+    /// No Cantonese rule matched; the Jyutping of the entry chosen for the
+    /// Mandarin reading
+    MandarinEntry,
+    /// No rule or script match; the dictionary's first entry
+    First,
+}
+
+/// Record of a reading choice, for explaining annotations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decision {
+    pub reading: String,
+    pub candidates: Vec<String>,
+    pub reason: DecisionReason,
+}
+
+impl Decision {
+    /// One-line explanation, e.g. `hái (rule 2: 还 → hái when next is 是/有/要)`
+    pub fn explain(&self) -> String {
+        let reason = match &self.reason {
+            DecisionReason::Rule { index, rule } => format!("rule {}: {}", index + 1, rule),
+            DecisionReason::ScriptMatch => "no rule matched; first entry for this script".into(),
+            DecisionReason::MandarinEntry => {
+                "no rule matched; entry of the Mandarin reading".into()
+            }
+            DecisionReason::First => "no rule matched; first dictionary entry".into(),
+        };
+        format!(
+            "{} ({}; candidates: {})",
+            self.reading,
+            reason,
+            self.candidates.join("|")
+        )
+    }
+}
+
+/// Lowercase tone-marked pinyin, so `Hai2`, `hái` and `HÁI` compare equal;
+/// Jyutping is only lowercased
+fn normalize_reading(reading: &str, language: RuleLanguage) -> String {
+    match language {
+        RuleLanguage::Mandarin => numbered_to_marked(reading).to_lowercase(),
+        RuleLanguage::Cantonese => reading.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"{
+        "rules": [
+            { "word": "还", "reading": "hai2", "next": ["是", "有"], "note": "adverb" },
+            { "word": "还", "reading": "huán", "previous": ["归", "偿"] },
+            { "word": "还", "reading": "hái" },
+            { "word": "行", "reading": "háng", "previous": ["银"], "next": ["长"] },
+            { "word": "行", "language": "cantonese", "reading": "hong4", "previous": ["银"] },
+            { "word": "行", "language": "cantonese", "reading": "haang4" }
+        ]
+    }"#;
+
+    fn rules() -> PolyphoneRules {
+        PolyphoneRules::from_reader(RULES.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn matches_previous_and_next_neighbors() {
+        let rules = rules();
+        let candidates = ["huán", "hái"];
+        let select = |previous, next| {
+            rules.select(RuleLanguage::Mandarin, "还", previous, next, &candidates)
+        };
+
+        assert_eq!(select("我", "是"), Some((0, 1)));
+        assert_eq!(select("回归", "钱"), Some((1, 0)));
+        assert_eq!(select("我", "钱"), Some((2, 1)));
+
+        let candidates = ["xíng", "háng"];
+        let select = |previous, next| {
+            rules.select(RuleLanguage::Mandarin, "行", previous, next, &candidates)
+        };
+        assert_eq!(select("银", "长"), Some((3, 1)));
+        assert_eq!(select("银", "业"), None);
+        assert_eq!(select("", "长"), None);
+    }
+
+    #[test]
+    fn skips_rules_whose_reading_is_not_a_candidate() {
+        let rules = rules();
+        assert_eq!(
+            rules.select(RuleLanguage::Mandarin, "还", "我", "是", &["huán"]),
+            None
+        );
+        assert_eq!(
+            rules.select(RuleLanguage::Mandarin, "还", "偿", "", &["HAI2", "Huan2"]),
+            Some((1, 1))
+        );
+        assert_eq!(
+            rules.select(RuleLanguage::Mandarin, "好", "", "", &["hǎo"]),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_mandarin_and_cantonese_rules_apart() {
+        let rules = rules();
+        assert_eq!(
+            rules.select(
+                RuleLanguage::Cantonese,
+                "行",
+                "银",
+                "",
+                &["haang4", "hong4"]
+            ),
+            Some((4, 1))
+        );
+        assert_eq!(
+            rules.select(RuleLanguage::Cantonese, "行", "银", "", &["háng"]),
+            None
+        );
+        assert_eq!(
+            rules.select(RuleLanguage::Mandarin, "行", "银", "", &["hong4"]),
+            None
+        );
+        assert_eq!(
+            rules.select(RuleLanguage::Cantonese, "还", "", "", &["waan4"]),
+            None
+        );
+    }
+
+    #[test]
+    fn builtin_rules_read_row_and_go_together_apart() {
+        let rules = PolyphoneRules::builtin();
+        let mandarin = |previous| {
+            let (_, candidate) = rules
+                .select(
+                    RuleLanguage::Mandarin,
+                    "行",
+                    previous,
+                    "",
+                    &["xíng", "háng"],
+                )
+                .unwrap();
+            candidate
+        };
+        let cantonese = |previous| {
+            let (_, candidate) = rules
+                .select(
+                    RuleLanguage::Cantonese,
+                    "行",
+                    previous,
+                    "",
+                    &["haang4", "hong4"],
+                )
+                .unwrap();
+            candidate
+        };
+
+        for previous in ["两", "银"] {
+            assert_eq!((mandarin(previous), cantonese(previous)), (1, 1));
+        }
+        for previous in ["一", "同"] {
+            assert_eq!((mandarin(previous), cantonese(previous)), (0, 0));
+        }
+    }
+
+    #[test]
+    fn builtin_rules_keep_particles_after_adverbs_and_verbs() {
+        let rules = PolyphoneRules::builtin();
+        let select = |word, previous, next, candidates: &[&str]| {
+            let (_, candidate) = rules
+                .select(RuleLanguage::Mandarin, word, previous, next, candidates)
+                .unwrap();
+            candidates[candidate].to_string()
+        };
+
+        for previous in ["相当", "适当", "伟大", "大大", "格外", "实在"] {
+            assert_eq!(
+                select("地", previous, "", &["dì", "de"]),
+                "de",
+                "{}",
+                previous
+            );
+        }
+        for previous in ["在", "当", "目的"] {
+            assert_eq!(
+                select("地", previous, "", &["dì", "de"]),
+                "dì",
+                "{}",
+                previous
+            );
+        }
+        assert_eq!(select("地", "", "上", &["dì", "de"]), "dì");
+
+        assert_eq!(select("了", "得", "奖", &["liǎo", "le"]), "le");
+        assert_eq!(select("了", "他得", "病", &["liǎo", "le"]), "le");
+        assert_eq!(select("了", "受不", "", &["liǎo", "le"]), "liǎo");
+
+        let candidates = ["zháo", "zhuó", "zhe"];
+        assert_eq!(select("着", "打", "伞", &candidates), "zhe");
+        assert_eq!(select("着", "点", "灯", &candidates), "zhe");
+        assert_eq!(select("着", "睡", "", &candidates), "zháo");
+    }
+
+    #[test]
+    fn describes_rules_and_decisions() {
+        let rules = rules();
+        assert_eq!(
+            rules.rules()[0].describe(),
+            "还 → hai2 when next is 是/有 (adverb)"
+        );
+        assert_eq!(
+            rules.rules()[3].describe(),
+            "行 → háng when previous is 银 and next is 长"
+        );
+        assert_eq!(rules.rules()[2].describe(), "还 → hái");
+
+        let decision = Decision {
+            reading: "hái".to_string(),
+            candidates: vec!["huán".to_string(), "hái".to_string()],
+            reason: DecisionReason::Rule {
+                index: 0,
+                rule: rules.rules()[0].describe(),
+            },
+        };
+        assert_eq!(
+            decision.explain(),
+            "hái (rule 1: 还 → hai2 when next is 是/有 (adverb); candidates: huán|hái)"
+        );
+        let decision = Decision {
+            reason: DecisionReason::First,
+            ..decision
+        };
+        assert_eq!(
+            decision.explain(),
+            "hái (no rule matched; first dictionary entry; candidates: huán|hái)"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_rule_files() {
+        assert!(PolyphoneRules::from_reader(r#"{"rules": [{"word": "还"}]}"#.as_bytes()).is_err());
+        assert!(PolyphoneRules::from_reader("[]".as_bytes()).is_err());
+        assert_eq!(rules().len(), 6);
+        assert!(PolyphoneRules::new(Vec::new()).is_empty());
+    }
+}
@@ -1,5 +1,23 @@
 // Production-ready Chinese text annotator with text normalization
-use crate::dictionary::Dictionary;
+use crate::dictionary::{AnnotationData, Dictionary};
+use crate::epub::{self, Book};
+pub use crate::html::HtmlTheme;
+use crate::html::{self, DocumentOptions, Line};
+use crate::ipa;
+pub use crate::ipa::ToneNotation;
+use crate::jyutping::{self, Jyutping};
+use crate::latex;
+pub use crate::latex::LatexMarkup;
+use crate::levels::{KnownWords, LevelList};
+use crate::markup;
+use crate::pinyin::{align_syllables, convert_syllables, Syllable};
+use crate::polyphone::{Decision, DecisionReason, PolyphoneRules, RuleLanguage};
+use crate::romanization::{self, Romanization};
+use crate::sandhi;
+use crate::segmenter::Segmenter;
+pub use crate::segmenter::SegmenterKind;
+pub use crate::subtitles::SubtitleMode;
+use crate::subtitles::{self, Annotation, SubtitleFormat};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +29,35 @@ pub struct AnnotationConfig {
     pub show_alternatives: bool,
     pub show_confidence: bool,
     pub use_traditional: bool,
+    /// Include English glosses in JSON, table and ruby output
+    pub show_definitions: bool,
+    /// How text is split into words before annotation
+    pub segmenter: SegmenterKind,
+    /// Include the reasoning behind each polyphone reading (Mandarin and
+    /// Cantonese) in JSON output
+    pub explain: bool,
+    /// Show spoken tones (一/不 and third-tone sandhi) instead of citation tones
+    pub tone_sandhi: bool,
+    /// Tone notation for the IPA style
+    pub ipa_tones: ToneNotation,
+    /// How ruby output lines readings up with multi-character words
+    pub ruby_layout: RubyLayout,
+    /// Color readings and characters by tone in HTML output
+    pub tone_colors: bool,
+    /// Page colors of HTML output
+    pub html_theme: HtmlTheme,
+    /// Hide readings in HTML output until their word is clicked
+    pub toggle_readings: bool,
+    /// Title of HTML and EPUB documents; the first line of the text if unset
+    pub title: Option<String>,
+    /// How LaTeX output writes annotated words
+    pub latex_markup: LatexMarkup,
+    /// Wrap LaTeX output in a compilable XeLaTeX document
+    pub latex_document: bool,
+    /// Where readings go in annotated subtitles
+    pub subtitle_mode: SubtitleMode,
+    /// Leave words at or below this proficiency level unannotated
+    pub annotate_above_level: Option<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +68,22 @@ pub enum OutputFormat {
     Ruby,     // <ruby>我<rt>wǒ</rt></ruby>
     Table,    // Tabular format for analysis
     Rows,     // Two rows: Chinese text on top, pinyin below
+    Html,     // Standalone HTML document with ruby paragraphs
+    Epub,     // EPUB 3 book, written with `format_epub`
+    Latex,    // \xpinyin{中国}{zhong1 guo2}
+}
+
+/// Ruby markup for words of more than one character. Both layouts fall back
+/// to one reading for the whole word when its characters and syllables do
+/// not line up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RubyLayout {
+    /// One base per character: `<ruby>中<rt>zhōng</rt>国<rt>guó</rt></ruby>`
+    #[default]
+    PerCharacter,
+    /// Bases and readings grouped per word:
+    /// `<ruby><rb>中</rb><rb>国</rb><rt>zhōng</rt><rt>guó</rt></ruby>`
+    Word,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +91,17 @@ pub enum AnnotationStyle {
     Pinyin,
     Zhuyin,
     Both,
+    PinyinNumbered,    // ni3 hao3
+    PinyinToneless,    // ni hao
+    PinyinFirstLetter, // nh
+    WadeGiles,         // ni³ hao³
+    MandarinYale,      // nǐ hǎu
+    GwoyeuRomatzyh,    // nii hao
+    Tongyong,          // nǐ hǎo
+    Mps2,              // nǐ hǎu
+    Ipa,               // ni˨˩˦ xɑʊ˨˩˦
+    Jyutping,          // nei5 hou2 (Cantonese)
+    Yale,              // néih hóu (Cantonese)
 }
 
 impl Default for AnnotationConfig {
@@ -39,6 +113,20 @@ impl Default for AnnotationConfig {
             show_alternatives: false,
             show_confidence: false,
             use_traditional: false,
+            show_definitions: false,
+            segmenter: SegmenterKind::ForwardMaximum,
+            explain: false,
+            tone_sandhi: false,
+            ipa_tones: ToneNotation::Letters,
+            ruby_layout: RubyLayout::PerCharacter,
+            tone_colors: false,
+            html_theme: HtmlTheme::Light,
+            toggle_readings: false,
+            title: None,
+            latex_markup: LatexMarkup::Xpinyin,
+            latex_document: false,
+            subtitle_mode: SubtitleMode::Line,
+            annotate_above_level: None,
         }
     }
 }
@@ -52,11 +140,39 @@ pub struct AnnotatedSegment {
     pub alternatives: Vec<String>,
     pub is_chinese: bool,
     pub position: usize,
+    /// English glosses of the selected reading
+    #[serde(default)]
+    pub definitions: Vec<String>,
+    /// How the reading was chosen, for segments with several readings
+    #[serde(default)]
+    pub decision: Option<Decision>,
+    /// Pinyin with tone sandhi applied, set in tone sandhi mode
+    #[serde(default)]
+    pub spoken_pinyin: Option<String>,
+    /// Zhuyin with tone sandhi applied, set in tone sandhi mode
+    #[serde(default)]
+    pub spoken_zhuyin: Option<String>,
+    /// Cantonese reading in Jyutping, when the dictionary has one
+    #[serde(default)]
+    pub jyutping: Option<String>,
+    /// How the Cantonese reading was chosen, for segments with several
+    #[serde(default)]
+    pub cantonese_decision: Option<Decision>,
+    /// Proficiency level of the word, when a level list is loaded
+    #[serde(default)]
+    pub level: Option<u8>,
+    /// Whether the word is on the known-word list
+    #[serde(default)]
+    pub known: bool,
 }
 
 pub struct ProductionAnnotator {
     dictionary: Dictionary,
     config: AnnotationConfig,
+    segmenter: Box<dyn Segmenter>,
+    polyphone_rules: PolyphoneRules,
+    levels: Option<LevelList>,
+    known_words: KnownWords,
 }
 
 impl ProductionAnnotator {
@@ -73,110 +189,493 @@ impl ProductionAnnotator {
 
         println!("🎯 Production annotator ready!");
 
-        Ok(Self { dictionary, config })
+        Ok(Self::with_dictionary(dictionary, config))
+    }
+
+    /// Create an annotator around an already loaded dictionary
+    pub fn with_dictionary(dictionary: Dictionary, config: AnnotationConfig) -> Self {
+        let segmenter = config.segmenter.build();
+        Self {
+            dictionary,
+            config,
+            segmenter,
+            polyphone_rules: PolyphoneRules::builtin(),
+            levels: None,
+            known_words: KnownWords::default(),
+        }
+    }
+
+    /// Replace the segmenter chosen by `config.segmenter` with a custom one
+    pub fn with_segmenter(mut self, segmenter: Box<dyn Segmenter>) -> Self {
+        self.segmenter = segmenter;
+        self
+    }
+
+    /// Replace the built-in polyphone rules
+    pub fn with_polyphone_rules(mut self, rules: PolyphoneRules) -> Self {
+        self.polyphone_rules = rules;
+        self
+    }
+
+    /// Tag segments with their level from an HSK or TOCFL list
+    pub fn with_levels(mut self, levels: LevelList) -> Self {
+        self.levels = Some(levels);
+        self
+    }
+
+    /// Leave the learner's known words unannotated
+    pub fn with_known_words(mut self, known_words: KnownWords) -> Self {
+        self.known_words = known_words;
+        self
+    }
+
+    /// The level list segments are tagged from
+    pub fn levels(&self) -> Option<&LevelList> {
+        self.levels.as_ref()
     }
 
     pub fn annotate(&self, text: &str) -> Result<Vec<AnnotatedSegment>> {
-        let mut segments = Vec::new();
+        let use_traditional = self.is_traditional(text);
+
+        let chars: Vec<char> = text.chars().collect();
+
+        let spans = self
+            .segmenter
+            .segment(&self.dictionary, &chars, use_traditional);
+
+        // Use original characters for display
+        let texts: Vec<String> = spans
+            .iter()
+            .map(|&(start, len)| chars[start..start + len].iter().collect())
+            .collect();
+
+        let mut segments: Vec<AnnotatedSegment> = spans
+            .iter()
+            .enumerate()
+            .map(|(index, &(start, len))| {
+                // Neighbors give polyphone rules their context
+                let neighbors = Neighbors {
+                    previous: index.checked_sub(1).map_or("", |i| texts[i].as_str()),
+                    next: texts.get(index + 1).map_or("", String::as_str),
+                };
+                self.annotate_span(
+                    texts[index].clone(),
+                    &chars[start..start + len],
+                    start,
+                    neighbors,
+                    use_traditional,
+                )
+            })
+            .collect();
+
+        if self.config.tone_sandhi {
+            self.apply_tone_sandhi(&mut segments);
+        }
+
+        Ok(segments)
+    }
+
+    /// Fill in spoken readings; sandhi crosses word boundaries, so this runs
+    /// over the whole segment sequence
+    fn apply_tone_sandhi(&self, segments: &mut [AnnotatedSegment]) {
+        let words: Vec<(&str, Option<&str>)> = segments
+            .iter()
+            .map(|segment| (segment.text.as_str(), segment.pinyin.as_deref()))
+            .collect();
+        let spoken = sandhi::spoken_readings(&words);
+
+        for (segment, spoken_pinyin) in segments.iter_mut().zip(spoken) {
+            let Some(spoken_pinyin) = spoken_pinyin else {
+                continue;
+            };
+            segment.spoken_zhuyin = if segment.pinyin.as_ref() == Some(&spoken_pinyin) {
+                segment.zhuyin.clone()
+            } else {
+                Some(convert_syllables(&segment.text, &spoken_pinyin, Syllable::zhuyin).join(" "))
+            };
+            segment.spoken_pinyin = Some(spoken_pinyin);
+        }
+    }
+
+    /// Whether to read `text` as traditional Chinese: as configured, or
+    /// auto-detected from the text
+    fn is_traditional(&self, text: &str) -> bool {
+        self.config.use_traditional || self.dictionary.detect_traditional(text)
+    }
+
+    /// Pinyin to display: the spoken reading in tone sandhi mode
+    fn shown_pinyin<'a>(&self, segment: &'a AnnotatedSegment) -> Option<&'a String> {
+        if self.config.tone_sandhi && segment.spoken_pinyin.is_some() {
+            segment.spoken_pinyin.as_ref()
+        } else {
+            segment.pinyin.as_ref()
+        }
+    }
+
+    /// The annotation for the configured style; for `Both` this is the
+    /// pinyin, and the zhuyin comes from `secondary_annotation`
+    fn primary_annotation(&self, segment: &AnnotatedSegment) -> Option<String> {
+        let pinyin = self.shown_pinyin(segment);
+        match self.config.annotation_style {
+            AnnotationStyle::Pinyin | AnnotationStyle::Both => pinyin.cloned(),
+            AnnotationStyle::Zhuyin => self.shown_zhuyin(segment).cloned(),
+            AnnotationStyle::PinyinNumbered => {
+                pinyin.map(|p| convert_syllables(&segment.text, p, Syllable::numbered).join(" "))
+            }
+            AnnotationStyle::PinyinToneless => {
+                pinyin.map(|p| convert_syllables(&segment.text, p, Syllable::toneless).join(" "))
+            }
+            AnnotationStyle::PinyinFirstLetter => pinyin.map(|p| {
+                convert_syllables(&segment.text, p, |s| s.first_letter().to_string())
+                    .concat()
+                    .to_lowercase()
+            }),
+            AnnotationStyle::WadeGiles => {
+                pinyin.map(|p| romanize_reading(&segment.text, p, Romanization::WadeGiles))
+            }
+            AnnotationStyle::MandarinYale => {
+                pinyin.map(|p| romanize_reading(&segment.text, p, Romanization::Yale))
+            }
+            AnnotationStyle::GwoyeuRomatzyh => {
+                pinyin.map(|p| romanize_reading(&segment.text, p, Romanization::GwoyeuRomatzyh))
+            }
+            AnnotationStyle::Tongyong => {
+                pinyin.map(|p| romanize_reading(&segment.text, p, Romanization::Tongyong))
+            }
+            AnnotationStyle::Mps2 => {
+                pinyin.map(|p| romanize_reading(&segment.text, p, Romanization::Mps2))
+            }
+            AnnotationStyle::Ipa => pinyin.map(|p| {
+                convert_syllables(&segment.text, p, |s| {
+                    ipa::transcribe(s, self.config.ipa_tones)
+                })
+                .join(" ")
+            }),
+            AnnotationStyle::Jyutping => segment.jyutping.clone(),
+            AnnotationStyle::Yale => segment.jyutping.as_deref().map(jyutping::to_yale),
+        }
+    }
+
+    /// Whether a segment gets a reading: Chinese, above the confidence
+    /// threshold and not a word the learner knows
+    fn is_annotated(&self, segment: &AnnotatedSegment) -> bool {
+        segment.is_chinese
+            && segment.confidence >= self.config.confidence_threshold
+            && !self.is_known(segment)
+    }
+
+    /// Whether the learner knows a word: it is on the known-word list, or
+    /// its level is at or below `annotate_above_level`
+    fn is_known(&self, segment: &AnnotatedSegment) -> bool {
+        let below_level = match (segment.level, self.config.annotate_above_level) {
+            (Some(level), Some(known)) => level <= known,
+            _ => false,
+        };
+        below_level || segment.known
+    }
+
+    /// The primary annotation of a segment that gets a reading
+    fn shown_annotation(&self, segment: &AnnotatedSegment) -> Option<String> {
+        if self.is_annotated(segment) {
+            self.primary_annotation(segment)
+        } else {
+            None
+        }
+    }
+
+    /// The second reading shown by the `Both` style: zhuyin
+    fn secondary_annotation<'a>(&self, segment: &'a AnnotatedSegment) -> Option<&'a String> {
+        match self.config.annotation_style {
+            AnnotationStyle::Both => self.shown_zhuyin(segment),
+            _ => None,
+        }
+    }
 
-        // Auto-detect script if not explicitly set
-        let use_traditional = if self.config.use_traditional {
-            true
+    /// Zhuyin to display: the spoken reading in tone sandhi mode
+    fn shown_zhuyin<'a>(&self, segment: &'a AnnotatedSegment) -> Option<&'a String> {
+        if self.config.tone_sandhi && segment.spoken_zhuyin.is_some() {
+            segment.spoken_zhuyin.as_ref()
         } else {
-            // Auto-detect script
-            self.dictionary.detect_traditional(text)
+            segment.zhuyin.as_ref()
+        }
+    }
+
+    /// Annotate one segmented span of the input
+    fn annotate_span(
+        &self,
+        segment_text: String,
+        segment_chars: &[char],
+        start: usize,
+        neighbors: Neighbors,
+        use_traditional: bool,
+    ) -> AnnotatedSegment {
+        if let Some(annotation_data) = self.dictionary.lookup_word(&segment_text, use_traditional) {
+            // Handle empty annotations (fallback to the opposite script)
+            let final_annotation_data = if annotation_data.is_empty() {
+                self.dictionary
+                    .lookup_word(&segment_text, !use_traditional)
+                    .filter(|fallback_data| !fallback_data.is_empty())
+                    .unwrap_or(annotation_data)
+            } else {
+                annotation_data
+            };
+
+            return self.build_segment(
+                segment_text,
+                &final_annotation_data,
+                start,
+                neighbors,
+                use_traditional,
+            );
+        }
+
+        // No dictionary match
+        let is_chinese = segment_chars.len() == 1 && self.is_chinese_char(segment_chars[0]);
+
+        // Rare characters may still have character-level readings
+        // (e.g. merged from Unihan)
+        let char_data = if is_chinese {
+            self.dictionary.lookup_char(&segment_text)
+        } else {
+            None
         };
 
-        let chars: Vec<char> = text.chars().collect();
-        let mut i = 0;
-
-        while i < chars.len() {
-            // Try to find the longest match in dictionary
-            let text_slice: String = chars[i..].iter().collect();
-            let match_result = self
-                .dictionary
-                .find_longest_match(&text_slice, 0, use_traditional);
-
-            if let Some((matched_len, annotation_data)) = match_result {
-                // Found dictionary match - use original characters for display
-                let segment_text: String = chars[i..i + matched_len].iter().collect();
-                let mut best_pinyin = None;
-                let mut best_zhuyin = None;
-                let mut confidence = 1.0;
-                let mut alternatives = Vec::new();
-
-                // Handle empty annotations (fallback to opposite trie)
-                let final_annotation_data = if annotation_data.is_empty() {
-                    // Try the opposite trie for any empty annotation
-                    let fallback_result =
-                        self.dictionary
-                            .find_longest_match(&segment_text, 0, !use_traditional);
-                    if let Some((_, fallback_data)) = fallback_result {
-                        if !fallback_data.is_empty() {
-                            fallback_data
-                        } else {
-                            annotation_data
-                        }
-                    } else {
-                        annotation_data
-                    }
-                } else {
-                    annotation_data
-                };
+        match char_data {
+            Some(char_data) if !char_data.is_empty() => {
+                self.build_segment(segment_text, &char_data, start, neighbors, use_traditional)
+            }
+            _ => AnnotatedSegment {
+                level: self
+                    .levels
+                    .as_ref()
+                    .and_then(|levels| levels.level(&segment_text)),
+                known: self.known_words.contains(&segment_text),
+                text: segment_text,
+                pinyin: None,
+                zhuyin: None,
+                confidence: 1.0,
+                alternatives: Vec::new(),
+                is_chinese,
+                position: start,
+                definitions: Vec::new(),
+                decision: None,
+                spoken_pinyin: None,
+                spoken_zhuyin: None,
+                jyutping: None,
+                cantonese_decision: None,
+            },
+        }
+    }
 
-                if final_annotation_data.len() == 1 {
-                    // Single pronunciation - high confidence
-                    best_pinyin = Some(final_annotation_data[0].pinyin.clone());
-                    best_zhuyin = Some(final_annotation_data[0].zhuyin.clone());
-                    confidence = 0.95;
-                } else if final_annotation_data.len() > 1 {
-                    // Multiple pronunciations - use direct string matching
-                    let pinyin_options: Vec<String> = final_annotation_data
-                        .iter()
-                        .map(|data| data.pinyin.clone())
-                        .collect();
-
-                    alternatives = pinyin_options.clone();
-
-                    // Direct string matching: find entry where the appropriate field matches input text
-                    let best_index = self.select_by_direct_matching(
-                        &final_annotation_data,
-                        &segment_text,
-                        use_traditional,
-                    );
-                    best_pinyin = Some(final_annotation_data[best_index].pinyin.clone());
-                    best_zhuyin = Some(final_annotation_data[best_index].zhuyin.clone());
-                    confidence = 0.8; // Medium confidence
+    /// Build a Chinese segment, choosing the best reading from its dictionary data
+    fn build_segment(
+        &self,
+        segment_text: String,
+        annotation_data: &[AnnotationData],
+        position: usize,
+        neighbors: Neighbors,
+        use_traditional: bool,
+    ) -> AnnotatedSegment {
+        let mut best_pinyin = None;
+        let mut best_zhuyin = None;
+        let mut confidence = 1.0;
+        let mut alternatives = Vec::new();
+        let mut definitions = Vec::new();
+        let mut decision = None;
+        let mut chosen = 0;
+
+        // Entries repeat a pinyin reading when it has several Cantonese
+        // readings, or as a surname (`Hé` next to `hé`); Mandarin selection
+        // only sees the distinct ones
+        let mut readings: Vec<&String> = Vec::new();
+        for data in annotation_data {
+            let lower = data.pinyin.to_lowercase();
+            match readings.iter().position(|r| r.to_lowercase() == lower) {
+                Some(i) if readings[i] != &data.pinyin && lower == data.pinyin => {
+                    readings[i] = &data.pinyin;
                 }
+                Some(_) => {}
+                None => readings.push(&data.pinyin),
+            }
+        }
 
-                segments.push(AnnotatedSegment {
-                    text: segment_text,
-                    pinyin: best_pinyin,
-                    zhuyin: best_zhuyin,
-                    confidence,
-                    alternatives,
-                    is_chinese: true,
-                    position: i,
-                });
-
-                i += matched_len;
-            } else {
-                // No dictionary match - use original character for display
-                let ch = chars[i];
-                segments.push(AnnotatedSegment {
-                    text: ch.to_string(),
-                    pinyin: None,
-                    zhuyin: None,
-                    confidence: 1.0,
-                    alternatives: Vec::new(),
-                    is_chinese: self.is_chinese_char(ch),
-                    position: i,
-                });
-                i += 1;
+        if readings.len() == 1 {
+            // Single pronunciation - high confidence; the common word's
+            // entry rather than the surname's
+            chosen = annotation_data
+                .iter()
+                .position(|data| &data.pinyin == readings[0])
+                .unwrap_or(0);
+            best_pinyin = Some(annotation_data[chosen].pinyin.clone());
+            best_zhuyin = entry_zhuyin(&segment_text, &annotation_data[chosen]);
+            definitions = annotation_data[chosen].definitions.clone();
+            confidence = 0.95;
+        } else if readings.len() > 1 {
+            // Multiple pronunciations - apply polyphone rules
+            alternatives = readings.into_iter().cloned().collect();
+
+            let (best_index, reason) =
+                self.select_reading(annotation_data, &segment_text, neighbors, use_traditional);
+            decision = Some(Decision {
+                reading: annotation_data[best_index].pinyin.clone(),
+                candidates: alternatives.clone(),
+                reason,
+            });
+            best_pinyin = Some(annotation_data[best_index].pinyin.clone());
+            best_zhuyin = entry_zhuyin(&segment_text, &annotation_data[best_index]);
+            definitions = annotation_data[best_index].definitions.clone();
+            confidence = 0.8; // Medium confidence
+            chosen = best_index;
+        }
+
+        let (jyutping, cantonese_decision) =
+            self.select_jyutping(annotation_data, &segment_text, neighbors, chosen);
+
+        // Sources differ in which entries carry glosses; borrow them from
+        // another entry with the same reading
+        if definitions.is_empty() {
+            if let Some(pinyin) = &best_pinyin {
+                definitions = annotation_data
+                    .iter()
+                    .find(|data| {
+                        !data.definitions.is_empty()
+                            && data.pinyin.to_lowercase() == pinyin.to_lowercase()
+                    })
+                    .map(|data| data.definitions.clone())
+                    .unwrap_or_default();
             }
         }
 
-        Ok(segments)
+        // Lists are in one script; the chosen entry gives the word in the other
+        let entry = annotation_data.get(chosen);
+        let forms = || {
+            std::iter::once(segment_text.as_str()).chain(
+                entry
+                    .into_iter()
+                    .flat_map(|entry| [entry.simplified.as_str(), entry.traditional.as_str()]),
+            )
+        };
+        let level = self
+            .levels
+            .as_ref()
+            .and_then(|levels| forms().find_map(|word| levels.level(word)));
+        let known = forms().any(|word| self.known_words.contains(word));
+
+        // Character-level fallback readings are less reliable than word data
+        if annotation_data.iter().all(|data| data.source.is_unihan()) {
+            confidence *= 0.7;
+        }
+
+        AnnotatedSegment {
+            text: segment_text,
+            pinyin: best_pinyin,
+            zhuyin: best_zhuyin,
+            confidence,
+            alternatives,
+            is_chinese: true,
+            position,
+            definitions,
+            decision,
+            spoken_pinyin: None,
+            spoken_zhuyin: None,
+            jyutping,
+            cantonese_decision,
+            level,
+            known,
+        }
+    }
+
+    /// Choose the Cantonese reading among the distinct Jyutping of the
+    /// entries: the first matching Cantonese polyphone rule, then the
+    /// Jyutping of the entry chosen for the Mandarin reading, then the first.
+    /// Words without any Jyutping are read character by character.
+    fn select_jyutping(
+        &self,
+        annotations: &[AnnotationData],
+        input_text: &str,
+        neighbors: Neighbors,
+        mandarin_index: usize,
+    ) -> (Option<String>, Option<Decision>) {
+        // A dictionary can list a word with no readings at all
+        let Some(mandarin) = annotations.get(mandarin_index) else {
+            return (None, None);
+        };
+
+        let mut candidates: Vec<&str> = Vec::new();
+        for annotation in annotations {
+            let jyutping = annotation.jyutping.as_str();
+            if !jyutping.is_empty() && !candidates.contains(&jyutping) {
+                candidates.push(jyutping);
+            }
+        }
+
+        match candidates.len() {
+            0 => {
+                return (self.compose_jyutping(input_text, &mandarin.pinyin), None);
+            }
+            1 => return (Some(candidates[0].to_string()), None),
+            _ => {}
+        }
+
+        let mandarin_entry = candidates
+            .iter()
+            .position(|&jyutping| jyutping == mandarin.jyutping);
+
+        let (index, reason) = match self.polyphone_rules.select(
+            RuleLanguage::Cantonese,
+            input_text,
+            neighbors.previous,
+            neighbors.next,
+            &candidates,
+        ) {
+            Some((rule_index, index)) => (
+                index,
+                DecisionReason::Rule {
+                    index: rule_index,
+                    rule: self.polyphone_rules.rules()[rule_index].describe(),
+                },
+            ),
+            None => match mandarin_entry {
+                Some(index) => (index, DecisionReason::MandarinEntry),
+                None => (0, DecisionReason::First),
+            },
+        };
+
+        let reading = candidates[index].to_string();
+        let decision = Decision {
+            reading: reading.clone(),
+            candidates: candidates.iter().map(|c| c.to_string()).collect(),
+            reason,
+        };
+        (Some(reading), Some(decision))
+    }
+
+    /// Jyutping for a word no Cantonese source lists, from its characters.
+    /// Each character takes the Jyutping of its entry for the matching pinyin
+    /// syllable, or its first Jyutping.
+    fn compose_jyutping(&self, text: &str, pinyin: &str) -> Option<String> {
+        if !self.dictionary.has_jyutping() {
+            return None;
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        let syllables: Vec<&str> = pinyin.split_whitespace().collect();
+        let aligned = syllables.len() == chars.len();
+
+        let mut readings = Vec::with_capacity(chars.len());
+        let mut buf = [0u8; 4];
+        for (i, ch) in chars.iter().enumerate() {
+            let entries = self.dictionary.lookup_char(ch.encode_utf8(&mut buf))?;
+            let with_jyutping = || entries.iter().filter(|entry| !entry.jyutping.is_empty());
+
+            let same_reading = with_jyutping().find(|entry| {
+                aligned && entry.pinyin.to_lowercase() == syllables[i].to_lowercase()
+            });
+            let entry = same_reading.or_else(|| with_jyutping().next())?;
+            readings.push(entry.jyutping.clone());
+        }
+
+        Some(readings.join(" "))
     }
 
     pub fn format_output(&self, segments: &[AnnotatedSegment]) -> String {
@@ -187,6 +686,9 @@ impl ProductionAnnotator {
             OutputFormat::Ruby => self.format_ruby(segments),
             OutputFormat::Table => self.format_table(segments),
             OutputFormat::Rows => self.format_rows(segments),
+            // An EPUB is binary; as text it is previewed as one HTML page
+            OutputFormat::Html | OutputFormat::Epub => self.format_html(segments),
+            OutputFormat::Latex => self.format_latex(segments),
         }
     }
 
@@ -194,12 +696,8 @@ impl ProductionAnnotator {
         let mut result = String::new();
 
         for segment in segments {
-            if segment.is_chinese && segment.confidence >= self.config.confidence_threshold {
-                let annotation = match self.config.annotation_style {
-                    AnnotationStyle::Pinyin => segment.pinyin.as_ref(),
-                    AnnotationStyle::Zhuyin => segment.zhuyin.as_ref(),
-                    AnnotationStyle::Both => segment.pinyin.as_ref(), // Primary annotation
-                };
+            if self.is_annotated(segment) {
+                let annotation = self.primary_annotation(segment);
 
                 if let Some(ann) = annotation {
                     result.push_str(&segment.text);
@@ -209,15 +707,13 @@ impl ProductionAnnotator {
                     let concatenated_ann = if segment.text.chars().count() > 1 {
                         ann.replace(" ", "")
                     } else {
-                        ann.to_string()
+                        ann
                     };
                     result.push_str(&concatenated_ann);
 
-                    if matches!(self.config.annotation_style, AnnotationStyle::Both) {
-                        if let Some(zhuyin) = &segment.zhuyin {
-                            result.push('/');
-                            result.push_str(zhuyin);
-                        }
+                    if let Some(zhuyin) = self.secondary_annotation(segment) {
+                        result.push('/');
+                        result.push_str(zhuyin);
                     }
 
                     if self.config.show_confidence {
@@ -253,10 +749,27 @@ impl ProductionAnnotator {
             text: String,
             pinyin: Option<String>,
             zhuyin: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            spoken_pinyin: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            spoken_zhuyin: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            jyutping: Option<String>,
+            /// Reading in the selected style, for styles derived from pinyin
+            #[serde(skip_serializing_if = "Option::is_none")]
+            annotation: Option<String>,
             confidence: f32,
             alternatives: Vec<String>,
             is_chinese: bool,
             position: usize,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            definitions: Option<Vec<String>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            decision: Option<Decision>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cantonese_decision: Option<Decision>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            level: Option<u8>,
         }
 
         #[derive(Serialize)]
@@ -273,10 +786,30 @@ impl ProductionAnnotator {
                 text: seg.text.clone(),
                 pinyin: seg.pinyin.clone(),
                 zhuyin: seg.zhuyin.clone(),
+                spoken_pinyin: seg.spoken_pinyin.clone(),
+                spoken_zhuyin: seg.spoken_zhuyin.clone(),
+                jyutping: seg.jyutping.clone(),
+                annotation: match self.config.annotation_style {
+                    AnnotationStyle::Pinyin
+                    | AnnotationStyle::Zhuyin
+                    | AnnotationStyle::Both
+                    | AnnotationStyle::Jyutping => None,
+                    _ => self.primary_annotation(seg),
+                },
                 confidence: seg.confidence,
                 alternatives: seg.alternatives.clone(),
                 is_chinese: seg.is_chinese,
                 position: seg.position,
+                definitions: self
+                    .config
+                    .show_definitions
+                    .then(|| seg.definitions.clone()),
+                decision: seg.decision.clone().filter(|_| self.config.explain),
+                cantonese_decision: seg
+                    .cantonese_decision
+                    .clone()
+                    .filter(|_| self.config.explain),
+                level: seg.level,
             })
             .collect();
 
@@ -309,17 +842,17 @@ impl ProductionAnnotator {
         let mut result = String::new();
 
         for segment in segments {
-            if segment.is_chinese && segment.confidence >= self.config.confidence_threshold {
-                let annotation = match self.config.annotation_style {
-                    AnnotationStyle::Pinyin => segment.pinyin.as_ref(),
-                    AnnotationStyle::Zhuyin => segment.zhuyin.as_ref(),
-                    AnnotationStyle::Both => segment.pinyin.as_ref(),
-                };
+            if self.is_annotated(segment) {
+                let annotation = self.primary_annotation(segment);
 
                 if let Some(ann) = annotation {
                     result.push_str(&segment.text);
                     result.push('[');
-                    result.push_str(ann);
+                    result.push_str(&ann);
+                    if let Some(zhuyin) = self.secondary_annotation(segment) {
+                        result.push('|');
+                        result.push_str(zhuyin);
+                    }
                     result.push(']');
                 } else {
                     result.push_str(&segment.text);
@@ -336,19 +869,11 @@ impl ProductionAnnotator {
         let mut result = String::new();
 
         for segment in segments {
-            if segment.is_chinese && segment.confidence >= self.config.confidence_threshold {
-                let annotation = match self.config.annotation_style {
-                    AnnotationStyle::Pinyin => segment.pinyin.as_ref(),
-                    AnnotationStyle::Zhuyin => segment.zhuyin.as_ref(),
-                    AnnotationStyle::Both => segment.pinyin.as_ref(),
-                };
+            if self.is_annotated(segment) {
+                let annotation = self.primary_annotation(segment);
 
                 if let Some(ann) = annotation {
-                    result.push_str("<ruby>");
-                    result.push_str(&segment.text);
-                    result.push_str("<rt>");
-                    result.push_str(ann);
-                    result.push_str("</rt></ruby>");
+                    self.push_ruby(&mut result, segment, &ann, None);
                 } else {
                     result.push_str(&segment.text);
                 }
@@ -360,14 +885,471 @@ impl ProductionAnnotator {
         result
     }
 
+    /// One `<ruby>` element for an annotated segment, with its glosses as a
+    /// tooltip when definitions are shown. `tones` has a tone number per
+    /// syllable of `reading` for tone colors.
+    fn push_ruby(
+        &self,
+        result: &mut String,
+        segment: &AnnotatedSegment,
+        reading: &str,
+        tones: Option<&[u8]>,
+    ) {
+        if self.config.show_definitions && !segment.definitions.is_empty() {
+            result.push_str("<ruby title=\"");
+            result.push_str(&escape_html(&segment.definitions.join("; ")));
+            result.push_str("\">");
+        } else {
+            result.push_str("<ruby>");
+        }
+        let zhuyin = self.secondary_annotation(segment);
+        self.push_ruby_content(result, &segment.text, reading, zhuyin, tones);
+        result.push_str("</ruby>");
+    }
+
+    /// Bases and readings of one ruby element, per character where the
+    /// reading has a syllable for each character, else for the whole text.
+    /// Tone colors need a base per syllable, so the whole-text fallback has none.
+    fn push_ruby_content(
+        &self,
+        result: &mut String,
+        text: &str,
+        reading: &str,
+        zhuyin: Option<&String>,
+        tones: Option<&[u8]>,
+    ) {
+        let pairs = align_syllables(text, reading).filter(|pairs| {
+            (pairs.len() > 1 || tones.is_some_and(|tones| tones.len() == pairs.len()))
+                && zhuyin.is_none_or(|zhuyin| zhuyin.split_whitespace().count() == pairs.len())
+        });
+        let Some(pairs) = pairs else {
+            result.push_str(text);
+            push_ruby_annotations(result, &[reading], zhuyin.map(|z| vec![z.as_str()]), None);
+            return;
+        };
+
+        let zhuyin: Option<Vec<&str>> = zhuyin.map(|z| z.split_whitespace().collect());
+        let classes: Option<Vec<Option<&str>>> = tones
+            .filter(|tones| tones.len() == pairs.len())
+            .map(|tones| tones.iter().map(|&tone| html::tone_class(tone)).collect());
+        let class = |i: usize| classes.as_ref().and_then(|classes| classes[i]);
+
+        match self.config.ruby_layout {
+            RubyLayout::PerCharacter => {
+                for (i, (base, reading)) in pairs.iter().enumerate() {
+                    match class(i) {
+                        Some(class) => {
+                            result.push_str(&format!("<span class=\"{class}\">{base}</span>"))
+                        }
+                        None => result.push_str(base),
+                    }
+                    push_ruby_annotations(
+                        result,
+                        &[reading],
+                        zhuyin.as_ref().map(|z| vec![z[i]]),
+                        Some(&[class(i)][..]),
+                    );
+                }
+            }
+            RubyLayout::Word => {
+                for (i, (base, _)) in pairs.iter().enumerate() {
+                    match class(i) {
+                        Some(class) => result.push_str(&format!("<rb class=\"{class}\">")),
+                        None => result.push_str("<rb>"),
+                    }
+                    result.push_str(base);
+                    result.push_str("</rb>");
+                }
+                let readings: Vec<&str> = pairs.iter().map(|&(_, reading)| reading).collect();
+                let classes: Vec<Option<&str>> = (0..pairs.len()).map(class).collect();
+                push_ruby_annotations(result, &readings, zhuyin, Some(&classes));
+            }
+        }
+    }
+
+    /// Tone number of each syllable of the displayed reading, 0 where a
+    /// token has none (`A4`): Jyutping tones 1-6 for the Cantonese styles,
+    /// else pinyin tones 1-5 (5 is neutral), following tone sandhi
+    fn syllable_tones(&self, segment: &AnnotatedSegment) -> Option<Vec<u8>> {
+        let tones = match self.config.annotation_style {
+            AnnotationStyle::Jyutping | AnnotationStyle::Yale => segment
+                .jyutping
+                .as_ref()?
+                .split_whitespace()
+                .map(|token| Jyutping::parse(token).map_or(0, |syllable| syllable.tone))
+                .collect(),
+            _ => self
+                .shown_pinyin(segment)?
+                .split_whitespace()
+                .map(|token| Syllable::parse(token).map_or(0, |syllable| syllable.tone))
+                .collect(),
+        };
+        Some(tones)
+    }
+
+    /// Ruby markup for each line of the input, with the line as written.
+    /// Text outside annotated words is escaped, and the markup is valid
+    /// XHTML so EPUB chapters can use it too.
+    fn ruby_lines(&self, segments: &[AnnotatedSegment]) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut text = String::new();
+        let mut markup = String::new();
+
+        for segment in segments {
+            let annotation = self.shown_annotation(segment);
+
+            if let Some(ann) = annotation {
+                let tones = if self.config.tone_colors {
+                    self.syllable_tones(segment)
+                } else {
+                    None
+                };
+                text.push_str(&segment.text);
+                self.push_ruby(&mut markup, segment, &ann, tones.as_deref());
+                continue;
+            }
+
+            for c in segment.text.chars() {
+                match c {
+                    '\n' => lines.push(finish_line(&mut text, &mut markup)),
+                    '\r' => {}
+                    c => {
+                        text.push(c);
+                        markup.push_str(&escape_html(c.encode_utf8(&mut [0; 4])));
+                    }
+                }
+            }
+        }
+        if !text.is_empty() {
+            lines.push(finish_line(&mut text, &mut markup));
+        }
+
+        lines
+    }
+
+    /// `lang` tag for documents: `zh-Hant` for traditional text, else `zh-Hans`
+    fn document_lang(&self, segments: &[AnnotatedSegment]) -> &'static str {
+        let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+        if self.is_traditional(&text) {
+            "zh-Hant"
+        } else {
+            "zh-Hans"
+        }
+    }
+
+    /// Document title: the configured one, else the start of the first
+    /// non-blank line, without Markdown heading marks
+    fn document_title(&self, segments: &[AnnotatedSegment]) -> String {
+        if let Some(title) = &self.config.title {
+            return title.clone();
+        }
+        let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+        text.lines()
+            .map(|line| line.trim().trim_start_matches('#').trim())
+            .find(|line| !line.is_empty())
+            .map(|line| line.chars().take(40).collect())
+            .unwrap_or_default()
+    }
+
+    fn document_options<'a>(&self, lang: &'a str, title: &'a str) -> DocumentOptions<'a> {
+        DocumentOptions {
+            lang,
+            title,
+            theme: self.config.html_theme,
+            tone_colors: self.config.tone_colors,
+            toggle_readings: self.config.toggle_readings,
+        }
+    }
+
+    /// A complete HTML document: one `<p>` of ruby markup per paragraph of
+    /// the input, wrapped by [`html::document`]
+    fn format_html(&self, segments: &[AnnotatedSegment]) -> String {
+        let body = html::paragraphs(&self.ruby_lines(segments));
+        let title = escape_html(&self.document_title(segments));
+        html::document(
+            &body,
+            &self.document_options(self.document_lang(segments), &title),
+        )
+    }
+
+    /// An EPUB 3 book of the annotated text, split into chapters at headings
+    /// and double blank lines. The book uses the HTML stylesheet, theme and
+    /// tone colors; readings are always shown, as e-readers may not run the
+    /// click-to-show script.
+    pub fn format_epub(&self, segments: &[AnnotatedSegment]) -> Result<Vec<u8>> {
+        let language = self.document_lang(segments);
+        let title = self.document_title(segments);
+        let chapters = epub::split_chapters(&self.ruby_lines(segments), &title);
+
+        let mut options = self.document_options(language, &title);
+        options.toggle_readings = false;
+
+        epub::package(&Book {
+            title: title.clone(),
+            language: language.to_string(),
+            stylesheet: html::stylesheet(&options),
+            chapters,
+        })
+    }
+
+    fn format_latex(&self, segments: &[AnnotatedSegment]) -> String {
+        let mut result = String::new();
+
+        for segment in segments {
+            let annotation = self.shown_annotation(segment);
+
+            match annotation {
+                Some(ann) => self.push_latex_word(&mut result, segment, &ann),
+                None => result.push_str(&latex::escape(&segment.text)),
+            }
+        }
+
+        if self.config.latex_document {
+            let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+            latex::document(&result, self.is_traditional(&text))
+        } else {
+            result
+        }
+    }
+
+    /// One annotated word in LaTeX: `\xpinyin` for the pinyin styles when
+    /// every character has a plain syllable, else `\ruby` per character
+    /// (`\doubleruby` with zhuyin below for `both`), or for the whole word
+    /// when characters and syllables do not line up
+    fn push_latex_word(&self, result: &mut String, segment: &AnnotatedSegment, reading: &str) {
+        let xpinyin = self.config.latex_markup == LatexMarkup::Xpinyin
+            && matches!(
+                self.config.annotation_style,
+                AnnotationStyle::Pinyin | AnnotationStyle::PinyinNumbered
+            );
+        if xpinyin {
+            let numbered = self
+                .shown_pinyin(segment)
+                .and_then(|pinyin| xpinyin_reading(&segment.text, pinyin));
+            if let Some(numbered) = numbered {
+                result.push_str(&format!(
+                    "\\xpinyin{{{}}}{{{}}}",
+                    latex::escape(&segment.text),
+                    numbered
+                ));
+                return;
+            }
+        }
+
+        let zhuyin = self.secondary_annotation(segment);
+        let pairs = align_syllables(&segment.text, reading).filter(|pairs| {
+            zhuyin.is_none_or(|zhuyin| zhuyin.split_whitespace().count() == pairs.len())
+        });
+        match pairs {
+            Some(pairs) => {
+                let zhuyin: Option<Vec<&str>> = zhuyin.map(|z| z.split_whitespace().collect());
+                for (i, (base, reading)) in pairs.iter().enumerate() {
+                    push_latex_ruby(result, base, reading, zhuyin.as_ref().map(|z| z[i]));
+                }
+            }
+            None => push_latex_ruby(result, &segment.text, reading, zhuyin.map(String::as_str)),
+        }
+    }
+
+    /// Annotate the text of an HTML document in place, leaving tags,
+    /// attributes, scripts, styles, code and existing ruby untouched. Text
+    /// nodes get ruby markup, or inline or bracket annotations when one of
+    /// those formats is chosen. Each text node goes through `normalize`
+    /// first.
+    pub fn annotate_html<N>(&self, html: &str, normalize: N) -> Result<String>
+    where
+        N: Fn(&str) -> String,
+    {
+        markup::annotate_html(html, |text| {
+            let segments = self.annotate(&normalize(text))?;
+            Ok(self.format_fragment(&segments))
+        })
+    }
+
+    /// Annotate the prose of a Markdown document, leaving code, front
+    /// matter, HTML, link destinations and URLs untouched. Prose gets inline
+    /// ruby HTML, or inline or bracket annotations when one of those formats
+    /// is chosen. Each run of prose goes through `normalize` first.
+    pub fn annotate_markdown<N>(&self, markdown: &str, normalize: N) -> Result<String>
+    where
+        N: Fn(&str) -> String,
+    {
+        markup::annotate_markdown(markdown, |text| {
+            let segments = self.annotate(&normalize(text))?;
+            Ok(self.format_fragment(&segments))
+        })
+    }
+
+    /// Annotated text to put back into a marked-up document: inline and
+    /// bracket output as they are, ruby markup for every other format
+    fn format_fragment(&self, segments: &[AnnotatedSegment]) -> String {
+        match self.config.output_format {
+            OutputFormat::Inline => self.format_inline(segments),
+            OutputFormat::Brackets => self.format_brackets(segments),
+            _ => self.format_ruby(segments),
+        }
+    }
+
+    /// Annotate the dialogue of a subtitle file and write it back in the
+    /// same format, with readings placed as `subtitle_mode` says. Each line
+    /// of dialogue goes through `normalize` first; styles, override tags and
+    /// notes are left as written.
+    pub fn annotate_subtitles<N>(
+        &self,
+        input: &str,
+        format: SubtitleFormat,
+        normalize: N,
+    ) -> Result<String>
+    where
+        N: Fn(&str) -> String,
+    {
+        subtitles::annotate(input, format, self.config.subtitle_mode, |text| {
+            let segments = self.annotate(&normalize(text))?;
+            let mut lines = vec![self.reading_line(&segments, false)];
+            if matches!(self.config.annotation_style, AnnotationStyle::Both) {
+                lines.push(self.reading_line(&segments, true));
+            }
+            Ok(Annotation {
+                lines,
+                ruby: self.cue_ruby(&segments),
+            })
+        })
+    }
+
+    /// The readings of a line of text on their own: one word per reading,
+    /// with punctuation written in ASCII and attached to the words (`你好，
+    /// 世界！` → `nǐhǎo, shìjiè!`). `zhuyin` gives the zhuyin of `both`.
+    /// Words found in the dictionary but without a reading in the style are
+    /// left out; text outside the dictionary is copied as it is.
+    fn reading_line(&self, segments: &[AnnotatedSegment], zhuyin: bool) -> String {
+        let mut line = String::new();
+        // Whether the next word needs a space before it
+        let mut space = false;
+        // A run of Latin letters or digits, which may span segments (`A4`)
+        let mut word = String::new();
+        let push_word = |line: &mut String, space: &mut bool, word: &str| {
+            if *space {
+                line.push(' ');
+            }
+            line.push_str(word);
+            *space = true;
+        };
+
+        for segment in segments {
+            let annotation = match self.shown_annotation(segment) {
+                Some(_) if zhuyin => self.secondary_annotation(segment).cloned(),
+                annotation => annotation,
+            };
+            if let Some(annotation) = annotation {
+                if !word.is_empty() {
+                    push_word(&mut line, &mut space, &std::mem::take(&mut word));
+                }
+                push_word(&mut line, &mut space, &annotation.replace(' ', ""));
+                continue;
+            }
+            // A word with no reading in this style leaves a gap rather
+            // than its characters
+            if self.is_annotated(segment) {
+                continue;
+            }
+
+            for c in segment.text.chars() {
+                let opening = matches!(
+                    c,
+                    '(' | '[' | '{' | '<' | '（' | '“' | '‘' | '「' | '『' | '《' | '〈'
+                );
+                let c = ascii_punctuation(c);
+                if c.is_alphanumeric() {
+                    word.push(c);
+                    continue;
+                }
+                if !word.is_empty() {
+                    push_word(&mut line, &mut space, &std::mem::take(&mut word));
+                }
+                match c {
+                    c if c.is_whitespace() => {}
+                    c if opening => {
+                        push_word(&mut line, &mut space, &c.to_string());
+                        space = false;
+                    }
+                    // Closing punctuation goes right after the word before it
+                    ',' | '.' | '!' | '?' | ':' | ';' | ')' | ']' | '}' | '>' | '"' | '\''
+                    | '…' | '%' => line.push(c),
+                    c => push_word(&mut line, &mut space, &c.to_string()),
+                }
+            }
+        }
+        if !word.is_empty() {
+            push_word(&mut line, &mut space, &word);
+        }
+
+        line
+    }
+
+    /// WebVTT cue text with ruby around the words; WebVTT ruby has no
+    /// `<rb>` or `<rtc>`, so `both` writes `pinyin/zhuyin` in each `<rt>`
+    fn cue_ruby(&self, segments: &[AnnotatedSegment]) -> String {
+        let mut result = String::new();
+
+        for segment in segments {
+            let Some(ann) = self.shown_annotation(segment) else {
+                result.push_str(&segment.text);
+                continue;
+            };
+            let zhuyin = self.secondary_annotation(segment);
+            let pairs = align_syllables(&segment.text, &ann)
+                .filter(|pairs| {
+                    zhuyin.is_none_or(|zhuyin| zhuyin.split_whitespace().count() == pairs.len())
+                })
+                .unwrap_or_else(|| vec![(segment.text.as_str(), ann.as_str())]);
+            let zhuyin: Vec<&str> = match zhuyin {
+                Some(zhuyin) if pairs.len() > 1 => zhuyin.split_whitespace().collect(),
+                Some(zhuyin) => vec![zhuyin.as_str()],
+                None => Vec::new(),
+            };
+
+            result.push_str("<ruby>");
+            for (i, (base, reading)) in pairs.iter().enumerate() {
+                result.push_str(base);
+                result.push_str("<rt>");
+                result.push_str(reading);
+                if let Some(zhuyin) = zhuyin.get(i) {
+                    result.push('/');
+                    result.push_str(zhuyin);
+                }
+                result.push_str("</rt>");
+            }
+            result.push_str("</ruby>");
+        }
+
+        result
+    }
+
     fn format_table(&self, segments: &[AnnotatedSegment]) -> String {
         let mut result = String::new();
-        result.push_str("Position\tText\tPinyin\tZhuyin\tConfidence\tAlternatives\n");
+        result.push_str("Position\tText\tPinyin\tZhuyin\tConfidence\tAlternatives");
+        let cantonese = matches!(
+            self.config.annotation_style,
+            AnnotationStyle::Jyutping | AnnotationStyle::Yale
+        );
+        if self.config.tone_sandhi {
+            result.push_str("\tSpokenPinyin\tSpokenZhuyin");
+        }
+        if cantonese {
+            result.push_str("\tJyutping");
+        }
+        if self.config.show_definitions {
+            result.push_str("\tDefinitions");
+        }
+        if self.levels.is_some() {
+            result.push_str("\tLevel");
+        }
+        result.push('\n');
 
         for segment in segments {
             if segment.is_chinese {
                 result.push_str(&format!(
-                    "{}\t{}\t{}\t{}\t{:.3}\t{}\n",
+                    "{}\t{}\t{}\t{}\t{:.3}\t{}",
                     segment.position,
                     segment.text,
                     segment.pinyin.as_deref().unwrap_or("-"),
@@ -375,6 +1357,29 @@ impl ProductionAnnotator {
                     segment.confidence,
                     segment.alternatives.join("|")
                 ));
+                if self.config.tone_sandhi {
+                    result.push_str(&format!(
+                        "\t{}\t{}",
+                        segment.spoken_pinyin.as_deref().unwrap_or("-"),
+                        segment.spoken_zhuyin.as_deref().unwrap_or("-")
+                    ));
+                }
+                if cantonese {
+                    result.push('\t');
+                    result.push_str(segment.jyutping.as_deref().unwrap_or("-"));
+                }
+                if self.config.show_definitions {
+                    result.push('\t');
+                    result.push_str(&segment.definitions.join("; ").replace('\t', " "));
+                }
+                if let Some(levels) = &self.levels {
+                    result.push('\t');
+                    match segment.level {
+                        Some(level) => result.push_str(&levels.system().label(level)),
+                        None => result.push('-'),
+                    }
+                }
+                result.push('\n');
             }
         }
 
@@ -384,14 +1389,13 @@ impl ProductionAnnotator {
     fn format_rows(&self, segments: &[AnnotatedSegment]) -> String {
         let mut text_segments = Vec::new();
         let mut pinyin_segments = Vec::new();
+        // Third row for the `Both` style
+        let mut zhuyin_segments = Vec::new();
+        let both = matches!(self.config.annotation_style, AnnotationStyle::Both);
 
         for segment in segments {
-            if segment.is_chinese && segment.confidence >= self.config.confidence_threshold {
-                let annotation = match self.config.annotation_style {
-                    AnnotationStyle::Pinyin => segment.pinyin.as_ref(),
-                    AnnotationStyle::Zhuyin => segment.zhuyin.as_ref(),
-                    AnnotationStyle::Both => segment.pinyin.as_ref(), // Primary annotation
-                };
+            if self.is_annotated(segment) {
+                let annotation = self.primary_annotation(segment);
 
                 text_segments.push(segment.text.clone());
 
@@ -399,13 +1403,20 @@ impl ProductionAnnotator {
                     // Remove spaces for cleaner alignment
                     let clean_annotation = ann.replace(" ", "");
                     pinyin_segments.push(clean_annotation);
+                    zhuyin_segments.push(
+                        self.secondary_annotation(segment)
+                            .map(|zhuyin| zhuyin.replace(" ", ""))
+                            .unwrap_or_default(),
+                    );
                 } else {
                     pinyin_segments.push(String::new());
+                    zhuyin_segments.push(String::new());
                 }
             } else if !segment.text.trim().is_empty() {
                 // Include non-Chinese text (punctuation, spaces, etc.)
                 text_segments.push(segment.text.clone());
                 pinyin_segments.push(String::new()); // Empty pinyin for non-Chinese
+                zhuyin_segments.push(String::new());
             }
         }
 
@@ -414,21 +1425,25 @@ impl ProductionAnnotator {
         for i in 0..text_segments.len() {
             let text_width = self.display_width(&text_segments[i]);
             let pinyin_width = self.display_width(&pinyin_segments[i]);
-            column_widths.push(text_width.max(pinyin_width));
+            let zhuyin_width = self.display_width(&zhuyin_segments[i]);
+            column_widths.push(text_width.max(pinyin_width).max(zhuyin_width));
         }
 
         // Build aligned rows
         let mut text_line = String::new();
         let mut pinyin_line = String::new();
+        let mut zhuyin_line = String::new();
 
         for i in 0..text_segments.len() {
             if i > 0 {
                 text_line.push_str("  "); // 2 spaces between columns
                 pinyin_line.push_str("  ");
+                zhuyin_line.push_str("  ");
             }
 
             let text_width = self.display_width(&text_segments[i]);
             let pinyin_width = self.display_width(&pinyin_segments[i]);
+            let zhuyin_width = self.display_width(&zhuyin_segments[i]);
             let column_width = column_widths[i];
 
             // Add text with padding
@@ -438,9 +1453,16 @@ impl ProductionAnnotator {
             // Add pinyin with padding
             pinyin_line.push_str(&pinyin_segments[i]);
             pinyin_line.push_str(&" ".repeat(column_width - pinyin_width));
+
+            zhuyin_line.push_str(&zhuyin_segments[i]);
+            zhuyin_line.push_str(&" ".repeat(column_width - zhuyin_width));
         }
 
-        format!("{}\n{}", text_line, pinyin_line)
+        if both {
+            format!("{}\n{}\n{}", text_line, pinyin_line, zhuyin_line)
+        } else {
+            format!("{}\n{}", text_line, pinyin_line)
+        }
     }
 
     /// Calculate display width for terminal output (Chinese chars = 2, Latin = 1)
@@ -465,6 +1487,8 @@ impl ProductionAnnotator {
             0xFFE0..=0xFFE6 |   // Full-width currency symbols
             // Additional full-width ranges
             0x3000..=0x303F |   // CJK punctuation
+            0x3100..=0x312F |   // Bopomofo
+            0x31A0..=0x31BF |   // Bopomofo extended
             0x2E80..=0x2EFF |   // CJK radicals supplement
             0x2F00..=0x2FDF     // Kangxi radicals
         )
@@ -485,88 +1509,703 @@ impl ProductionAnnotator {
         (dict_entries, model_info)
     }
 
+    /// Replace the configuration, rebuilding the segmenter when
+    /// `config.segmenter` chooses another strategy
     pub fn set_config(&mut self, config: AnnotationConfig) {
+        if config.segmenter != self.config.segmenter {
+            self.segmenter = config.segmenter.build();
+        }
         self.config = config;
     }
 
-    /// Select the best pronunciation from multiple variants
-    fn select_best_pronunciation(
+    /// Choose among several readings: the first matching polyphone rule,
+    /// then the first entry whose headword is written in the input's script,
+    /// then the first entry
+    fn select_reading(
         &self,
-        annotations: &[crate::dictionary::AnnotationData],
-    ) -> usize {
-        if annotations.is_empty() {
-            return 0;
+        annotations: &[AnnotationData],
+        input_text: &str,
+        neighbors: Neighbors,
+        use_traditional: bool,
+    ) -> (usize, DecisionReason) {
+        let candidates: Vec<&str> = annotations.iter().map(|a| a.pinyin.as_str()).collect();
+        if let Some((rule_index, index)) = self.polyphone_rules.select(
+            RuleLanguage::Mandarin,
+            input_text,
+            neighbors.previous,
+            neighbors.next,
+            &candidates,
+        ) {
+            let rule = self.polyphone_rules.rules()[rule_index].describe();
+            return (
+                index,
+                DecisionReason::Rule {
+                    index: rule_index,
+                    rule,
+                },
+            );
         }
 
-        // Simple heuristics to choose the best pronunciation
-        for (i, annotation) in annotations.iter().enumerate() {
-            let pinyin = &annotation.pinyin;
+        // Common-word readings go before surname ones (`hé` before `Hé`)
+        let is_surname = |annotation: &AnnotationData| {
+            annotation
+                .pinyin
+                .chars()
+                .next()
+                .is_some_and(char::is_uppercase)
+        };
+        let script_matches = |annotation: &AnnotationData| {
+            if use_traditional {
+                annotation.traditional == input_text
+            } else {
+                annotation.simplified == input_text
+            }
+        };
 
-            // Prefer standard pronunciations over rare ones
-            // Common patterns that indicate standard pronunciation:
+        let script_match = annotations
+            .iter()
+            .position(|a| script_matches(a) && !is_surname(a))
+            .or_else(|| annotations.iter().position(script_matches));
+
+        match script_match {
+            Some(index) => (index, DecisionReason::ScriptMatch),
+            None => (
+                annotations.iter().position(|a| !is_surname(a)).unwrap_or(0),
+                DecisionReason::First,
+            ),
+        }
+    }
 
-            // 1. Prefer "wǒ" over "ě" for 我
-            if pinyin == "wǒ" {
-                return i;
+    /// Decision log: one line per segment whose reading was chosen among
+    /// several, with the rule or fallback that chose it
+    pub fn format_decisions(&self, segments: &[AnnotatedSegment]) -> String {
+        let mut result = String::new();
+        for segment in segments {
+            for decision in segment.decision.iter().chain(&segment.cantonese_decision) {
+                result.push_str(&format!(
+                    "{}\t{}\t{}\n",
+                    segment.position,
+                    segment.text,
+                    decision.explain()
+                ));
             }
+        }
+        result
+    }
+}
 
-            // 2. Prefer "nǐ" over "nì" for 你
-            if pinyin == "nǐ" {
-                return i;
-            }
+/// Text of the segments on either side of the one being annotated
+#[derive(Clone, Copy)]
+struct Neighbors<'a> {
+    previous: &'a str,
+    next: &'a str,
+}
 
-            // 3. Prefer "hǎo" over "hào" for 好
-            if pinyin == "hǎo" {
-                return i;
+/// `<rt>` elements for the readings; with a second row of readings (zhuyin
+/// in the `Both` style) each row goes in its own `<rtc>` container, pinyin
+/// above and zhuyin below. `classes` holds a tone class per reading.
+fn push_ruby_annotations(
+    result: &mut String,
+    readings: &[&str],
+    second: Option<Vec<&str>>,
+    classes: Option<&[Option<&str>]>,
+) {
+    let push_rts = |result: &mut String, readings: &[&str]| {
+        for (i, reading) in readings.iter().enumerate() {
+            match classes.and_then(|classes| classes.get(i).copied().flatten()) {
+                Some(class) => result.push_str(&format!("<rt class=\"{class}\">")),
+                None => result.push_str("<rt>"),
             }
+            result.push_str(reading);
+            result.push_str("</rt>");
+        }
+    };
+
+    match second {
+        Some(second) => {
+            result.push_str("<rtc>");
+            push_rts(result, readings);
+            result.push_str("</rtc><rtc>");
+            push_rts(result, &second);
+            result.push_str("</rtc>");
+        }
+        None => push_rts(result, readings),
+    }
+}
 
-            // 4. Prefer "shì" over "sì" for 是
-            if pinyin == "shì" {
-                return i;
-            }
+/// An entry's zhuyin, derived from its pinyin when the source has none
+fn entry_zhuyin(text: &str, data: &AnnotationData) -> Option<String> {
+    if !data.zhuyin.is_empty() {
+        return Some(data.zhuyin.clone());
+    }
+    (!data.pinyin.is_empty())
+        .then(|| convert_syllables(text, &data.pinyin, Syllable::zhuyin).join(" "))
+}
 
-            // 5. Prefer "de" over "dí" for 的
-            if pinyin == "de" {
-                return i;
-            }
+/// A word's reading in another romanization
+fn romanize_reading(text: &str, pinyin: &str, system: Romanization) -> String {
+    convert_syllables(text, pinyin, |s| romanization::romanize(s, system)).join(" ")
+}
 
-            // 6. Prefer "le" over "liǎo" for 了
-            if pinyin == "le" {
-                return i;
-            }
+/// `\ruby{base}{reading}`, or `\doubleruby{base}{reading}{below}` with a
+/// second reading under the base. Latin text read as itself (`A4`) stays plain.
+fn push_latex_ruby(result: &mut String, base: &str, reading: &str, below: Option<&str>) {
+    if base == reading && below.is_none_or(|below| below == reading) {
+        result.push_str(&latex::escape(base));
+        return;
+    }
+    let base = latex::escape(base);
+    let reading = latex::escape(reading);
+    match below {
+        Some(below) => result.push_str(&format!(
+            "\\doubleruby{{{}}}{{{}}}{{{}}}",
+            base,
+            reading,
+            latex::escape(below)
+        )),
+        None => result.push_str(&format!("\\ruby{{{}}}{{{}}}", base, reading)),
+    }
+}
+
+/// Numbered pinyin for `\xpinyin`, which pairs each character with one
+/// syllable; `None` for words with erhua or Latin letters (`A4`)
+fn xpinyin_reading(text: &str, pinyin: &str) -> Option<String> {
+    let syllables: Vec<Syllable> = pinyin
+        .split_whitespace()
+        .map(Syllable::parse)
+        .collect::<Option<_>>()?;
+    if syllables.len() != text.chars().count()
+        || syllables.iter().any(|s| s.erhua)
+        || text.chars().any(|c| c.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+    Some(
+        syllables
+            .iter()
+            .map(Syllable::numbered)
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// ASCII form of full-width and CJK punctuation for reading lines
+fn ascii_punctuation(c: char) -> char {
+    match c {
+        '，' | '、' => ',',
+        '。' | '．' => '.',
+        '！' => '!',
+        '？' => '?',
+        '：' => ':',
+        '；' => ';',
+        '（' => '(',
+        '）' => ')',
+        '“' | '”' | '「' | '」' | '『' | '』' => '"',
+        '‘' | '’' => '\'',
+        '《' | '〈' => '<',
+        '》' | '〉' => '>',
+        '～' => '~',
+        '　' => ' ',
+        c => c,
+    }
+}
+
+/// Take the text and markup collected for a line, trimming ASCII spaces
+/// but keeping ideographic ones, which indent Chinese paragraphs
+fn finish_line(text: &mut String, markup: &mut String) -> Line {
+    let line = Line {
+        text: text
+            .trim_matches(|c: char| c.is_ascii_whitespace())
+            .to_string(),
+        markup: markup
+            .trim_matches(|c: char| c.is_ascii_whitespace())
+            .to_string(),
+    };
+    text.clear();
+    markup.clear();
+    line
+}
+
+/// Escape text for use in HTML content or attribute values
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
         }
+    }
+    escaped
+}
 
-        // If no specific rules match, return the first one
-        0
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::fixture::{self, entry};
+    use crate::levels::LevelSystem;
+
+    fn annotator(entries: &[AnnotationData], config: AnnotationConfig) -> ProductionAnnotator {
+        ProductionAnnotator::with_dictionary(fixture::dictionary(entries), config)
     }
 
-    /// Select pronunciation by direct string matching
-    fn select_by_direct_matching(
-        &self,
-        annotations: &[crate::dictionary::AnnotationData],
-        input_text: &str,
-        use_traditional: bool,
-    ) -> usize {
-        if annotations.is_empty() {
-            return 0;
+    fn style(annotation_style: AnnotationStyle) -> AnnotationConfig {
+        AnnotationConfig {
+            annotation_style,
+            ..Default::default()
         }
+    }
 
-        // Direct string matching: find entry where the appropriate field matches input text
-        for (i, annotation) in annotations.iter().enumerate() {
-            if use_traditional {
-                // For Traditional input, match against traditional field
-                if annotation.traditional == input_text {
-                    return i;
-                }
-            } else {
-                // For Simplified input, match against simplified field
-                if annotation.simplified == input_text {
-                    return i;
-                }
-            }
+    /// `text` annotated over `entries` in `output_format` and `annotation_style`
+    fn formatted(
+        entries: &[AnnotationData],
+        output_format: OutputFormat,
+        annotation_style: AnnotationStyle,
+        text: &str,
+    ) -> String {
+        let config = AnnotationConfig {
+            output_format,
+            annotation_style,
+            ..Default::default()
+        };
+        let annotator = annotator(entries, config);
+        annotator.format_output(&annotator.annotate(text).unwrap())
+    }
+
+    #[test]
+    fn ruby_layouts_align_readings_or_fall_back_to_the_whole_word() {
+        let entries = [
+            entry("中国", "中國", "zhōng guó"),
+            entry("一点儿", "一點兒", "yī diǎnr"),
+            entry("哪儿", "哪兒", "nǎr"),
+        ];
+        let ruby = |ruby_layout, text| {
+            let config = AnnotationConfig {
+                output_format: OutputFormat::Ruby,
+                ruby_layout,
+                ..Default::default()
+            };
+            let annotator = annotator(&entries, config);
+            annotator.format_output(&annotator.annotate(text).unwrap())
+        };
+
+        assert_eq!(
+            ruby(RubyLayout::PerCharacter, "中国"),
+            "<ruby>中<rt>zhōng</rt>国<rt>guó</rt></ruby>"
+        );
+        assert_eq!(
+            ruby(RubyLayout::Word, "中国"),
+            "<ruby><rb>中</rb><rb>国</rb><rt>zhōng</rt><rt>guó</rt></ruby>"
+        );
+
+        // Erhua keeps 儿 with the syllable it belongs to
+        assert_eq!(
+            ruby(RubyLayout::PerCharacter, "一点儿"),
+            "<ruby>一<rt>yī</rt>点儿<rt>diǎnr</rt></ruby>"
+        );
+        assert_eq!(
+            ruby(RubyLayout::Word, "一点儿"),
+            "<ruby><rb>一</rb><rb>点儿</rb><rt>yī</rt><rt>diǎnr</rt></ruby>"
+        );
+
+        // A single syllable over two characters annotates the whole word
+        for layout in [RubyLayout::PerCharacter, RubyLayout::Word] {
+            assert_eq!(ruby(layout, "哪儿"), "<ruby>哪儿<rt>nǎr</rt></ruby>");
         }
+    }
+
+    #[test]
+    fn romanized_readings_keep_latin_letters() {
+        assert_eq!(
+            romanize_reading("AA制", "A A zhì", Romanization::WadeGiles),
+            "A A chih⁴"
+        );
+    }
+
+    #[test]
+    fn both_style_renders_pinyin_and_zhuyin_in_every_format() {
+        let entries = [entry("我", "我", "wǒ"), entry("中国", "中國", "zhōng guó")];
+        let both =
+            |output_format| formatted(&entries, output_format, AnnotationStyle::Both, "我爱中国");
 
-        // Fallback: if no exact match found, use the old heuristic method
-        self.select_best_pronunciation(annotations)
+        assert_eq!(
+            both(OutputFormat::Inline),
+            "我(wǒ/ㄨㄛˇ)爱中国(zhōngguó/ㄓㄨㄥ ㄍㄨㄛˊ)"
+        );
+        assert_eq!(
+            both(OutputFormat::Brackets),
+            "我[wǒ|ㄨㄛˇ]爱中国[zhōng guó|ㄓㄨㄥ ㄍㄨㄛˊ]"
+        );
+        assert_eq!(
+            both(OutputFormat::Ruby),
+            "<ruby>我<rtc><rt>wǒ</rt></rtc><rtc><rt>ㄨㄛˇ</rt></rtc></ruby>爱\
+             <ruby>中<rtc><rt>zhōng</rt></rtc><rtc><rt>ㄓㄨㄥ</rt></rtc>\
+             国<rtc><rt>guó</rt></rtc><rtc><rt>ㄍㄨㄛˊ</rt></rtc></ruby>"
+        );
+
+        let rows = both(OutputFormat::Rows);
+        let rows: Vec<&str> = rows.lines().map(str::trim_end).collect();
+        assert_eq!(
+            rows,
+            [
+                "我     爱  中国",
+                "wǒ         zhōngguó",
+                "ㄨㄛˇ      ㄓㄨㄥㄍㄨㄛˊ",
+            ]
+        );
+
+        let table = both(OutputFormat::Table);
+        let mut table = table.lines();
+        assert_eq!(
+            table.next(),
+            Some("Position\tText\tPinyin\tZhuyin\tConfidence\tAlternatives")
+        );
+        assert_eq!(
+            table.nth(2),
+            Some("2\t中国\tzhōng guó\tㄓㄨㄥ ㄍㄨㄛˊ\t0.950\t")
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&both(OutputFormat::Json)).unwrap();
+        let word = &json["segments"][2];
+        assert_eq!(word["pinyin"], "zhōng guó");
+        assert_eq!(word["zhuyin"], "ㄓㄨㄥ ㄍㄨㄛˊ");
+        assert_eq!(json["metadata"]["annotation_style"], "Both");
+    }
+
+    #[test]
+    fn definitions_reach_json_table_and_ruby_output() {
+        let mut zhongguo = entry("中国", "中國", "zhōng guó");
+        zhongguo.definitions = vec!["China".to_string()];
+        let mut chuantong = entry("传统", "傳統", "chuán tǒng");
+        chuantong.definitions = ["tradition", "traditional", "convention"]
+            .map(String::from)
+            .to_vec();
+        let mut config = AnnotationConfig {
+            output_format: OutputFormat::Json,
+            show_definitions: true,
+            ..Default::default()
+        };
+        let mut annotator = annotator(&[zhongguo, chuantong], config.clone());
+        let segments = annotator.annotate("中国传统").unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&annotator.format_output(&segments)).unwrap();
+        assert_eq!(json["segments"][0]["definitions"][0], "China");
+        assert_eq!(json["segments"][1]["definitions"][2], "convention");
+
+        config.output_format = OutputFormat::Table;
+        annotator.set_config(config.clone());
+        let table = annotator.format_output(&segments);
+        assert!(table.lines().next().unwrap().ends_with("\tDefinitions"));
+        assert!(table.contains("\ttradition; traditional; convention"));
+
+        config.output_format = OutputFormat::Ruby;
+        annotator.set_config(config);
+        assert!(annotator
+            .format_output(&segments)
+            .starts_with("<ruby title=\"China\">"));
+    }
+
+    #[test]
+    fn html_documents_tag_the_script_and_color_tones() {
+        let entries = [entry("中国", "中國", "zhōng guó"), entry("国", "國", "guó")];
+        let html = |text| {
+            let config = AnnotationConfig {
+                output_format: OutputFormat::Html,
+                tone_colors: true,
+                ..Default::default()
+            };
+            let annotator = annotator(&entries, config);
+            annotator.format_output(&annotator.annotate(text).unwrap())
+        };
+
+        let simplified = html("中国\n\n<中国>");
+        assert!(simplified.contains("<html lang=\"zh-Hans\">"));
+        assert!(simplified.contains("<title>中国</title>"));
+        assert!(simplified.contains(
+            "<p><ruby><span class=\"tone1\">中</span><rt class=\"tone1\">zhōng</rt>\
+             <span class=\"tone2\">国</span><rt class=\"tone2\">guó</rt></ruby></p>\n\
+             <p>&lt;<ruby>"
+        ));
+        assert!(html("中國").contains("<html lang=\"zh-Hant\">"));
+    }
+
+    #[test]
+    fn html_blank_lines_separate_paragraphs() {
+        let config = AnnotationConfig {
+            output_format: OutputFormat::Html,
+            ..Default::default()
+        };
+        let annotator = annotator(&[entry("国", "國", "guó")], config);
+        let html = annotator.format_output(&annotator.annotate("一\n二\n\n  \n国").unwrap());
+
+        assert!(
+            html.contains("<body>\n<p>一<br>\n二</p>\n<p><ruby>国<rt>guó</rt></ruby></p>\n</body>")
+        );
+    }
+
+    #[test]
+    fn latex_uses_xpinyin_where_it_can_and_ruby_otherwise() {
+        let entries = [
+            entry("中国", "中國", "zhōng guó"),
+            entry("卡拉OK", "卡拉OK", "kǎ lā O K"),
+            entry("哪儿", "哪兒", "nǎr"),
+        ];
+        let latex = |annotation_style, latex_markup| {
+            let config = AnnotationConfig {
+                output_format: OutputFormat::Latex,
+                annotation_style,
+                latex_markup,
+                ..Default::default()
+            };
+            let annotator = annotator(&entries, config);
+            annotator.format_output(&annotator.annotate("中国$卡拉OK哪儿").unwrap())
+        };
+
+        assert_eq!(
+            latex(AnnotationStyle::Pinyin, LatexMarkup::Xpinyin),
+            r"\xpinyin{中国}{zhong1 guo2}\$\ruby{卡}{kǎ}\ruby{拉}{lā}OK\ruby{哪儿}{nǎr}"
+        );
+        assert_eq!(
+            latex(AnnotationStyle::Pinyin, LatexMarkup::Ruby),
+            r"\ruby{中}{zhōng}\ruby{国}{guó}\$\ruby{卡}{kǎ}\ruby{拉}{lā}OK\ruby{哪儿}{nǎr}"
+        );
+        assert_eq!(
+            latex(AnnotationStyle::Zhuyin, LatexMarkup::Xpinyin),
+            r"\ruby{中}{ㄓㄨㄥ}\ruby{国}{ㄍㄨㄛˊ}\$\ruby{卡}{ㄎㄚˇ}\ruby{拉}{ㄌㄚ}OK\ruby{哪儿}{ㄋㄚˇㄦ}"
+        );
+        assert_eq!(
+            latex(AnnotationStyle::Both, LatexMarkup::Xpinyin),
+            concat!(
+                r"\doubleruby{中}{zhōng}{ㄓㄨㄥ}\doubleruby{国}{guó}{ㄍㄨㄛˊ}\$",
+                r"\doubleruby{卡}{kǎ}{ㄎㄚˇ}\doubleruby{拉}{lā}{ㄌㄚ}OK\doubleruby{哪儿}{nǎr}{ㄋㄚˇㄦ}"
+            )
+        );
+    }
+
+    #[test]
+    fn known_words_and_levels_at_or_below_the_threshold_go_unannotated() {
+        let entries = [
+            entry("我", "我", "wǒ"),
+            entry("爱", "爱", "ài"),
+            entry("中国", "中國", "zhōng guó"),
+        ];
+        let mut levels = LevelList::new(LevelSystem::Hsk3);
+        levels.parse("我 1\n爱 1\n中国 2\n", None);
+        let inline = |annotate_above_level, known: &str| {
+            let config = AnnotationConfig {
+                annotate_above_level,
+                ..Default::default()
+            };
+            let annotator = annotator(&entries, config)
+                .with_levels(levels.clone())
+                .with_known_words(KnownWords::parse(known));
+            annotator.format_output(&annotator.annotate("我爱中国").unwrap())
+        };
+
+        assert_eq!(inline(None, ""), "我(wǒ)爱(ài)中国(zhōngguó)");
+        assert_eq!(inline(Some(1), ""), "我爱中国(zhōngguó)");
+        assert_eq!(inline(Some(2), ""), "我爱中国");
+        assert_eq!(inline(None, "我\n"), "我爱(ài)中国(zhōngguó)");
+        assert_eq!(inline(Some(1), "中国\n"), "我爱中国");
+        // Known words match in either script
+        assert_eq!(inline(None, "中國\n"), "我(wǒ)爱(ài)中国");
+    }
+
+    #[test]
+    fn ipa_style_follows_the_tone_notation_and_keeps_latin_letters() {
+        let entries = [
+            entry("你好", "你好", "nǐ hǎo"),
+            entry("卡拉OK", "卡拉OK", "kǎ lā O K"),
+        ];
+        let ipa = |ipa_tones| {
+            let annotator = annotator(
+                &entries,
+                AnnotationConfig {
+                    annotation_style: AnnotationStyle::Ipa,
+                    ipa_tones,
+                    ..Default::default()
+                },
+            );
+            let segments = annotator.annotate("你好卡拉OK").unwrap();
+            segments
+                .iter()
+                .filter_map(|segment| annotator.primary_annotation(segment))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ipa(ToneNotation::Letters),
+            ["ni˨˩˦ xɑʊ˨˩˦", "kʰa˨˩˦ la˥˥ O K"]
+        );
+        assert_eq!(
+            ipa(ToneNotation::Superscript),
+            ["ni²¹⁴ xɑʊ²¹⁴", "kʰa²¹⁴ la⁵⁵ O K"]
+        );
+    }
+
+    /// Entry with a Cantonese reading as well
+    fn cantonese(simplified: &str, pinyin: &str, jyutping: &str) -> AnnotationData {
+        AnnotationData {
+            jyutping: jyutping.to_string(),
+            ..entry(simplified, simplified, pinyin)
+        }
+    }
+
+    #[test]
+    fn jyutping_styles_pick_by_cantonese_rules_and_compose_missing_readings() {
+        let entries = [
+            cantonese("两", "liǎng", "loeng5"),
+            cantonese("行", "háng", "hong4"),
+            cantonese("行", "xíng", "haang4"),
+            cantonese("你", "nǐ", "nei5"),
+            cantonese("好", "hǎo", "hou2"),
+            entry("你好", "你好", "nǐ hǎo"),
+        ];
+        let annotate = |annotation_style, text| {
+            let annotator = annotator(&entries, style(annotation_style));
+            let segments = annotator.annotate(text).unwrap();
+            segments
+                .iter()
+                .filter_map(|segment| annotator.primary_annotation(segment))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            annotate(AnnotationStyle::Jyutping, "两行"),
+            ["loeng5", "hong4"]
+        );
+        assert_eq!(annotate(AnnotationStyle::Jyutping, "行"), ["haang4"]);
+        assert_eq!(annotate(AnnotationStyle::Yale, "两行"), ["léuhng", "hòhng"]);
+        assert_eq!(annotate(AnnotationStyle::Jyutping, "你好"), ["nei5 hou2"]);
+        assert_eq!(annotate(AnnotationStyle::Yale, "你好"), ["néih hóu"]);
+    }
+
+    #[test]
+    fn words_listed_without_readings_get_no_jyutping() {
+        let annotator = annotator(
+            &[cantonese("你", "nǐ", "nei5")],
+            style(AnnotationStyle::Jyutping),
+        );
+        let neighbors = Neighbors {
+            previous: "",
+            next: "",
+        };
+
+        let (jyutping, decision) = annotator.select_jyutping(&[], "你", neighbors, 0);
+        assert_eq!(jyutping, None);
+        assert!(decision.is_none());
+    }
+
+    #[test]
+    fn html_input_is_annotated_in_its_text_nodes() {
+        let entries = [
+            entry("我", "我", "wǒ"),
+            entry("爱", "爱", "ài"),
+            entry("中国", "中國", "zhōng guó"),
+        ];
+        let html = |output_format| {
+            let config = AnnotationConfig {
+                output_format,
+                ..Default::default()
+            };
+            annotator(&entries, config)
+                .annotate_html("<p title=\"中国\">我愛<b>中国</b></p>", |text| {
+                    text.replace('愛', "爱")
+                })
+                .unwrap()
+        };
+
+        assert_eq!(
+            html(OutputFormat::Ruby),
+            "<p title=\"中国\"><ruby>我<rt>wǒ</rt></ruby><ruby>爱<rt>ài</rt></ruby>\
+             <b><ruby>中<rt>zhōng</rt>国<rt>guó</rt></ruby></b></p>"
+        );
+        assert_eq!(
+            html(OutputFormat::Inline),
+            "<p title=\"中国\">我(wǒ)爱(ài)<b>中国(zhōngguó)</b></p>"
+        );
+    }
+
+    #[test]
+    fn markdown_input_is_annotated_in_its_prose() {
+        let entries = [
+            entry("我", "我", "wǒ"),
+            entry("爱", "爱", "ài"),
+            entry("中国", "中國", "zhōng guó"),
+        ];
+        let markdown = |output_format| {
+            let config = AnnotationConfig {
+                output_format,
+                ..Default::default()
+            };
+            annotator(&entries, config)
+                .annotate_markdown(
+                    "# 我愛[中国](https://中国.example/)\n\n`中国`\n",
+                    |text| text.replace('愛', "爱"),
+                )
+                .unwrap()
+        };
+
+        assert_eq!(
+            markdown(OutputFormat::Ruby),
+            "# <ruby>我<rt>wǒ</rt></ruby><ruby>爱<rt>ài</rt></ruby>\
+             [<ruby>中<rt>zhōng</rt>国<rt>guó</rt></ruby>](https://中国.example/)\n\n`中国`\n"
+        );
+        assert_eq!(
+            markdown(OutputFormat::Inline),
+            "# 我(wǒ)爱(ài)[中国(zhōngguó)](https://中国.example/)\n\n`中国`\n"
+        );
+    }
+
+    #[test]
+    fn subtitle_reading_lines_leave_out_words_without_a_reading() {
+        let mut shijie = entry("世界", "世界", "shì jiè");
+        shijie.zhuyin.clear();
+        let entries = [entry("你好", "你好", "nǐ hǎo"), shijie];
+        let input = "1\n00:00:01,000 --> 00:00:02,000\n你好，龘世界。\n";
+        let annotate = |annotation_style| {
+            annotator(&entries, style(annotation_style))
+                .annotate_subtitles(input, SubtitleFormat::Srt, str::to_string)
+                .unwrap()
+        };
+
+        assert_eq!(
+            annotate(AnnotationStyle::Zhuyin),
+            "1\n00:00:01,000 --> 00:00:02,000\n你好，龘世界。\nㄋㄧˇㄏㄠˇ, ㄕˋㄐㄧㄝˋ.\n"
+        );
+        assert_eq!(
+            annotate(AnnotationStyle::Both),
+            "1\n00:00:01,000 --> 00:00:02,000\n你好，龘世界。\nnǐhǎo, shìjiè.\nㄋㄧˇㄏㄠˇ, ㄕˋㄐㄧㄝˋ.\n"
+        );
+    }
+
+    #[test]
+    fn set_config_rebuilds_the_segmenter() {
+        let entries = [
+            entry("研究", "研究", "yán jiū"),
+            entry("研究生", "研究生", "yán jiū shēng"),
+            entry("生命", "生命", "shēng mìng"),
+            entry("起源", "起源", "qǐ yuán"),
+        ];
+        let words = |annotator: &ProductionAnnotator| {
+            annotator
+                .annotate("研究生命起源")
+                .unwrap()
+                .into_iter()
+                .map(|segment| segment.text)
+                .collect::<Vec<_>>()
+        };
+        let mut annotator = annotator(&entries, AnnotationConfig::default());
+        assert_eq!(words(&annotator), ["研究生", "命", "起源"]);
+
+        annotator.set_config(AnnotationConfig {
+            segmenter: SegmenterKind::Bidirectional,
+            ..Default::default()
+        });
+        assert_eq!(words(&annotator), ["研究", "生命", "起源"]);
     }
 }
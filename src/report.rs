@@ -0,0 +1,466 @@
+//! Readability and vocabulary profile of an annotated text.
+//!
+//! The report counts characters, words and sentences, spreads the words
+//! over the levels of a loaded HSK or TOCFL list, lists the characters the
+//! dictionary has no reading for and the most frequent words, and estimates
+//! how hard the text is to read. It is written as JSON or as a plain-text
+//! table.
+
+use crate::flashcards;
+use crate::levels::LevelList;
+use crate::production_annotator::AnnotatedSegment;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Highest level whose words count as basic for the readability score
+const INTERMEDIATE_LEVEL: u8 = 3;
+
+/// Share of the words a reader should know to follow a text without help
+const COMPREHENSION_COVERAGE: f32 = 0.95;
+
+/// Summary of a text
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Chinese characters
+    pub characters: usize,
+    /// Chinese words (segments), counting repeats
+    pub words: usize,
+    pub unique_words: usize,
+    pub sentences: usize,
+    /// Chinese characters per sentence
+    pub average_sentence_length: f32,
+    /// Words with more than one dictionary reading
+    pub polyphonic_words: usize,
+    pub polyphonic_share: f32,
+    /// Word counts per level, when a level list is loaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub levels: Option<LevelProfile>,
+    /// Chinese characters without a dictionary reading, most frequent first
+    pub out_of_dictionary: Vec<CharacterCount>,
+    pub top_words: Vec<WordCount>,
+    pub readability: Readability,
+}
+
+/// Words of the text per level of a proficiency scale
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelProfile {
+    pub system: String,
+    pub levels: Vec<LevelCount>,
+    /// Words on none of the loaded lists
+    pub unlisted: LevelCount,
+    /// The lowest level whose words make up 95% of the text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage_level: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelCount {
+    pub level: String,
+    pub words: usize,
+    pub unique_words: usize,
+    pub share: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CharacterCount {
+    pub character: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WordCount {
+    pub word: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinyin: Option<String>,
+    pub count: usize,
+}
+
+/// Estimated readability: 100 for short sentences of basic words, 0 for
+/// long sentences of rare words
+#[derive(Debug, Clone, Serialize)]
+pub struct Readability {
+    pub score: u8,
+    pub band: String,
+    /// Share of words above level 3 (HSK 4+, TOCFL B2+) or not listed,
+    /// when a level list is loaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficult_words: Option<f32>,
+}
+
+/// Summarize annotated segments, listing up to `top` frequent words
+pub fn build(segments: &[AnnotatedSegment], levels: Option<&LevelList>, top: usize) -> Report {
+    let words: Vec<&AnnotatedSegment> = segments.iter().filter(|s| s.is_chinese).collect();
+
+    let characters = words
+        .iter()
+        .flat_map(|word| word.text.chars())
+        .filter(|c| c.is_alphabetic() && !c.is_ascii())
+        .count();
+
+    // Counts in order of first appearance, so ties keep the text order
+    let mut word_counts: Vec<(&AnnotatedSegment, usize)> = Vec::new();
+    let mut word_index: HashMap<&str, usize> = HashMap::new();
+    for &word in &words {
+        match word_index.get(word.text.as_str()) {
+            Some(&i) => word_counts[i].1 += 1,
+            None => {
+                word_index.insert(&word.text, word_counts.len());
+                word_counts.push((word, 1));
+            }
+        }
+    }
+
+    let mut out_of_dictionary: Vec<CharacterCount> = Vec::new();
+    for word in &words {
+        if word.pinyin.is_some() || word.jyutping.is_some() {
+            continue;
+        }
+        match out_of_dictionary
+            .iter_mut()
+            .find(|counted| counted.character == word.text)
+        {
+            Some(counted) => counted.count += 1,
+            None => out_of_dictionary.push(CharacterCount {
+                character: word.text.clone(),
+                count: 1,
+            }),
+        }
+    }
+    out_of_dictionary.sort_by_key(|counted| std::cmp::Reverse(counted.count));
+
+    let mut top_words: Vec<WordCount> = word_counts
+        .iter()
+        .filter(|(word, _)| word.pinyin.is_some() || word.jyutping.is_some())
+        .map(|(word, count)| WordCount {
+            word: word.text.clone(),
+            pinyin: word.pinyin.clone(),
+            count: *count,
+        })
+        .collect();
+    top_words.sort_by_key(|word| std::cmp::Reverse(word.count));
+    top_words.truncate(top);
+
+    let sentences = flashcards::sentences(segments)
+        .into_iter()
+        .filter(|&(start, end)| segments[start..end].iter().any(|s| s.is_chinese))
+        .count();
+    let polyphonic_words = words.iter().filter(|w| w.alternatives.len() > 1).count();
+
+    let difficult_words = levels.map(|_| {
+        let difficult = words
+            .iter()
+            .filter(|word| word.level.is_none_or(|level| level > INTERMEDIATE_LEVEL))
+            .count();
+        share(difficult, words.len())
+    });
+    let levels = levels.map(|levels| level_profile(&word_counts, levels));
+    let average_sentence_length = if sentences > 0 {
+        characters as f32 / sentences as f32
+    } else {
+        0.0
+    };
+
+    Report {
+        characters,
+        words: words.len(),
+        unique_words: word_counts.len(),
+        sentences,
+        average_sentence_length,
+        polyphonic_words,
+        polyphonic_share: share(polyphonic_words, words.len()),
+        levels,
+        out_of_dictionary,
+        top_words,
+        readability: readability(average_sentence_length, difficult_words),
+    }
+}
+
+/// Count the words of each level
+fn level_profile(word_counts: &[(&AnnotatedSegment, usize)], levels: &LevelList) -> LevelProfile {
+    let system = levels.system();
+    let total: usize = word_counts.iter().map(|(_, count)| count).sum();
+    let count = |level: Option<u8>| {
+        let matching = word_counts.iter().filter(|(word, _)| word.level == level);
+        let (words, unique_words) = matching.fold((0, 0), |(words, unique), (_, count)| {
+            (words + count, unique + 1)
+        });
+        (words, unique_words)
+    };
+
+    let mut counts = Vec::new();
+    let mut covered = 0;
+    let mut coverage_level = None;
+    for level in system.levels() {
+        let (words, unique_words) = count(Some(level));
+        covered += words;
+        if coverage_level.is_none() && total > 0 && share(covered, total) >= COMPREHENSION_COVERAGE
+        {
+            coverage_level = Some(system.label(level));
+        }
+        counts.push(LevelCount {
+            level: system.label(level),
+            words,
+            unique_words,
+            share: share(words, total),
+        });
+    }
+
+    let (words, unique_words) = count(None);
+    LevelProfile {
+        system: system.name().to_string(),
+        levels: counts,
+        unlisted: LevelCount {
+            level: "Not listed".to_string(),
+            words,
+            unique_words,
+            share: share(words, total),
+        },
+        coverage_level,
+    }
+}
+
+/// Combine sentence length (8 characters is easy, 40 is hard) and the
+/// share of difficult words, weighted equally; without level lists only
+/// sentence length counts
+fn readability(average_sentence_length: f32, difficult_words: Option<f32>) -> Readability {
+    let sentence = ((average_sentence_length - 8.0) / 32.0).clamp(0.0, 1.0);
+    let difficulty = match difficult_words {
+        Some(share) => 0.5 * sentence + 0.5 * share.clamp(0.0, 1.0),
+        None => sentence,
+    };
+    let score = (100.0 * (1.0 - difficulty)).round() as u8;
+    let band = match score {
+        80.. => "easy",
+        60..=79 => "intermediate",
+        40..=59 => "advanced",
+        _ => "difficult",
+    };
+    Readability {
+        score,
+        band: band.to_string(),
+        difficult_words,
+    }
+}
+
+fn share(part: usize, total: usize) -> f32 {
+    if total > 0 {
+        part as f32 / total as f32
+    } else {
+        0.0
+    }
+}
+
+impl Report {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// The report as aligned plain-text tables
+    pub fn to_table(&self) -> String {
+        let mut result = String::new();
+        let mut row = |label: &str, value: String| {
+            result.push_str(&format!("{}{}\n", pad(label, 22), value));
+        };
+
+        row("Characters", self.characters.to_string());
+        row(
+            "Words",
+            format!("{} ({} unique)", self.words, self.unique_words),
+        );
+        row("Sentences", self.sentences.to_string());
+        row(
+            "Sentence length",
+            format!("{:.1} characters", self.average_sentence_length),
+        );
+        row(
+            "Polyphonic words",
+            format!(
+                "{} ({})",
+                self.polyphonic_words,
+                percent(self.polyphonic_share)
+            ),
+        );
+        if let Some(difficult_words) = self.readability.difficult_words {
+            row("Difficult words", percent(difficult_words));
+        }
+        row(
+            "Readability",
+            format!("{}/100 ({})", self.readability.score, self.readability.band),
+        );
+        if let Some(levels) = &self.levels {
+            row(
+                "Reading level",
+                match &levels.coverage_level {
+                    Some(level) => format!("{} (95% of words)", level),
+                    None => format!("above {}", levels.system),
+                },
+            );
+        }
+
+        if let Some(levels) = &self.levels {
+            result.push_str(&format!("\n{}\n", levels.system));
+            result.push_str(&format!(
+                "{}{:>8}{:>8}{:>8}\n",
+                pad("Level", 14),
+                "Words",
+                "Share",
+                "Unique"
+            ));
+            for level in levels.levels.iter().chain([&levels.unlisted]) {
+                result.push_str(&format!(
+                    "{}{:>8}{:>8}{:>8}\n",
+                    pad(&level.level, 14),
+                    level.words,
+                    percent(level.share),
+                    level.unique_words
+                ));
+            }
+        }
+
+        if !self.top_words.is_empty() {
+            result.push_str("\nTop words\n");
+            for (rank, word) in self.top_words.iter().enumerate() {
+                result.push_str(&format!(
+                    "{:>3}. {}{:>6}  {}\n",
+                    rank + 1,
+                    pad(&word.word, 12),
+                    word.count,
+                    word.pinyin.as_deref().unwrap_or("")
+                ));
+            }
+        }
+
+        if !self.out_of_dictionary.is_empty() {
+            result.push_str("\nOut-of-dictionary characters\n");
+            let characters: Vec<String> = self
+                .out_of_dictionary
+                .iter()
+                .map(|counted| format!("{} ×{}", counted.character, counted.count))
+                .collect();
+            result.push_str(&characters.join("  "));
+            result.push('\n');
+        }
+
+        result
+    }
+}
+
+fn percent(share: f32) -> String {
+    format!("{:.1}%", share * 100.0)
+}
+
+/// Pad to a column width, counting CJK characters as two columns
+fn pad(text: &str, width: usize) -> String {
+    let used: usize = text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum();
+    format!("{}{}", text, " ".repeat(width.saturating_sub(used)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::fixture::{self, entry};
+    use crate::levels::LevelSystem;
+    use crate::production_annotator::{AnnotationConfig, ProductionAnnotator};
+
+    fn levels() -> LevelList {
+        let mut levels = LevelList::new(LevelSystem::Hsk3);
+        levels.parse("我 1\n爱 1\n中国 2\n钱 2\n还 4\n", None);
+        levels
+    }
+
+    /// Report on `text`, with the level list when `with_levels` is set
+    fn report(text: &str, with_levels: bool) -> Report {
+        let entries = [
+            entry("我", "我", "wǒ"),
+            entry("爱", "愛", "ài"),
+            entry("中国", "中國", "zhōng guó"),
+            entry("还", "還", "hái"),
+            entry("还", "還", "huán"),
+            entry("钱", "錢", "qián"),
+        ];
+        let annotator = ProductionAnnotator::with_dictionary(
+            fixture::dictionary(&entries),
+            AnnotationConfig::default(),
+        )
+        .with_levels(levels());
+        let segments = annotator.annotate(text).unwrap();
+        build(&segments, annotator.levels().filter(|_| with_levels), 3)
+    }
+
+    #[test]
+    fn counts_characters_words_and_sentences() {
+        let report = report("我爱中国。我还钱。\n龘龘", false);
+
+        assert_eq!(report.characters, 9);
+        assert_eq!(report.words, 8);
+        assert_eq!(report.unique_words, 6);
+        assert_eq!(report.sentences, 3);
+        assert_eq!(report.average_sentence_length, 3.0);
+        assert_eq!(report.polyphonic_words, 1);
+        assert_eq!(report.polyphonic_share, 0.125);
+        assert!(report.levels.is_none());
+
+        let unknown: Vec<(&str, usize)> = report
+            .out_of_dictionary
+            .iter()
+            .map(|counted| (counted.character.as_str(), counted.count))
+            .collect();
+        assert_eq!(unknown, [("龘", 2)]);
+
+        let top: Vec<(&str, usize)> = report
+            .top_words
+            .iter()
+            .map(|word| (word.word.as_str(), word.count))
+            .collect();
+        assert_eq!(top, [("我", 2), ("爱", 1), ("中国", 1)]);
+    }
+
+    #[test]
+    fn profiles_words_by_level() {
+        let profile = report("我爱中国。我还钱。\n龘龘", true).levels.unwrap();
+
+        assert_eq!(profile.system, "HSK 3.0");
+        let counts: Vec<(&str, usize, usize)> = profile
+            .levels
+            .iter()
+            .map(|level| (level.level.as_str(), level.words, level.unique_words))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("HSK 1", 3, 2),
+                ("HSK 2", 2, 2),
+                ("HSK 3", 0, 0),
+                ("HSK 4", 1, 1),
+                ("HSK 5", 0, 0),
+                ("HSK 6", 0, 0),
+                ("HSK 7-9", 0, 0)
+            ]
+        );
+        assert_eq!(profile.unlisted.words, 2);
+        assert_eq!(profile.unlisted.share, 0.25);
+        // A quarter of the words are on no list, so no level covers 95%
+        assert_eq!(profile.coverage_level, None);
+
+        let covered = report("我爱中国。我还钱。", true).levels.unwrap();
+        assert_eq!(covered.coverage_level.as_deref(), Some("HSK 4"));
+    }
+
+    #[test]
+    fn scores_sentence_length_and_difficult_words() {
+        let plain = report("我爱中国。我还钱。\n龘龘", false).readability;
+        assert_eq!((plain.score, plain.band.as_str()), (100, "easy"));
+        assert_eq!(plain.difficult_words, None);
+
+        // 还 (HSK 4) and the two unlisted 龘 are 3 of 8 words
+        let leveled = report("我爱中国。我还钱。\n龘龘", true).readability;
+        assert_eq!(leveled.difficult_words, Some(0.375));
+        assert_eq!((leveled.score, leveled.band.as_str()), (81, "easy"));
+
+        assert_eq!(readability(40.0, None).score, 0);
+        assert_eq!(readability(24.0, None).band, "advanced");
+        assert_eq!(readability(24.0, Some(0.5)).score, 50);
+        assert_eq!(readability(16.0, Some(0.0)).band, "easy");
+        assert_eq!(readability(40.0, Some(0.4)).band, "difficult");
+    }
+}
@@ -0,0 +1,614 @@
+//! Romanizations of Mandarin other than Hanyu Pinyin.
+//!
+//! Every system is derived from a parsed [`Syllable`], so any pinyin the
+//! dictionary stores (marked, numbered or toneless) can be converted:
+//!
+//! | System | 中國 | 你好 |
+//! |--------|------|------|
+//! | Wade-Giles | chung¹ kuo² | ni³ hao³ |
+//! | Yale | jūng gwó | nǐ hǎu |
+//! | Gwoyeu Romatzyh | jong gwo | nii hao |
+//! | Tongyong Pinyin | jhong guó | nǐ hǎo |
+//! | MPS II | jūng guó | nǐ hǎu |
+//!
+//! Gwoyeu Romatzyh spells tones with letters instead of marks; the neutral
+//! tone is written in its basic (first tone) form.
+
+use crate::pinyin::{map_syllables, tone_vowel, Syllable, COMBINING_TONES, TONE_MARKS};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Romanization {
+    /// Wade-Giles with aspiration apostrophes and superscript tone numbers
+    WadeGiles,
+    /// Yale romanization of Mandarin
+    Yale,
+    /// Gwoyeu Romatzyh tonal spelling
+    GwoyeuRomatzyh,
+    /// Tongyong Pinyin (first tone unmarked, neutral tone with a ring)
+    Tongyong,
+    /// Mandarin Phonetic Symbols II
+    Mps2,
+}
+
+/// Superscript tone numbers for Wade-Giles
+const SUPERSCRIPTS: [char; 4] = ['¹', '²', '³', '⁴'];
+
+/// Combining ring above, Tongyong's neutral tone mark
+const RING_ABOVE: char = '\u{030A}';
+
+/// Convert space-separated pinyin in any spelling, passing non-pinyin tokens
+/// through unchanged
+pub fn convert(pinyin: &str, system: Romanization) -> String {
+    map_syllables(pinyin, |syllable| romanize(syllable, system))
+}
+
+/// Convert a single syllable
+pub fn romanize(syllable: &Syllable, system: Romanization) -> String {
+    let romanized = match system {
+        Romanization::WadeGiles => wade_giles(syllable),
+        Romanization::Yale => yale(syllable),
+        Romanization::GwoyeuRomatzyh => gwoyeu_romatzyh(syllable),
+        Romanization::Tongyong => tongyong(syllable),
+        Romanization::Mps2 => mps2(syllable),
+    };
+
+    if syllable.capitalized {
+        let mut chars = romanized.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => romanized,
+        }
+    } else {
+        romanized
+    }
+}
+
+fn wade_giles(syllable: &Syllable) -> String {
+    let initial = syllable.initial;
+    let mut result = if syllable.is_apical() {
+        match initial {
+            "z" => "tzŭ",
+            "c" => "tz'ŭ",
+            "s" => "ssŭ",
+            "zh" => "chih",
+            "ch" => "ch'ih",
+            "sh" => "shih",
+            _ => "jih",
+        }
+        .to_string()
+    } else if initial.is_empty() {
+        wade_giles_zero_initial(syllable.final_).to_string()
+    } else {
+        let initial_spelling = match initial {
+            "b" => "p",
+            "p" => "p'",
+            "d" => "t",
+            "t" => "t'",
+            "g" => "k",
+            "k" => "k'",
+            "j" | "zh" => "ch",
+            "q" | "ch" => "ch'",
+            "x" => "hs",
+            "r" => "j",
+            "z" => "ts",
+            "c" => "ts'",
+            other => other,
+        };
+        let final_spelling = match syllable.final_ {
+            "e" if matches!(initial, "g" | "k" | "h") => "o",
+            "uo" if !matches!(initial, "g" | "k" | "h" | "sh") => "o",
+            "uei" if matches!(initial, "g" | "k") => "uei",
+            other => wade_giles_final(other),
+        };
+        format!("{}{}", initial_spelling, final_spelling)
+    };
+
+    if syllable.erhua {
+        result.push_str("rh");
+    }
+    if (1..=4).contains(&syllable.tone) {
+        result.push(SUPERSCRIPTS[syllable.tone as usize - 1]);
+    }
+    result
+}
+
+fn wade_giles_final(final_: &str) -> &str {
+    match final_ {
+        "e" => "ê",
+        "ê" => "eh",
+        "en" => "ên",
+        "eng" => "êng",
+        "ong" => "ung",
+        "er" => "êrh",
+        "ie" => "ieh",
+        "iou" => "iu",
+        "ian" => "ien",
+        "iong" => "iung",
+        "uei" => "ui",
+        "uen" => "un",
+        "ueng" => "ung",
+        "üe" => "üeh",
+        other => other,
+    }
+}
+
+fn wade_giles_zero_initial(final_: &str) -> &str {
+    match final_ {
+        "i" => "i",
+        "ia" => "ya",
+        "io" => "yo",
+        "ie" => "yeh",
+        "iao" => "yao",
+        "iou" => "yu",
+        "ian" => "yen",
+        "in" => "yin",
+        "iang" => "yang",
+        "ing" => "ying",
+        "iong" => "yung",
+        "u" => "wu",
+        "ua" => "wa",
+        "uo" => "wo",
+        "uai" => "wai",
+        "uei" => "wei",
+        "uan" => "wan",
+        "uen" => "wên",
+        "uang" => "wang",
+        "ueng" => "wêng",
+        "ü" => "yü",
+        "üe" => "yüeh",
+        "üan" => "yüan",
+        "ün" => "yün",
+        other => wade_giles_final(other),
+    }
+}
+
+fn yale(syllable: &Syllable) -> String {
+    let initial = syllable.initial;
+    let mut result = if syllable.is_apical() {
+        match initial {
+            "zh" => "jr",
+            "ch" => "chr",
+            "sh" => "shr",
+            "r" => "r",
+            "z" => "dz",
+            "c" => "tsz",
+            _ => "sz",
+        }
+        .to_string()
+    } else {
+        // With an initial, i, in, ing and u stay vowels and uen is wun;
+        // every other medial is written y or w as in the zero-initial forms
+        let final_spelling = match (initial.is_empty(), syllable.final_) {
+            (false, "i") => "i",
+            (false, "in") => "in",
+            (false, "ing") => "ing",
+            (false, "u") => "u",
+            (false, "uen") => "wun",
+            (false, "o") if matches!(initial, "b" | "p" | "m" | "f") => "wo",
+            (_, final_) => yale_final(final_),
+        };
+        let initial_spelling = match initial {
+            "x" if matches!(syllable.final_, "i" | "in" | "ing") => "sy",
+            "x" => "s",
+            "j" | "zh" => "j",
+            "q" | "ch" => "ch",
+            "z" => "dz",
+            "c" => "ts",
+            other => other,
+        };
+        format!("{}{}", initial_spelling, final_spelling)
+    };
+
+    if syllable.erhua {
+        result.push('r');
+    }
+    place_mark(&result, syllable.tone, None)
+}
+
+fn yale_final(final_: &str) -> &str {
+    match final_ {
+        "ê" => "e",
+        "ao" => "au",
+        "ong" => "ung",
+        "i" => "yi",
+        "ia" => "ya",
+        "io" => "yo",
+        "ie" => "ye",
+        "iao" => "yau",
+        "iou" => "you",
+        "ian" => "yan",
+        "in" => "yin",
+        "iang" => "yang",
+        "ing" => "ying",
+        "iong" => "yung",
+        "u" => "wu",
+        "ua" => "wa",
+        "uo" => "wo",
+        "uai" => "wai",
+        "uei" => "wei",
+        "uan" => "wan",
+        "uen" => "wen",
+        "uang" => "wang",
+        "ueng" => "weng",
+        "ü" => "yu",
+        "üe" => "ywe",
+        "üan" => "ywan",
+        "ün" => "yun",
+        other => other,
+    }
+}
+
+/// Gwoyeu Romatzyh finals: first tone, then second, third and fourth tone
+/// spellings after an initial, then second, third and fourth tone spellings
+/// without one. Zero-initial first tone spellings equal the basic form.
+const GR_FINALS: [(&str, [&str; 7]); 41] = [
+    ("a", ["a", "ar", "aa", "ah", "ar", "aa", "ah"]),
+    ("o", ["o", "or", "oo", "oh", "or", "oo", "oh"]),
+    ("e", ["e", "er", "ee", "eh", "er", "ee", "eh"]),
+    ("ê", ["e", "er", "ee", "eh", "er", "ee", "eh"]),
+    ("ai", ["ai", "air", "ae", "ay", "air", "ae", "ay"]),
+    ("ei", ["ei", "eir", "eei", "ey", "eir", "eei", "ey"]),
+    ("ao", ["au", "aur", "ao", "aw", "aur", "ao", "aw"]),
+    ("ou", ["ou", "our", "oou", "ow", "our", "oou", "ow"]),
+    ("an", ["an", "arn", "aan", "ann", "arn", "aan", "ann"]),
+    ("en", ["en", "ern", "een", "enn", "ern", "een", "enn"]),
+    ("ang", ["ang", "arng", "aang", "anq", "arng", "aang", "anq"]),
+    ("eng", ["eng", "erng", "eeng", "enq", "erng", "eeng", "enq"]),
+    ("ong", ["ong", "orng", "oong", "onq", "orng", "oong", "onq"]),
+    ("er", ["el", "erl", "eel", "ell", "erl", "eel", "ell"]),
+    ("i", ["i", "yi", "ii", "ih", "yi", "yii", "yih"]),
+    ("ia", ["ia", "ya", "ea", "iah", "ya", "yea", "yah"]),
+    ("io", ["io", "yo", "eo", "ioh", "yo", "yeo", "yoh"]),
+    ("ie", ["ie", "ye", "iee", "ieh", "ye", "yee", "yeh"]),
+    ("iao", ["iau", "yau", "eau", "iaw", "yau", "yeau", "yaw"]),
+    ("iou", ["iou", "you", "eou", "iow", "you", "yeou", "yow"]),
+    ("ian", ["ian", "yan", "ean", "iann", "yan", "yean", "yann"]),
+    ("in", ["in", "yn", "iin", "inn", "yn", "yiin", "yinn"]),
+    (
+        "iang",
+        ["iang", "yang", "eang", "ianq", "yang", "yeang", "yanq"],
+    ),
+    ("ing", ["ing", "yng", "iing", "inq", "yng", "yiing", "yinq"]),
+    (
+        "iong",
+        ["iong", "yong", "eong", "ionq", "yong", "yeong", "yonq"],
+    ),
+    ("u", ["u", "wu", "uu", "uh", "wu", "wuu", "wuh"]),
+    ("ua", ["ua", "wa", "oa", "uah", "wa", "woa", "wah"]),
+    ("uo", ["uo", "wo", "uoo", "uoh", "wo", "woo", "woh"]),
+    ("uai", ["uai", "wai", "oai", "uay", "wai", "woai", "way"]),
+    ("uei", ["uei", "wei", "oei", "uey", "wei", "woei", "wey"]),
+    ("uan", ["uan", "wan", "oan", "uann", "wan", "woan", "wann"]),
+    ("uen", ["uen", "wen", "oen", "uenn", "wen", "woen", "wenn"]),
+    (
+        "uang",
+        ["uang", "wang", "oang", "uanq", "wang", "woang", "wanq"],
+    ),
+    (
+        "ueng",
+        ["ueng", "weng", "oeng", "uenq", "weng", "woeng", "wenq"],
+    ),
+    ("ü", ["iu", "yu", "eu", "iuh", "yu", "yeu", "yuh"]),
+    ("üe", ["iue", "yue", "eue", "iueh", "yue", "yeue", "yueh"]),
+    (
+        "üan",
+        ["iuan", "yuan", "euan", "iuann", "yuan", "yeuan", "yuann"],
+    ),
+    ("ün", ["iun", "yun", "eun", "iunn", "yun", "yeun", "yunn"]),
+    ("m", ["m", "m", "m", "m", "m", "m", "m"]),
+    ("n", ["n", "n", "n", "n", "n", "n", "n"]),
+    ("ng", ["ng", "ng", "ng", "ng", "ng", "ng", "ng"]),
+];
+
+fn gwoyeu_romatzyh(syllable: &Syllable) -> String {
+    let initial = syllable.initial;
+    let tone = syllable.tone;
+
+    let initial_spelling = match initial {
+        "j" | "zh" => "j",
+        "q" | "ch" => "ch",
+        "x" | "sh" => "sh",
+        "z" => "tz",
+        "c" => "ts",
+        other => other,
+    };
+
+    let final_spelling = if syllable.is_apical() {
+        match tone {
+            2 => "yr",
+            3 => "yy",
+            4 => "yh",
+            _ => "y",
+        }
+    } else {
+        match GR_FINALS
+            .iter()
+            .find(|(final_, _)| *final_ == syllable.final_)
+        {
+            Some((_, forms)) => match (initial.is_empty(), tone) {
+                (false, 2..=4) => forms[tone as usize - 1],
+                (true, 2..=4) => forms[tone as usize + 2],
+                _ => forms[0],
+            },
+            None => syllable.final_,
+        }
+    };
+
+    // Sonorant initials mark the first tone with h and spell the second
+    // tone in the basic form
+    let sonorant = matches!(initial, "m" | "n" | "l" | "r");
+    let mut result = match tone {
+        1 if sonorant => format!("{}h{}", initial_spelling, final_spelling),
+        2 if sonorant => {
+            let basic = if syllable.is_apical() {
+                "y"
+            } else {
+                GR_FINALS
+                    .iter()
+                    .find(|(final_, _)| *final_ == syllable.final_)
+                    .map_or(syllable.final_, |(_, forms)| forms[0])
+            };
+            format!("{}{}", initial_spelling, basic)
+        }
+        _ => format!("{}{}", initial_spelling, final_spelling),
+    };
+
+    if syllable.erhua {
+        result.push('l');
+    }
+    result
+}
+
+fn tongyong(syllable: &Syllable) -> String {
+    let initial = syllable.initial;
+    let mut result = if syllable.is_apical() {
+        let initial_spelling = if initial == "zh" { "jh" } else { initial };
+        format!("{}ih", initial_spelling)
+    } else if initial.is_empty() {
+        match syllable.final_ {
+            "i" => "yi",
+            "ia" => "ya",
+            "io" => "yo",
+            "ie" => "ye",
+            "iao" => "yao",
+            "iou" => "you",
+            "ian" => "yan",
+            "in" => "yin",
+            "iang" => "yang",
+            "ing" => "ying",
+            "iong" => "yong",
+            "u" => "wu",
+            "ua" => "wa",
+            "uo" => "wo",
+            "uai" => "wai",
+            "uei" => "wei",
+            "uan" => "wan",
+            "uen" => "wun",
+            "uang" => "wang",
+            "ueng" => "wong",
+            "ü" => "yu",
+            "üe" => "yue",
+            "üan" => "yuan",
+            "ün" => "yun",
+            other => other,
+        }
+        .to_string()
+    } else {
+        let initial_spelling = match initial {
+            "q" => "c",
+            "x" => "s",
+            "zh" => "jh",
+            other => other,
+        };
+        let final_spelling = match syllable.final_ {
+            "eng" if matches!(initial, "b" | "p" | "m" | "f") => "ong",
+            "iong" => "yong",
+            "uen" => "un",
+            "ü" => "yu",
+            "üe" => "yue",
+            "üan" => "yuan",
+            "ün" => "yun",
+            other => other,
+        };
+        format!("{}{}", initial_spelling, final_spelling)
+    };
+
+    if syllable.erhua {
+        result.push('r');
+    }
+    match syllable.tone {
+        1 => result,
+        tone => place_mark(&result, tone, Some(RING_ABOVE)),
+    }
+}
+
+fn mps2(syllable: &Syllable) -> String {
+    let initial = syllable.initial;
+    let mut result = if syllable.is_apical() {
+        match initial {
+            "zh" => "jr",
+            "ch" => "chr",
+            "sh" => "shr",
+            "r" => "r",
+            "z" => "tz",
+            "c" => "tsz",
+            _ => "sz",
+        }
+        .to_string()
+    } else if initial.is_empty() {
+        match syllable.final_ {
+            "i" => "yi",
+            "ia" => "ya",
+            "io" => "yo",
+            "ie" => "ye",
+            "iao" => "yau",
+            "iou" => "you",
+            "ian" => "yan",
+            "in" => "yin",
+            "iang" => "yang",
+            "ing" => "ying",
+            "iong" => "yung",
+            "u" => "wu",
+            "ua" => "wa",
+            "uo" => "wo",
+            "uai" => "wai",
+            "uei" => "wei",
+            "uan" => "wan",
+            "uen" => "wen",
+            "uang" => "wang",
+            "ueng" => "weng",
+            "ü" => "yu",
+            "üe" => "yue",
+            "üan" => "yuan",
+            "ün" => "yun",
+            other => mps2_final(other),
+        }
+        .to_string()
+    } else {
+        let initial_spelling = match initial {
+            "j" | "zh" => "j",
+            "q" | "ch" => "ch",
+            "x" | "sh" => "sh",
+            "z" => "tz",
+            "c" => "ts",
+            other => other,
+        };
+        format!("{}{}", initial_spelling, mps2_final(syllable.final_))
+    };
+
+    if syllable.erhua {
+        result.push('r');
+    }
+    place_mark(&result, syllable.tone, None)
+}
+
+fn mps2_final(final_: &str) -> &str {
+    match final_ {
+        "ê" => "e",
+        "ao" => "au",
+        "ong" => "ung",
+        "iao" => "iau",
+        "iong" => "iung",
+        "ueng" => "ung",
+        "ü" => "iu",
+        "üe" => "iue",
+        "üan" => "iuan",
+        "ün" => "iun",
+        other => other,
+    }
+}
+
+/// Put a tone diacritic on the main vowel, or after the last letter of a
+/// spelling without one (Yale `jr̄`, `dz̄`). Tone 5 gets `neutral_mark`, if any.
+fn place_mark(spelling: &str, tone: u8, neutral_mark: Option<char>) -> String {
+    let mark = match tone {
+        1..=4 => COMBINING_TONES[tone as usize - 1],
+        _ => match neutral_mark {
+            Some(mark) => mark,
+            None => return spelling.to_string(),
+        },
+    };
+
+    let chars: Vec<char> = spelling.chars().collect();
+    let Some(target) = tone_vowel(&chars).or(chars.len().checked_sub(1)) else {
+        return spelling.to_string();
+    };
+
+    let mut result = String::with_capacity(spelling.len() + 2);
+    for (i, &c) in chars.iter().enumerate() {
+        if i != target {
+            result.push(c);
+            continue;
+        }
+        let precomposed = TONE_MARKS
+            .iter()
+            .find(|(plain, _)| *plain == c)
+            .filter(|_| (1..=4).contains(&tone))
+            .map(|(_, marked)| marked[tone as usize - 1]);
+        match precomposed {
+            Some(marked) => result.push(marked),
+            None => {
+                result.push(c);
+                result.push(mark);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pinyin, then Wade-Giles, Yale, Gwoyeu Romatzyh, Tongyong and MPS II
+    const SYLLABLES: [[&str; 6]; 25] = [
+        ["zhōng", "chung¹", "jūng", "jong", "jhong", "jūng"],
+        ["guó", "kuo²", "gwó", "gwo", "guó", "guó"],
+        ["guì", "kuei⁴", "gwèi", "guey", "guèi", "guèi"],
+        ["kuī", "k'uei¹", "kwēi", "kuei", "kuei", "kuēi"],
+        ["huì", "hui⁴", "hwèi", "huey", "huèi", "huèi"],
+        ["dùn", "tun⁴", "dwùn", "duenn", "dùn", "duèn"],
+        ["wén", "wên²", "wén", "wen", "wún", "wén"],
+        ["nǚ", "nü³", "nyǔ", "neu", "nyǔ", "niǔ"],
+        ["xué", "hsüeh²", "sywé", "shyue", "syué", "shiué"],
+        ["rì", "jih⁴", "r̀", "ryh", "rìh", "r̀"],
+        ["sī", "ssŭ¹", "sz̄", "sy", "sih", "sz̄"],
+        ["cí", "tz'ŭ²", "tsz\u{301}", "tsyr", "cíh", "tsz\u{301}"],
+        ["yī", "i¹", "yī", "i", "yi", "yī"],
+        ["ér", "êrh²", "ér", "erl", "ér", "ér"],
+        ["jiǔ", "chiu³", "jyǒu", "jeou", "jiǒu", "jiǒu"],
+        ["qù", "ch'ü⁴", "chyù", "chiuh", "cyù", "chiù"],
+        ["xiǎng", "hsiang³", "syǎng", "sheang", "siǎng", "shiǎng"],
+        ["lǜ", "lü⁴", "lyù", "liuh", "lyù", "liù"],
+        ["yǒu", "yu³", "yǒu", "yeou", "yǒu", "yǒu"],
+        ["bō", "po¹", "bwō", "bo", "bo", "bō"],
+        ["shuō", "shuo¹", "shwō", "shuo", "shuo", "shuō"],
+        ["chūn", "ch'un¹", "chwūn", "chuen", "chun", "chuēn"],
+        ["yuǎn", "yüan³", "ywǎn", "yeuan", "yuǎn", "yuǎn"],
+        ["zǒu", "tsou³", "dzǒu", "tzoou", "zǒu", "tzǒu"],
+        ["huār", "huarh¹", "hwār", "hual", "huar", "huār"],
+    ];
+
+    const SYSTEMS: [Romanization; 5] = [
+        Romanization::WadeGiles,
+        Romanization::Yale,
+        Romanization::GwoyeuRomatzyh,
+        Romanization::Tongyong,
+        Romanization::Mps2,
+    ];
+
+    #[test]
+    fn converts_each_syllable() {
+        for [pinyin, expected @ ..] in SYLLABLES {
+            for (system, expected) in SYSTEMS.into_iter().zip(expected) {
+                assert_eq!(
+                    convert(pinyin, system),
+                    expected,
+                    "{} in {:?}",
+                    pinyin,
+                    system
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn accepts_numbered_pinyin_and_keeps_case() {
+        assert_eq!(
+            convert("Bei3 jing1", Romanization::WadeGiles),
+            "Pei³ ching¹"
+        );
+        assert_eq!(convert("Zhong1 guo2", Romanization::Yale), "Jūng gwó");
+        assert_eq!(
+            convert("zhong1 guo2", Romanization::GwoyeuRomatzyh),
+            "jong gwo"
+        );
+    }
+
+    #[test]
+    fn passes_other_tokens_through() {
+        assert_eq!(convert("kǎ lā OK", Romanization::WadeGiles), "k'a³ la¹ OK");
+        assert_eq!(convert("，", Romanization::Yale), "，");
+    }
+}
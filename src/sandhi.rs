@@ -0,0 +1,277 @@
+//! Mandarin tone sandhi.
+//!
+//! Rewrites citation tones into the tones actually spoken:
+//!
+//! - 不 (bù) becomes bú before a fourth tone
+//! - 一 (yī) becomes yí before a fourth or neutral tone and yì before the
+//!   other tones, except as an ordinal (第一), inside numbers (十一, 一一)
+//!   and at the end of a word (统一) or phrase
+//! - in a run of third tones every syllable but the last becomes a second
+//!   tone. A single-syllable word keeps its third tone when the word after it
+//!   has more than one syllable and has itself changed (小 老虎 → xiǎo láohǔ)
+//!
+//! Runs are broken by anything without a reading, such as punctuation and
+//! Latin text, and by words whose reading does not have one syllable per
+//! character.
+
+use crate::pinyin::Syllable;
+
+/// Characters that make a neighboring 一 part of a number
+const NUMERALS: &str = "零〇一二三四五六七八九十百千万萬亿億两兩";
+
+struct Unit {
+    ch: char,
+    syllable: Syllable,
+    word: usize,
+    /// Number of syllables in the unit's word
+    word_len: usize,
+    /// Position of the unit within its word
+    offset: usize,
+}
+
+/// Spoken readings for a sequence of words, given each word's text and
+/// citation pinyin (space-separated syllables). Returns `None` for words
+/// without pinyin; other words get their reading with rewritten tones.
+pub fn spoken_readings(words: &[(&str, Option<&str>)]) -> Vec<Option<String>> {
+    // Units in text order, with `None` wherever a run must break
+    let mut units: Vec<Option<Unit>> = Vec::new();
+
+    for (word, (text, pinyin)) in words.iter().enumerate() {
+        let Some(pinyin) = pinyin else {
+            units.push(None);
+            continue;
+        };
+
+        let chars: Vec<char> = text.chars().collect();
+        let syllables: Option<Vec<Syllable>> =
+            pinyin.split_whitespace().map(Syllable::parse).collect();
+
+        match syllables {
+            Some(syllables) if syllables.len() == chars.len() => {
+                let word_len = syllables.len();
+                for (offset, (ch, syllable)) in chars.into_iter().zip(syllables).enumerate() {
+                    units.push(Some(Unit {
+                        ch,
+                        syllable,
+                        word,
+                        word_len,
+                        offset,
+                    }));
+                }
+            }
+            _ => units.push(None),
+        }
+    }
+
+    let citation: Vec<Option<u8>> = units
+        .iter()
+        .map(|unit| unit.as_ref().map(|u| u.syllable.tone))
+        .collect();
+    let mut spoken = citation.clone();
+
+    apply_yi_bu(&units, &citation, &mut spoken);
+    apply_third_tone(&units, &mut spoken);
+
+    // Rebuild each word's reading, keeping unchanged syllables as written
+    let mut readings: Vec<Option<String>> = words
+        .iter()
+        .map(|(_, pinyin)| pinyin.map(str::to_string))
+        .collect();
+
+    let mut i = 0;
+    while i < units.len() {
+        let Some(unit) = &units[i] else {
+            i += 1;
+            continue;
+        };
+
+        let (word, word_len) = (unit.word, unit.word_len);
+        if let Some(pinyin) = words[word].1 {
+            let syllables: Vec<String> = pinyin
+                .split_whitespace()
+                .enumerate()
+                .map(
+                    |(offset, token)| match (&units[i + offset], spoken[i + offset]) {
+                        (Some(unit), Some(tone)) if tone != unit.syllable.tone => Syllable {
+                            tone,
+                            ..unit.syllable.clone()
+                        }
+                        .marked(),
+                        _ => token.to_string(),
+                    },
+                )
+                .collect();
+            readings[word] = Some(syllables.join(" "));
+        }
+        i += word_len;
+    }
+
+    readings
+}
+
+fn apply_yi_bu(units: &[Option<Unit>], citation: &[Option<u8>], spoken: &mut [Option<u8>]) {
+    let is_numeral = |unit: Option<&Option<Unit>>| match unit {
+        Some(Some(unit)) => NUMERALS.contains(unit.ch),
+        _ => false,
+    };
+
+    for i in 0..units.len() {
+        let Some(unit) = &units[i] else {
+            continue;
+        };
+        let Some(next_tone) = citation.get(i + 1).copied().flatten() else {
+            continue;
+        };
+
+        match (unit.ch, unit.syllable.tone) {
+            ('不', 4) if next_tone == 4 => spoken[i] = Some(2),
+            // 一 closing a longer word keeps its tone whatever follows
+            ('一', 1) if unit.word_len > 1 && unit.offset + 1 == unit.word_len => {}
+            ('一', 1) => {
+                let previous = i.checked_sub(1).map(|p| &units[p]);
+                let ordinal = matches!(previous, Some(Some(p)) if p.ch == '第');
+                if ordinal || is_numeral(previous) || is_numeral(units.get(i + 1)) {
+                    continue;
+                }
+                // A neutral tone after 一 is usually an underlying fourth
+                // tone (一个 yí ge)
+                spoken[i] = Some(if matches!(next_tone, 4 | 5) { 2 } else { 4 });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn apply_third_tone(units: &[Option<Unit>], spoken: &mut [Option<u8>]) {
+    let mut end = units.len();
+
+    while end > 0 {
+        if spoken[end - 1] != Some(3) {
+            end -= 1;
+            continue;
+        }
+
+        let mut start = end - 1;
+        while start > 0 && spoken[start - 1] == Some(3) {
+            start -= 1;
+        }
+
+        // Right to left, so each syllable knows whether the next one changed
+        for k in (start..end - 1).rev() {
+            let (Some(unit), Some(next)) = (&units[k], &units[k + 1]) else {
+                continue;
+            };
+            let monosyllable_before_changed_word = unit.word_len == 1
+                && next.offset == 0
+                && next.word_len > 1
+                && spoken[k + 1] == Some(2);
+            if !monosyllable_before_changed_word {
+                spoken[k] = Some(2);
+            }
+        }
+
+        end = start;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spoken readings of `words`, each `(text, pinyin)` with an empty
+    /// pinyin for words without a reading
+    fn spoken(words: &[(&str, &str)]) -> Vec<String> {
+        let words: Vec<(&str, Option<&str>)> = words
+            .iter()
+            .map(|&(text, pinyin)| (text, (!pinyin.is_empty()).then_some(pinyin)))
+            .collect();
+        spoken_readings(&words)
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect()
+    }
+
+    #[test]
+    fn yi_rises_before_a_fourth_or_neutral_tone() {
+        assert_eq!(spoken(&[("一样", "yī yàng")]), ["yí yàng"]);
+        assert_eq!(spoken(&[("一", "yī"), ("个", "ge")]), ["yí", "ge"]);
+        assert_eq!(spoken(&[("一定", "yī dìng")]), ["yí dìng"]);
+    }
+
+    #[test]
+    fn yi_falls_before_other_tones_except_in_numbers() {
+        assert_eq!(spoken(&[("一天", "yī tiān")]), ["yì tiān"]);
+        assert_eq!(spoken(&[("一年", "yī nián")]), ["yì nián"]);
+        assert_eq!(spoken(&[("一起", "yī qǐ")]), ["yì qǐ"]);
+
+        assert_eq!(
+            spoken(&[("第一", "dì yī"), ("天", "tiān")]),
+            ["dì yī", "tiān"]
+        );
+        assert_eq!(
+            spoken(&[("十一", "shí yī"), ("年", "nián")]),
+            ["shí yī", "nián"]
+        );
+        assert_eq!(spoken(&[("一一", "yī yī")]), ["yī yī"]);
+        assert_eq!(spoken(&[("统一", "tǒng yī")]), ["tǒng yī"]);
+        assert_eq!(
+            spoken(&[("统一", "tǒng yī"), ("思想", "sī xiǎng")]),
+            ["tǒng yī", "sī xiǎng"]
+        );
+        assert_eq!(
+            spoken(&[("唯一", "wéi yī"), ("的", "de")]),
+            ["wéi yī", "de"]
+        );
+        assert_eq!(spoken(&[("一", "yī"), ("，", "")]), ["yī", ""]);
+    }
+
+    #[test]
+    fn bu_rises_only_before_a_fourth_tone() {
+        assert_eq!(spoken(&[("不", "bù"), ("是", "shì")]), ["bú", "shì"]);
+        assert_eq!(spoken(&[("不要", "bù yào")]), ["bú yào"]);
+        assert_eq!(spoken(&[("不", "bù"), ("好", "hǎo")]), ["bù", "hǎo"]);
+        assert_eq!(spoken(&[("不", "bù"), ("来", "lái")]), ["bù", "lái"]);
+    }
+
+    #[test]
+    fn two_third_tones_make_the_first_rise() {
+        assert_eq!(spoken(&[("你好", "nǐ hǎo")]), ["ní hǎo"]);
+        assert_eq!(spoken(&[("你", "ni3"), ("好", "hao3")]), ["ní", "hao3"]);
+        assert_eq!(
+            spoken(&[("你", "nǐ"), ("，", ""), ("好", "hǎo")]),
+            ["nǐ", "", "hǎo"]
+        );
+    }
+
+    #[test]
+    fn longer_third_tone_runs_follow_word_boundaries() {
+        assert_eq!(
+            spoken(&[("小", "xiǎo"), ("老虎", "lǎo hǔ")]),
+            ["xiǎo", "láo hǔ"]
+        );
+        assert_eq!(
+            spoken(&[
+                ("我", "wǒ"),
+                ("想", "xiǎng"),
+                ("买", "mǎi"),
+                ("小", "xiǎo"),
+                ("老虎", "lǎo hǔ")
+            ]),
+            ["wó", "xiáng", "mái", "xiǎo", "láo hǔ"]
+        );
+        assert_eq!(spoken(&[("展览馆", "zhǎn lǎn guǎn")]), ["zhán lán guǎn"]);
+        assert_eq!(
+            spoken(&[("我", "wǒ"), ("也", "yě"), ("很", "hěn"), ("好", "hǎo")]),
+            ["wó", "yé", "hén", "hǎo"]
+        );
+    }
+
+    #[test]
+    fn words_without_a_syllable_per_character_break_runs() {
+        assert_eq!(
+            spoken(&[("你", "nǐ"), ("点儿", "diǎnr"), ("好", "hǎo")]),
+            ["nǐ", "diǎnr", "hǎo"]
+        );
+        assert_eq!(spoken(&[("OK", "OK"), ("不", "bù")]), ["OK", "bù"]);
+    }
+}
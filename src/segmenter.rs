@@ -0,0 +1,361 @@
+//! Word segmentation strategies.
+//!
+//! A [`Segmenter`] splits text into spans using only the dictionary's word
+//! tables; the annotator then looks up readings for each span. Spans are
+//! `(start, length)` pairs in characters that cover the input in order,
+//! with characters outside any dictionary word as single-character spans.
+
+use crate::dictionary::Dictionary;
+use serde::{Deserialize, Serialize};
+
+/// `(start, length)` of a segment, in characters
+pub type Span = (usize, usize);
+
+/// Splits text into words
+pub trait Segmenter: Send + Sync {
+    /// Segment `chars` against the simplified or traditional word table
+    fn segment(&self, dictionary: &Dictionary, chars: &[char], use_traditional: bool) -> Vec<Span>;
+}
+
+/// Built-in segmentation strategies, selectable from the configuration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SegmenterKind {
+    /// Greedy forward maximum matching (longest dictionary word first)
+    #[default]
+    ForwardMaximum,
+    /// Greedy maximum matching from the end of the text
+    BackwardMaximum,
+    /// Forward and backward matching, keeping the better result
+    Bidirectional,
+    /// Most probable path through all dictionary words, by word frequency
+    MaxProbability,
+}
+
+impl SegmenterKind {
+    /// Create the segmenter for this strategy
+    pub fn build(self) -> Box<dyn Segmenter> {
+        match self {
+            SegmenterKind::ForwardMaximum => Box::new(ForwardMaximum),
+            SegmenterKind::BackwardMaximum => Box::new(BackwardMaximum),
+            SegmenterKind::Bidirectional => Box::new(Bidirectional),
+            SegmenterKind::MaxProbability => Box::new(MaxProbability),
+        }
+    }
+}
+
+/// Forward maximum matching: always take the longest dictionary word
+/// starting at the current position
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ForwardMaximum;
+
+impl Segmenter for ForwardMaximum {
+    fn segment(&self, dictionary: &Dictionary, chars: &[char], use_traditional: bool) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let len = dictionary
+                .longest_match_len(chars, i, use_traditional)
+                .unwrap_or(1);
+            spans.push((i, len));
+            i += len;
+        }
+
+        spans
+    }
+}
+
+/// Longest word backward matching tries when the dictionary does not record
+/// the length of its longest word
+const FALLBACK_MAX_WORD_LENGTH: usize = 16;
+
+/// Backward maximum matching: always take the longest dictionary word
+/// ending at the current position, working from the end of the text
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackwardMaximum;
+
+impl Segmenter for BackwardMaximum {
+    fn segment(&self, dictionary: &Dictionary, chars: &[char], use_traditional: bool) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut end = chars.len();
+
+        while end > 0 {
+            let max_len = match dictionary.max_word_length() {
+                0 => FALLBACK_MAX_WORD_LENGTH.min(end),
+                max => max.min(end),
+            };
+
+            // The word tables have no suffix index, so try each length
+            let len = (2..=max_len)
+                .rev()
+                .find(|&len| {
+                    let word: String = chars[end - len..end].iter().collect();
+                    dictionary.contains_word(&word, use_traditional)
+                })
+                .unwrap_or(1);
+
+            spans.push((end - len, len));
+            end -= len;
+        }
+
+        spans.reverse();
+        spans
+    }
+}
+
+/// Bidirectional maximum matching: run forward and backward matching and
+/// keep the result with fewer words, then the one with fewer single-character
+/// words. Remaining ties go to backward matching, which resolves overlapping
+/// ambiguities correctly more often in Chinese text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bidirectional;
+
+impl Segmenter for Bidirectional {
+    fn segment(&self, dictionary: &Dictionary, chars: &[char], use_traditional: bool) -> Vec<Span> {
+        let forward = ForwardMaximum.segment(dictionary, chars, use_traditional);
+        let backward = BackwardMaximum.segment(dictionary, chars, use_traditional);
+
+        let score = |spans: &[Span]| {
+            let singles = spans.iter().filter(|&&(_, len)| len == 1).count();
+            (spans.len(), singles)
+        };
+
+        if score(&forward) < score(&backward) {
+            forward
+        } else {
+            backward
+        }
+    }
+}
+
+/// Extra cost, in nats, of leaving a character out of every dictionary word
+const UNKNOWN_CHAR_PENALTY: f64 = 2.0;
+
+/// Maximum-probability segmentation: dynamic programming over every
+/// dictionary word at every position, minimizing the summed cost
+/// `-ln(frequency / total)` of the words on the path. Without frequency
+/// data every word costs the same, so the path with the fewest words wins.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxProbability;
+
+impl Segmenter for MaxProbability {
+    fn segment(&self, dictionary: &Dictionary, chars: &[char], use_traditional: bool) -> Vec<Span> {
+        let n = chars.len();
+
+        // Add-one smoothing over all entries so words missing from the
+        // frequency list still get a finite cost
+        let total = dictionary.total_frequency() + dictionary.entry_count() as u64;
+        let log_total = (total.max(2) as f64).ln();
+        // A character outside the dictionary costs more than any word
+        let unknown_cost = log_total + UNKNOWN_CHAR_PENALTY;
+
+        // best[i] = (cost of the best path from i to the end, first word length)
+        let mut best = vec![(0.0f64, 0usize); n + 1];
+        for i in (0..n).rev() {
+            let mut choice = (unknown_cost + best[i + 1].0, 1);
+            for (len, frequency) in dictionary.prefix_matches(chars, i, use_traditional) {
+                let cost = log_total - (frequency as f64 + 1.0).ln() + best[i + len].0;
+                if cost < choice.0 {
+                    choice = (cost, len);
+                }
+            }
+            best[i] = choice;
+        }
+
+        let mut spans = Vec::new();
+        let mut i = 0;
+        while i < n {
+            let len = best[i].1;
+            spans.push((i, len));
+            i += len;
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::fixture::{self, entry};
+    use crate::dictionary::AnnotationData;
+    use crate::import::frequency;
+
+    fn words(dictionary: &Dictionary, segmenter: SegmenterKind, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        segmenter
+            .build()
+            .segment(dictionary, &chars, false)
+            .into_iter()
+            .map(|(start, len)| chars[start..start + len].iter().collect())
+            .collect()
+    }
+
+    fn research_dictionary() -> Dictionary {
+        fixture::dictionary(&[
+            entry("研究", "研究", "yán jiū"),
+            entry("研究生", "研究生", "yán jiū shēng"),
+            entry("生命", "生命", "shēng mìng"),
+            entry("起源", "起源", "qǐ yuán"),
+        ])
+    }
+
+    #[test]
+    fn forward_and_backward_matching_take_the_longest_word() {
+        let dictionary = research_dictionary();
+        assert_eq!(
+            words(&dictionary, SegmenterKind::ForwardMaximum, "研究生命起源"),
+            ["研究生", "命", "起源"]
+        );
+        assert_eq!(
+            words(&dictionary, SegmenterKind::BackwardMaximum, "研究生命起源"),
+            ["研究", "生命", "起源"]
+        );
+        assert_eq!(
+            words(&dictionary, SegmenterKind::ForwardMaximum, "好研究"),
+            ["好", "研究"]
+        );
+        assert!(words(&dictionary, SegmenterKind::BackwardMaximum, "").is_empty());
+    }
+
+    #[test]
+    fn bidirectional_prefers_fewer_single_characters_then_backward() {
+        // Same word count; backward leaves no single characters
+        let dictionary = research_dictionary();
+        assert_eq!(
+            words(&dictionary, SegmenterKind::Bidirectional, "研究生命起源"),
+            ["研究", "生命", "起源"]
+        );
+
+        // Without 研究, backward strands two single characters
+        let dictionary = fixture::dictionary(&[
+            entry("研究生", "研究生", "yán jiū shēng"),
+            entry("生命", "生命", "shēng mìng"),
+        ]);
+        assert_eq!(
+            words(&dictionary, SegmenterKind::BackwardMaximum, "研究生命"),
+            ["研", "究", "生命"]
+        );
+        assert_eq!(
+            words(&dictionary, SegmenterKind::Bidirectional, "研究生命"),
+            ["研究生", "命"]
+        );
+
+        // A true tie goes to backward matching
+        let dictionary = fixture::dictionary(&[
+            entry("和平", "和平", "hé píng"),
+            entry("平等", "平等", "píng děng"),
+        ]);
+        assert_eq!(
+            words(&dictionary, SegmenterKind::Bidirectional, "和平等"),
+            ["和", "平等"]
+        );
+    }
+
+    #[test]
+    fn backward_matching_respects_the_longest_word() {
+        let mut data = fixture::data(&[
+            entry("研究", "研究", "yán jiū"),
+            entry("研究生", "研究生", "yán jiū shēng"),
+        ]);
+        data.stats.max_word_length = 2;
+        let dictionary = Dictionary::from_data(data);
+        assert_eq!(
+            words(&dictionary, SegmenterKind::BackwardMaximum, "研究生"),
+            ["研究", "生"]
+        );
+        assert_eq!(
+            words(&dictionary, SegmenterKind::BackwardMaximum, "好研究"),
+            ["好", "研究"]
+        );
+    }
+
+    #[test]
+    fn backward_matching_caps_the_word_length_without_a_maximum() {
+        let long_word = "一二三四五六七八九十一二三四五六七";
+        let mut data = fixture::data(&[
+            entry("研究生", "研究生", "yán jiū shēng"),
+            entry(long_word, long_word, ""),
+        ]);
+        data.stats.max_word_length = 0;
+        let dictionary = Dictionary::from_data(data);
+
+        assert_eq!(
+            words(&dictionary, SegmenterKind::BackwardMaximum, "读研究生"),
+            ["读", "研究生"]
+        );
+        // 17 characters is past the fallback limit of 16
+        assert_eq!(
+            words(&dictionary, SegmenterKind::BackwardMaximum, long_word).len(),
+            17
+        );
+    }
+
+    /// The words of `research_dictionary` and `extra`, with `counts` applied
+    fn frequency_dictionary(counts: &[(&str, u64)], extra: &[AnnotationData]) -> Dictionary {
+        let mut entries = vec![
+            entry("研究", "研究", "yán jiū"),
+            entry("研究生", "研究生", "yán jiū shēng"),
+            entry("生命", "生命", "shēng mìng"),
+            entry("起源", "起源", "qǐ yuán"),
+        ];
+        entries.extend_from_slice(extra);
+        let mut data = fixture::data(&entries);
+        let counts = counts
+            .iter()
+            .map(|&(word, count)| (word.to_string(), count))
+            .collect();
+        frequency::apply(&mut data, &counts);
+        Dictionary::from_data(data)
+    }
+
+    #[test]
+    fn max_probability_follows_word_frequencies() {
+        let dictionary = frequency_dictionary(
+            &[
+                ("研究", 5000),
+                ("研究生", 200),
+                ("生命", 3000),
+                ("起源", 800),
+            ],
+            &[],
+        );
+        assert_eq!(
+            words(&dictionary, SegmenterKind::MaxProbability, "研究生命起源"),
+            ["研究", "生命", "起源"]
+        );
+        assert_eq!(
+            words(&dictionary, SegmenterKind::MaxProbability, "我的研究生"),
+            ["我", "的", "研究生"]
+        );
+
+        // Make 研究生 and 命 common and 生命 rare, and the other path wins
+        let dictionary = frequency_dictionary(
+            &[
+                ("研究", 50),
+                ("研究生", 9000),
+                ("生命", 1),
+                ("命", 9000),
+                ("起源", 800),
+            ],
+            &[entry("命", "命", "mìng")],
+        );
+        assert_eq!(
+            words(&dictionary, SegmenterKind::MaxProbability, "研究生命起源"),
+            ["研究生", "命", "起源"]
+        );
+    }
+
+    #[test]
+    fn max_probability_without_frequencies_takes_the_fewest_words() {
+        let dictionary = fixture::dictionary(&[
+            entry("北京", "北京", "Běi jīng"),
+            entry("北京大学", "北京大學", "Běi jīng Dà xué"),
+            entry("大学", "大學", "dà xué"),
+            entry("大学生", "大學生", "dà xué shēng"),
+        ]);
+        assert_eq!(
+            words(&dictionary, SegmenterKind::MaxProbability, "北京大学生"),
+            ["北京", "大学生"]
+        );
+    }
+}
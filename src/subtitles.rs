@@ -0,0 +1,459 @@
+//! Subtitle files (SRT, WebVTT, ASS) in and out.
+//!
+//! Only dialogue text is annotated: cue numbers, timings, cue settings,
+//! headers, styles and inline markup (`<i>`, `<v Speaker>`, ASS override
+//! blocks and `\N` breaks) are copied through unchanged. Readings are added
+//! in one of three ways:
+//!
+//! - [`SubtitleMode::Line`]: a reading line under each line of cue text (any
+//!   format; a `\N` line with smaller text in ASS)
+//! - [`SubtitleMode::Ruby`]: ruby markup in the cue text (WebVTT)
+//! - [`SubtitleMode::Track`]: a second event per dialogue line in a
+//!   `Reading` style at the top of the screen (ASS)
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A subtitle file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+    /// Advanced SubStation Alpha, including SSA
+    Ass,
+}
+
+impl SubtitleFormat {
+    /// Format for a file name with a subtitle extension (`.srt`, `.vtt`,
+    /// `.ass`, `.ssa`)
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        Self::from_name(&extension)
+    }
+
+    /// Format for a name as given on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" | "webvtt" => Some(SubtitleFormat::WebVtt),
+            "ass" | "ssa" => Some(SubtitleFormat::Ass),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "SRT",
+            SubtitleFormat::WebVtt => "WebVTT",
+            SubtitleFormat::Ass => "ASS",
+        }
+    }
+}
+
+/// Where readings go in annotated subtitles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubtitleMode {
+    /// A reading line under the cue text
+    #[default]
+    Line,
+    /// Ruby markup in the cue text (WebVTT only)
+    Ruby,
+    /// A separate event in its own style (ASS only)
+    Track,
+}
+
+/// Readings for a piece of dialogue, provided by the annotator
+#[derive(Debug, Clone, Default)]
+pub struct Annotation {
+    /// Reading lines: the annotation, plus a zhuyin line for the `both` style
+    pub lines: Vec<String>,
+    /// The text with ruby markup, for WebVTT
+    pub ruby: String,
+}
+
+/// Name of the ASS style added for reading events
+const READING_STYLE: &str = "Reading";
+
+/// Annotate the dialogue of a subtitle file, calling `annotate` for each
+/// piece of text, and write the file back in the same format
+pub fn annotate<F>(
+    input: &str,
+    format: SubtitleFormat,
+    mode: SubtitleMode,
+    annotate: F,
+) -> Result<String>
+where
+    F: FnMut(&str) -> Result<Annotation>,
+{
+    match (format, mode) {
+        (SubtitleFormat::Srt, SubtitleMode::Ruby | SubtitleMode::Track)
+        | (SubtitleFormat::WebVtt, SubtitleMode::Track)
+        | (SubtitleFormat::Ass, SubtitleMode::Ruby) => bail!(
+            "{} subtitles do not support the {:?} mode",
+            format.name(),
+            mode
+        ),
+        _ => {}
+    }
+
+    let newline = if input.contains("\r\n") { "\r\n" } else { "\n" };
+    let (bom, body) = match input.strip_prefix('\u{feff}') {
+        Some(body) => ("\u{feff}", body),
+        None => ("", input),
+    };
+    let lines: Vec<&str> = body.lines().collect();
+
+    let mut output = match format {
+        SubtitleFormat::Srt | SubtitleFormat::WebVtt => {
+            annotate_cues(&lines, format, mode, annotate)?
+        }
+        SubtitleFormat::Ass => annotate_ass(&lines, mode, annotate)?,
+    }
+    .join(newline);
+    if body.ends_with('\n') {
+        output.push_str(newline);
+    }
+
+    Ok(format!("{}{}", bom, output))
+}
+
+/// SRT and WebVTT: blocks separated by blank lines, each cue a timing line
+/// (`00:00:01,000 --> 00:00:02,000`) followed by its text lines
+fn annotate_cues<F>(
+    lines: &[&str],
+    format: SubtitleFormat,
+    mode: SubtitleMode,
+    mut annotate: F,
+) -> Result<Vec<String>>
+where
+    F: FnMut(&str) -> Result<Annotation>,
+{
+    let mut output = Vec::with_capacity(lines.len() * 2);
+    let mut block_start = 0;
+
+    while block_start < lines.len() {
+        let block_end = lines[block_start..]
+            .iter()
+            .position(|line| line.trim().is_empty())
+            .map_or(lines.len(), |len| block_start + len);
+        let block = &lines[block_start..block_end];
+
+        // The WebVTT header, comments, style and region blocks have no
+        // timing line and are copied as they are
+        let timing = block.iter().position(|line| line.contains("-->"));
+        let is_cue = timing.is_some()
+            && !(format == SubtitleFormat::WebVtt
+                && ["WEBVTT", "NOTE", "STYLE", "REGION"]
+                    .iter()
+                    .any(|keyword| block[0].starts_with(keyword)));
+
+        match timing.filter(|_| is_cue) {
+            Some(timing) => {
+                output.extend(block[..=timing].iter().map(|line| line.to_string()));
+                let text = &block[timing + 1..];
+                match mode {
+                    SubtitleMode::Ruby => {
+                        for line in text {
+                            output.push(ruby_line(line, format, &mut annotate)?);
+                        }
+                    }
+                    _ => {
+                        // Each text line is followed by its readings, so
+                        // the lines of two speakers stay apart
+                        for line in text {
+                            output.push(line.to_string());
+                            let annotation = annotate(&plain_text(line, format))?;
+                            for reading in annotation.lines.iter().filter(|r| !r.is_empty()) {
+                                output.push(match format {
+                                    SubtitleFormat::WebVtt => {
+                                        format!("<c.reading>{}</c>", reading)
+                                    }
+                                    _ => reading.clone(),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            None => output.extend(block.iter().map(|line| line.to_string())),
+        }
+
+        // Keep the blank lines between blocks
+        let next = lines[block_end..]
+            .iter()
+            .position(|line| !line.trim().is_empty())
+            .map_or(lines.len(), |len| block_end + len);
+        output.extend(lines[block_end..next].iter().map(|line| line.to_string()));
+        block_start = next;
+    }
+
+    Ok(output)
+}
+
+/// A cue line with ruby markup around its words, keeping its tags
+fn ruby_line<F>(line: &str, format: SubtitleFormat, annotate: &mut F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<Annotation>,
+{
+    let mut result = String::new();
+    for chunk in split_markup(line, format) {
+        match chunk {
+            Chunk::Markup(markup) => result.push_str(markup),
+            Chunk::Text(text) => result.push_str(&annotate(text)?.ruby),
+        }
+    }
+    Ok(result)
+}
+
+/// Advanced SubStation Alpha: `Dialogue:` lines in the `[Events]` section,
+/// whose last field is the text
+fn annotate_ass<F>(lines: &[&str], mode: SubtitleMode, mut annotate: F) -> Result<Vec<String>>
+where
+    F: FnMut(&str) -> Result<Annotation>,
+{
+    let mut output = Vec::with_capacity(lines.len() * 2);
+    let mut section = String::new();
+    let mut style_format: Vec<String> = Vec::new();
+    let mut event_format: Vec<String> = Vec::new();
+    let mut reading_style_added = false;
+
+    for &line in lines {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            section = trimmed.to_lowercase();
+            output.push(line.to_string());
+            continue;
+        }
+
+        if let Some(fields) = trimmed.strip_prefix("Format:") {
+            let fields = fields.split(',').map(|f| f.trim().to_lowercase()).collect();
+            if section.contains("styles") {
+                style_format = fields;
+            } else if section == "[events]" {
+                event_format = fields;
+            }
+            output.push(line.to_string());
+            continue;
+        }
+
+        // Add the reading style after the first style, based on it
+        if let Some(style) = trimmed.strip_prefix("Style:") {
+            output.push(line.to_string());
+            if mode == SubtitleMode::Track && !reading_style_added {
+                output.push(reading_style(style, &style_format));
+                reading_style_added = true;
+            }
+            continue;
+        }
+
+        let Some(event) = trimmed.strip_prefix("Dialogue:") else {
+            output.push(line.to_string());
+            continue;
+        };
+
+        // The text is the last field and may itself contain commas
+        let field_count = event_format.len().max(10);
+        let fields: Vec<&str> = event.splitn(field_count, ',').collect();
+        if fields.len() < field_count {
+            output.push(line.to_string());
+            continue;
+        }
+        let text = fields[field_count - 1];
+        let annotation = annotate(&plain_text(text, SubtitleFormat::Ass))?;
+        let readings: Vec<&String> = annotation.lines.iter().filter(|r| !r.is_empty()).collect();
+        if readings.is_empty() {
+            output.push(line.to_string());
+            continue;
+        }
+
+        match mode {
+            SubtitleMode::Track => {
+                output.push(line.to_string());
+                let style = event_format.iter().position(|f| f == "style").unwrap_or(3);
+                let mut reading_fields: Vec<String> =
+                    fields.iter().map(|f| f.to_string()).collect();
+                reading_fields[style] = READING_STYLE.to_string();
+                reading_fields[field_count - 1] = readings
+                    .iter()
+                    .map(|r| r.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\\N");
+                output.push(format!("Dialogue:{}", reading_fields.join(",")));
+            }
+            _ => {
+                let mut annotated = line.to_string();
+                for reading in readings {
+                    annotated.push_str("\\N{\\fscx75\\fscy75}");
+                    annotated.push_str(reading);
+                }
+                output.push(annotated);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// A copy of an ASS style named `Reading`, at two thirds of the size and
+/// aligned to the top center
+fn reading_style(style: &str, format: &[String]) -> String {
+    let mut values: Vec<String> = style.split(',').map(|v| v.trim().to_string()).collect();
+    for (i, field) in format.iter().enumerate() {
+        let Some(value) = values.get_mut(i) else {
+            break;
+        };
+        match field.as_str() {
+            "name" => *value = READING_STYLE.to_string(),
+            "fontsize" => {
+                if let Ok(size) = value.parse::<f32>() {
+                    *value = format!("{}", (size * 2.0 / 3.0).round());
+                }
+            }
+            "alignment" => *value = "8".to_string(),
+            _ => {}
+        }
+    }
+    format!("Style: {}", values.join(","))
+}
+
+/// A piece of a subtitle line: inline markup, or text to annotate
+enum Chunk<'a> {
+    Markup(&'a str),
+    Text(&'a str),
+}
+
+/// Split a line into markup and text: `<...>` tags and `{\...}` overrides
+/// in SRT and WebVTT; `{...}` override blocks and `\N`, `\n`, `\h` in ASS
+fn split_markup(line: &str, format: SubtitleFormat) -> Vec<Chunk<'_>> {
+    let mut chunks = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < line.len() {
+        let rest = &line[i..];
+        let markup_len = match format {
+            SubtitleFormat::Ass if rest.starts_with('{') => rest.find('}').map(|end| end + 1),
+            SubtitleFormat::Ass if ["\\N", "\\n", "\\h"].iter().any(|b| rest.starts_with(b)) => {
+                Some(2)
+            }
+            SubtitleFormat::Ass => None,
+            _ if rest.starts_with('<') => rest.find('>').map(|end| end + 1),
+            _ if rest.starts_with("{\\") => rest.find('}').map(|end| end + 1),
+            _ => None,
+        };
+
+        match markup_len {
+            Some(len) => {
+                if text_start < i {
+                    chunks.push(Chunk::Text(&line[text_start..i]));
+                }
+                chunks.push(Chunk::Markup(&line[i..i + len]));
+                i += len;
+                text_start = i;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if text_start < line.len() {
+        chunks.push(Chunk::Text(&line[text_start..]));
+    }
+
+    chunks
+}
+
+/// The text of a line without its markup; ASS line breaks become spaces
+fn plain_text(line: &str, format: SubtitleFormat) -> String {
+    split_markup(line, format)
+        .into_iter()
+        .map(|chunk| match chunk {
+            Chunk::Text(text) => text,
+            Chunk::Markup(markup) if markup.starts_with('\\') => " ",
+            Chunk::Markup(_) => "",
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Annotate with a stand-in annotator that reads every text as `(text)`,
+    /// returning the output and the texts it was given
+    fn run(input: &str, format: SubtitleFormat, mode: SubtitleMode) -> (String, Vec<String>) {
+        let mut seen = Vec::new();
+        let output = annotate(input, format, mode, |text| {
+            seen.push(text.to_string());
+            Ok(Annotation {
+                lines: vec![format!("({})", text)],
+                ruby: format!("<ruby>{}<rt>r</rt></ruby>", text),
+            })
+        })
+        .unwrap();
+        (output, seen)
+    }
+
+    #[test]
+    fn srt_keeps_numbers_timings_and_tags() {
+        let input = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>你好</i>\r\n- 世界\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n再见\r\n";
+        let (output, seen) = run(input, SubtitleFormat::Srt, SubtitleMode::Line);
+
+        assert_eq!(
+            output,
+            "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>你好</i>\r\n(你好)\r\n- 世界\r\n(- 世界)\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n再见\r\n(再见)\r\n"
+        );
+        assert_eq!(seen, ["你好", "- 世界", "再见"]);
+    }
+
+    #[test]
+    fn webvtt_skips_header_notes_and_styles() {
+        let input = "WEBVTT\n\nNOTE 中文注释\n\nSTYLE\n::cue { color: white; }\n\nintro\n00:01.000 --> 00:02.000 line:90%\n<v 小明>你好\n";
+        let (output, seen) = run(input, SubtitleFormat::WebVtt, SubtitleMode::Line);
+        assert_eq!(
+            output,
+            "WEBVTT\n\nNOTE 中文注释\n\nSTYLE\n::cue { color: white; }\n\nintro\n00:01.000 --> 00:02.000 line:90%\n<v 小明>你好\n<c.reading>(你好)</c>\n"
+        );
+        assert_eq!(seen, ["你好"]);
+
+        let (output, seen) = run(input, SubtitleFormat::WebVtt, SubtitleMode::Ruby);
+        assert!(output
+            .ends_with("00:01.000 --> 00:02.000 line:90%\n<v 小明><ruby>你好<rt>r</rt></ruby>\n"));
+        assert_eq!(seen, ["你好"]);
+    }
+
+    const ASS: &str = "[Script Info]\nTitle: 测试\n\n[V4+ Styles]\nFormat: Name, Fontname, Fontsize, Alignment\nStyle: Default,Arial,48,2\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nComment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,注释\nDialogue: 0,0:00:01.00,0:00:02.50,Default,,0,0,0,,{\\i1}你好，\\N世界{\\i0}\n";
+
+    #[test]
+    fn ass_keeps_overrides_and_adds_a_reading_line() {
+        let (output, seen) = run(ASS, SubtitleFormat::Ass, SubtitleMode::Line);
+        let (head, last) = output.trim_end().rsplit_once('\n').unwrap();
+
+        assert_eq!(head, ASS.trim_end().rsplit_once('\n').unwrap().0);
+        assert_eq!(
+            last,
+            "Dialogue: 0,0:00:01.00,0:00:02.50,Default,,0,0,0,,{\\i1}你好，\\N世界{\\i0}\\N{\\fscx75\\fscy75}(你好， 世界)"
+        );
+        assert_eq!(seen, ["你好， 世界"]);
+    }
+
+    #[test]
+    fn ass_track_adds_a_style_and_an_event() {
+        let (output, _) = run(ASS, SubtitleFormat::Ass, SubtitleMode::Track);
+        assert!(output.contains("Style: Default,Arial,48,2\nStyle: Reading,Arial,32,8\n"));
+        assert!(output.contains(
+            "Dialogue: 0,0:00:01.00,0:00:02.50,Default,,0,0,0,,{\\i1}你好，\\N世界{\\i0}\nDialogue: 0,0:00:01.00,0:00:02.50,Reading,,0,0,0,,(你好， 世界)\n"
+        ));
+    }
+
+    #[test]
+    fn rejects_modes_a_format_cannot_show() {
+        let fail = |format, mode| annotate("", format, mode, |_| Ok(Annotation::default()));
+        assert!(fail(SubtitleFormat::Srt, SubtitleMode::Ruby).is_err());
+        assert!(fail(SubtitleFormat::WebVtt, SubtitleMode::Track).is_err());
+        assert!(fail(SubtitleFormat::Ass, SubtitleMode::Ruby).is_err());
+        assert_eq!(
+            SubtitleFormat::from_path("movie.ZH.ssa"),
+            Some(SubtitleFormat::Ass)
+        );
+    }
+}